@@ -1,5 +1,84 @@
+use std::cell::RefCell;
+use std::io::Write;
 pub use tracing::debug;
 pub use tracing::error;
 pub use tracing::info;
 pub use tracing::trace;
 pub use tracing::warn;
+
+type DeferredOutput = Box<dyn FnOnce() + Send>;
+
+thread_local! {
+    static OUTPUT_BUFFER: RefCell<Option<Vec<DeferredOutput>>> = const { RefCell::new(None) };
+}
+
+/// Output written on a thread while it was buffering, see [`buffer_output`]
+#[derive(Default)]
+#[must_use]
+pub struct BufferedOutput(Vec<DeferredOutput>);
+
+impl BufferedOutput {
+    /// Writes the buffered output in the order it was produced
+    pub fn flush(self) {
+        for write in self.0 {
+            write();
+        }
+    }
+}
+
+/// Runs `f`, buffering the output it writes with [`write_output`] on the current thread
+///
+/// Used for tasks running in parallel, so their output can be written one task after another in
+/// a deterministic order
+pub fn buffer_output<R>(f: impl FnOnce() -> R) -> (R, BufferedOutput) {
+    let previous = OUTPUT_BUFFER.replace(Some(vec![]));
+    let result = f();
+    let buffered = OUTPUT_BUFFER.replace(previous).unwrap_or_default();
+    (result, BufferedOutput(buffered))
+}
+
+/// Whether output written on the current thread is buffered
+pub fn is_output_buffered() -> bool {
+    OUTPUT_BUFFER.with_borrow(Option::is_some)
+}
+
+/// Runs `f` writing its output immediately, even if the current thread is buffering, e.g. for
+/// progress updates that are useless once they are outdated
+pub fn unbuffered<R>(f: impl FnOnce() -> R) -> R {
+    let previous = OUTPUT_BUFFER.take();
+    let result = f();
+    OUTPUT_BUFFER.set(previous);
+    result
+}
+
+/// Writes output immediately, or once the buffered output is flushed if the current thread is
+/// buffering
+pub fn write_output(write: impl FnOnce() + Send + 'static) {
+    let deferred = OUTPUT_BUFFER.with_borrow_mut(|buffer| match buffer {
+        Some(buffer) => {
+            buffer.push(Box::new(write));
+            None
+        }
+        None => Some(write),
+    });
+    if let Some(write) = deferred {
+        write();
+    }
+}
+
+/// Writer for log lines going to stdout, honouring output buffering
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let bytes = buf.to_vec();
+        write_output(move || {
+            let _ = std::io::stdout().lock().write_all(&bytes);
+        });
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stdout().flush()
+    }
+}
@@ -1,5 +1,6 @@
 use std::env::current_dir;
 use std::path::PathBuf;
+use tool_tool_base::logging::LogWriter;
 use tool_tool_base::result::{Context, ToolToolResult, bail};
 use tool_tool_logic::configuration::CONFIGURATION_FILE_NAME;
 use tracing::info;
@@ -14,6 +15,7 @@ pub fn run_cli() -> ToolToolResult<()> {
 
     let fmt_layer = tracing_subscriber::fmt::layer()
         .with_target(false)
+        .with_writer(|| LogWriter)
         .with_span_events(FmtSpan::ENTER)
         .with_filter(tracing_subscriber::filter::LevelFilter::INFO);
 
@@ -1,8 +1,8 @@
-use crate::configuration::platform::DownloadPlatform;
+use crate::configuration::platform::{Architecture, DownloadPlatform, Libc};
 use crate::types::{Env, FilePath};
 use std::fmt::Debug;
 use std::io::{Read, Seek, Write};
-use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
 use tool_tool_base::result::ToolToolResult;
 
@@ -10,7 +10,7 @@ pub trait ReadSeek: Read + Seek + 'static {}
 
 impl<T: Read + Seek + 'static> ReadSeek for T {}
 
-pub trait Adapter: Debug + 'static {
+pub trait Adapter: Debug + Send + Sync + 'static {
     /**
        Get the command line arguments, the first one is the path to the binary
     */
@@ -59,15 +59,42 @@ pub trait Adapter: Debug + 'static {
     fn exit(&self, exit_code: i32);
 
     /**
-        Download a file from a url
+        Download a file from a url with additional request headers, calling `progress` whenever
+        data was received
     */
-    fn download_file(&self, url: &str, destination_path: &FilePath) -> ToolToolResult<()>;
+    fn download_file(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        destination_path: &FilePath,
+        progress: &mut dyn FnMut(&DownloadProgress),
+    ) -> ToolToolResult<()>;
+
+    /**
+    Check if stderr is an interactive terminal, so progress can be rendered in place
+    */
+    fn is_terminal(&self) -> bool;
+
+    /**
+    Replace the progress line on stderr, an empty line clears it
+    */
+    fn print_progress(&self, line: &str);
 
     /**
         Get the currently running platform
     */
     fn get_platform(&self) -> DownloadPlatform;
 
+    /**
+        Get the CPU architecture of the currently running platform
+    */
+    fn get_architecture(&self) -> Architecture;
+
+    /**
+        Get the C library flavour (glibc or musl) of the currently running platform, None if not on linux
+    */
+    fn get_libc(&self) -> Option<Libc>;
+
     /**
     Execute the given binary with the given arguments
     */
@@ -85,6 +112,12 @@ pub trait Adapter: Debug + 'static {
     */
     fn now(&self) -> ToolToolResult<Duration>;
 
+    /**
+    Get the wall clock time since the unix epoch, on the same scale as the modification times
+    returned by `file_metadata`
+    */
+    fn system_time(&self) -> ToolToolResult<Duration>;
+
     /**
     Try to acquire an exclusive lock on the lockfile
     */
@@ -109,9 +142,75 @@ pub trait Adapter: Debug + 'static {
     Make a file executable
     */
     fn make_file_executable(&self, path: &FilePath) -> ToolToolResult<()>;
+
+    /**
+    Create a symbolic link at the given path, the target is relative to the directory containing the link
+    */
+    fn create_symlink(&self, path: &FilePath, target: &str) -> ToolToolResult<()>;
+
+    /**
+    Set the unix permission bits of a file, this is a no-op on platforms without unix permissions
+    */
+    fn set_file_mode(&self, path: &FilePath, mode: u32) -> ToolToolResult<()>;
+
+    /**
+    List the names of the files and directories directly contained in a directory
+    A missing directory results in an empty list
+    */
+    fn list_directory(&self, path: &FilePath) -> ToolToolResult<Vec<String>>;
+
+    /**
+    Get the size and modification time of a file or directory
+    */
+    fn file_metadata(&self, path: &FilePath) -> ToolToolResult<FileMetadata>;
+
+    /**
+    Create a hard link to a file, falling back to a copy (which may be a reflink) if the file
+    cannot be linked, e.g. because it is on a different file system
+    The destination must not exist
+    */
+    fn link_file(&self, from: &FilePath, to: &FilePath) -> ToolToolResult<()>;
+
+    /**
+    Delete a single file
+    */
+    fn delete_file(&self, path: &FilePath) -> ToolToolResult<()>;
+
+    /**
+    Rename a file or directory in a single atomic step
+    The destination must not exist and has to be on the same file system as the source, an
+    existing destination has to be moved out of the way first
+    */
+    fn rename(&self, from: &FilePath, to: &FilePath) -> ToolToolResult<()>;
+
+    /**
+    List all regular files below a directory recursively, the returned paths are relative to the directory
+    Symbolic links are neither listed nor followed, a missing directory results in an empty list
+    */
+    fn list_files(&self, path: &FilePath) -> ToolToolResult<Vec<FilePath>>;
 }
 
-pub type AdapterBox = Rc<dyn Adapter>;
+pub type AdapterBox = Arc<dyn Adapter>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileMetadata {
+    pub is_directory: bool,
+    /// Size in bytes, zero for directories
+    pub size: u64,
+    /// Time of the last modification since the unix epoch
+    pub modified: Duration,
+}
+
+/// Progress of a running download
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadProgress {
+    /// Bytes received so far, including bytes of resumed partial downloads
+    pub downloaded: u64,
+    /// Total size in bytes, if reported by the server
+    pub total: Option<u64>,
+    /// Time since the download started
+    pub elapsed: Duration,
+}
 
 #[derive(Debug)]
 pub struct ExecutionRequest {
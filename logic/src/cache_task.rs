@@ -0,0 +1,278 @@
+use crate::adapter::Adapter;
+use crate::configuration::ToolToolConfiguration;
+use crate::configuration::platform::HostPlatform;
+use crate::download_task::CHECKSUM_MARKER_FILE_NAME;
+use crate::types::FilePath;
+use crate::workspace::Workspace;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use tool_tool_base::result::ToolToolResult;
+use tracing::info;
+
+/// File in the tool directory, rewritten whenever a command of the tool is run
+pub(crate) const LAST_USED_FILE_NAME: &str = ".tool-tool.last-used";
+
+/// Installed tool directory in the project cache
+struct CacheEntry {
+    name: String,
+    tool_name: String,
+    path: FilePath,
+    size: u64,
+    last_used: Duration,
+    referenced: bool,
+}
+
+/// Prints every cached tool with its size, last use and whether the configuration references it
+pub fn run_cache_status_task(workspace: &Workspace) -> ToolToolResult<()> {
+    let adapter = workspace.adapter();
+    let entries = collect_cache_entries(workspace)?;
+    let temp_size = directory_size(adapter, &workspace.temp_dir())?;
+    let mut message = format!("Cache directory '{}':\n", workspace.cache_dir());
+    if entries.is_empty() {
+        message.push_str("\tNo cached tools\n");
+    }
+    let width = entries
+        .iter()
+        .map(|entry| entry.name.len())
+        .max()
+        .unwrap_or_default();
+    for entry in &entries {
+        message.push_str(&format!(
+            "\t{:width$}  {:>10}  last used {}  {}\n",
+            entry.name,
+            format_size(entry.size),
+            format_timestamp(entry.last_used),
+            if entry.referenced {
+                "referenced"
+            } else {
+                "unreferenced"
+            }
+        ));
+    }
+    if temp_size > 0 {
+        message.push_str(&format!("Temporary files: {}\n", format_size(temp_size)));
+    }
+    let total_size = entries.iter().map(|entry| entry.size).sum::<u64>() + temp_size;
+    message.push_str(&format!("Total: {}\n", format_size(total_size)));
+    adapter.print(&message);
+    Ok(())
+}
+
+/// Removes cached tools not referenced by the configuration and stale temporary directories
+///
+/// The `keep_last` most recently used unreferenced versions of each tool are kept, so switching
+/// between branches does not require downloading them again
+pub fn run_gc_task(workspace: &Workspace, keep_last: usize, dry_run: bool) -> ToolToolResult<()> {
+    let adapter = workspace.adapter();
+    let cache_dir = workspace.cache_dir();
+    let mut unreferenced: BTreeMap<String, Vec<CacheEntry>> = BTreeMap::new();
+    for entry in collect_cache_entries(workspace)? {
+        if !entry.referenced {
+            unreferenced
+                .entry(entry.tool_name.clone())
+                .or_default()
+                .push(entry);
+        }
+    }
+    let mut removals: Vec<(String, FilePath, u64)> = vec![];
+    for mut entries in unreferenced.into_values() {
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+        for entry in entries.into_iter().skip(keep_last) {
+            removals.push((entry.name, entry.path, entry.size));
+        }
+    }
+    removals.sort_by(|a, b| a.0.cmp(&b.0));
+    let temp_dir = workspace.temp_dir();
+    if adapter.file_exists(&temp_dir)? {
+        let size = directory_size(adapter, &temp_dir)?;
+        removals.push(("tmp".to_string(), temp_dir, size));
+    }
+    if removals.is_empty() {
+        adapter.print(&format!("No unused cache entries in '{cache_dir}'\n"));
+        return Ok(());
+    }
+    let total_size: u64 = removals.iter().map(|(_, _, size)| size).sum();
+    let names: Vec<String> = removals
+        .iter()
+        .map(|(name, _, size)| format!("{name} ({})", format_size(*size)))
+        .collect();
+    let action = if dry_run { "Would remove" } else { "Removed" };
+    let plural = if removals.len() == 1 {
+        "entry"
+    } else {
+        "entries"
+    };
+    if !dry_run {
+        for (name, path, _) in &removals {
+            info!("Removing cache entry {name}");
+            adapter.delete_directory_all(path)?;
+        }
+    }
+    adapter.print(&format!(
+        "{action} {} cache {plural} ({}) from '{cache_dir}':\n\t{}\n",
+        removals.len(),
+        format_size(total_size),
+        names.join("\n\t")
+    ));
+    Ok(())
+}
+
+fn collect_cache_entries(workspace: &Workspace) -> ToolToolResult<Vec<CacheEntry>> {
+    let adapter = workspace.adapter();
+    let cache_dir = workspace.cache_dir();
+    let temp_dir = workspace.temp_dir();
+    let referenced_paths: BTreeSet<FilePath> = workspace
+        .config()
+        .tools
+        .iter()
+        .map(|tool| workspace.tool_dir(tool))
+        .collect();
+    let mut entries = vec![];
+    for name in adapter.list_directory(&cache_dir)? {
+        let path = cache_dir.join(&name);
+        let metadata = adapter.file_metadata(&path)?;
+        if !metadata.is_directory || path == temp_dir {
+            continue;
+        }
+        let mut last_used = metadata.modified;
+        for file_name in [LAST_USED_FILE_NAME, CHECKSUM_MARKER_FILE_NAME] {
+            let file_path = path.join(file_name);
+            if adapter.file_exists(&file_path)? {
+                last_used = adapter.file_metadata(&file_path)?.modified;
+                break;
+            }
+        }
+        entries.push(CacheEntry {
+            tool_name: tool_name_of(&name, workspace.config()),
+            size: directory_size(adapter, &path)?,
+            referenced: referenced_paths.contains(&path),
+            name,
+            path,
+            last_used,
+        });
+    }
+    Ok(entries)
+}
+
+/// Determines the tool of a `<name>-<version>-<platform>` cache directory, preferring the names
+/// of configured tools since both names and versions may contain dashes
+fn tool_name_of(dir_name: &str, config: &ToolToolConfiguration) -> String {
+    let configured_name = config
+        .tools
+        .iter()
+        .map(|tool| tool.name.as_str())
+        .filter(|name| dir_name.starts_with(&format!("{name}-")))
+        .max_by_key(|name| name.len());
+    if let Some(name) = configured_name {
+        return name.to_string();
+    }
+    let name_and_version = HostPlatform::all()
+        .iter()
+        .find_map(|platform| dir_name.strip_suffix(&format!("-{platform}")))
+        .unwrap_or(dir_name);
+    name_and_version
+        .rsplit_once('-')
+        .map_or(name_and_version, |(name, _)| name)
+        .to_string()
+}
+
+fn directory_size(adapter: &dyn Adapter, path: &FilePath) -> ToolToolResult<u64> {
+    let mut size = 0;
+    for file in adapter.list_files(path)? {
+        size += adapter.file_metadata(&path.join(file))?.size;
+    }
+    Ok(size)
+}
+
+pub(crate) fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if size < 1024 {
+        return format!("{size} B");
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Formats a timestamp since the unix epoch as UTC date and time
+fn format_timestamp(timestamp: Duration) -> String {
+    let seconds = timestamp.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    let seconds_of_day = seconds % 86400;
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60
+    )
+}
+
+/// Converts days since the unix epoch to a (year, month, day) date in the proleptic gregorian
+/// calendar, see <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_adapter::MockAdapter;
+    use crate::runner_initial::load_config;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(50 * 1024 * 1024), "50.0 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(Duration::ZERO), "1970-01-01 00:00 UTC");
+        assert_eq!(
+            format_timestamp(Duration::from_secs(951_782_400 + 3_661)),
+            "2000-02-29 01:01 UTC"
+        );
+        assert_eq!(
+            format_timestamp(Duration::from_secs(1_791_504_000)),
+            "2026-10-09 00:00 UTC"
+        );
+    }
+
+    #[test]
+    fn test_tool_name_of() -> ToolToolResult<()> {
+        let adapter = MockAdapter::new();
+        adapter.try_lock()?;
+        let config = load_config(&adapter)?;
+        assert_eq!(tool_name_of("lsd-1.0.0-linux-x86_64", &config), "lsd");
+        assert_eq!(
+            tool_name_of("node-22.0.0-linux-x86_64-musl", &config),
+            "node"
+        );
+        assert_eq!(
+            tool_name_of("foo-bar-2.0-windows-x86_64", &config),
+            "foo-bar"
+        );
+        assert_eq!(tool_name_of("unknown", &config), "unknown");
+        Ok(())
+    }
+}
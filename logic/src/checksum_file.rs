@@ -0,0 +1,178 @@
+use crate::adapter::Adapter;
+use crate::configuration::DownloadArtifact;
+use crate::download_task::download_from_mirrors;
+use crate::file_type::get_filename_from_url;
+use crate::hash::ChecksumAlgorithm;
+use crate::redact::redact_url;
+use crate::types::FilePath;
+use crate::workspace::Workspace;
+use tool_tool_base::result::{ToolToolResult, bail, err};
+use tracing::info;
+
+/// Checksum of an artifact as published upstream
+#[derive(Debug)]
+pub(crate) struct UpstreamChecksum {
+    pub algorithm: ChecksumAlgorithm,
+    pub checksum: String,
+    /// Url of the checksum file with credentials redacted, for error messages
+    pub checksum_url: String,
+}
+
+impl UpstreamChecksum {
+    /// Downloads the checksum file of the artifact to the given path and extracts its checksum
+    pub(crate) fn fetch(
+        workspace: &Workspace,
+        artifact: &DownloadArtifact,
+        headers: &[(String, String)],
+        checksum_url: &str,
+        checksum_path: &FilePath,
+    ) -> ToolToolResult<Self> {
+        info!(
+            "Downloading checksum file {} for {}",
+            redact_url(checksum_url),
+            redact_url(&artifact.url)
+        );
+        download_from_mirrors(workspace, checksum_url, headers, checksum_path)?;
+        let content = std::io::read_to_string(workspace.adapter().read_file(checksum_path)?)?;
+        let file_name = get_filename_from_url(&artifact.url).unwrap_or_default();
+        let checksum_url = redact_url(checksum_url);
+        let checksum = find_checksum(&content, file_name).ok_or_else(|| {
+            err!("Checksum file '{checksum_url}' does not contain a checksum for '{file_name}'")
+        })?;
+        let guessed_algorithm = ChecksumAlgorithm::from_checksum(&checksum);
+        let algorithm = match (artifact.checksum_format, guessed_algorithm) {
+            (Some(algorithm), Some(guessed)) if algorithm == guessed => algorithm,
+            (Some(algorithm), _) => bail!(
+                "Checksum '{checksum}' for '{file_name}' in '{checksum_url}' is not a valid {algorithm} checksum"
+            ),
+            (None, Some(guessed)) => guessed,
+            (None, None) => bail!(
+                "Could not determine the algorithm of checksum '{checksum}' for '{file_name}' in '{checksum_url}'"
+            ),
+        };
+        Ok(Self {
+            algorithm,
+            checksum,
+            checksum_url,
+        })
+    }
+
+    /// Verifies a downloaded file against the upstream checksum
+    pub(crate) fn verify(
+        &self,
+        adapter: &dyn Adapter,
+        tool_name: &str,
+        path: &FilePath,
+    ) -> ToolToolResult<()> {
+        let actual = self.algorithm.compute(adapter.read_file(path)?)?;
+        if actual != self.checksum {
+            bail!(
+                "Upstream {} checksum mismatch for tool '{tool_name}' (from '{}')\nExpected: {}\nActual:   {}",
+                self.algorithm,
+                self.checksum_url,
+                self.checksum,
+                actual
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Finds the checksum for the given file name in an upstream checksum file
+///
+/// Supports the GNU coreutils format (`<hash>  <file>`, `<hash> *<file>`), the BSD format
+/// (`SHA512 (<file>) = <hash>`) and files containing just a single hash
+pub(crate) fn find_checksum(content: &str, file_name: &str) -> Option<String> {
+    let lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let mut single_hash = None;
+    let mut line_count = 0;
+    for line in lines {
+        line_count += 1;
+        let (hash, name) = parse_line(line);
+        match name {
+            Some(name) if name == file_name => return Some(hash.to_ascii_lowercase()),
+            Some(_) => {}
+            None => single_hash = Some(hash),
+        }
+    }
+    single_hash
+        .filter(|_| line_count == 1)
+        .map(str::to_ascii_lowercase)
+}
+
+/// Splits a checksum line into the hash and the file name (if any)
+fn parse_line(line: &str) -> (&str, Option<&str>) {
+    // BSD format: SHA512 (file) = hash
+    if let Some((prefix, hash)) = line.rsplit_once(") = ")
+        && let Some((_, name)) = prefix.split_once(" (")
+    {
+        return (hash.trim(), Some(name));
+    }
+    match line.split_once(char::is_whitespace) {
+        Some((hash, name)) => {
+            let name = name.trim_start();
+            let name = name.strip_prefix('*').unwrap_or(name);
+            let name = name.strip_prefix("./").unwrap_or(name);
+            (hash, Some(name))
+        }
+        None => (line, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_checksum;
+
+    #[test]
+    fn gnu_format() {
+        let content = "abc123  node-v22.0.0-linux-x64.tar.xz\nDEF456 *node-v22.0.0-win-x64.zip\n";
+        assert_eq!(
+            find_checksum(content, "node-v22.0.0-linux-x64.tar.xz"),
+            Some("abc123".to_string())
+        );
+        assert_eq!(
+            find_checksum(content, "node-v22.0.0-win-x64.zip"),
+            Some("def456".to_string())
+        );
+        assert_eq!(find_checksum(content, "node.tar.gz"), None);
+    }
+
+    #[test]
+    fn relative_paths() {
+        let content = "abc123  ./dist/tool.tar.gz\nfff000  ./tool.tar.gz\n";
+        assert_eq!(
+            find_checksum(content, "tool.tar.gz"),
+            Some("fff000".to_string())
+        );
+    }
+
+    #[test]
+    fn bsd_format() {
+        let content = "SHA512 (tool-1.0.tar.gz) = abc123\nSHA512 (tool-1.0.zip) = def456";
+        assert_eq!(
+            find_checksum(content, "tool-1.0.zip"),
+            Some("def456".to_string())
+        );
+    }
+
+    #[test]
+    fn single_hash() {
+        assert_eq!(
+            find_checksum("ABC123\n", "anything.tar.gz"),
+            Some("abc123".to_string())
+        );
+        assert_eq!(find_checksum("abc123\ndef456\n", "anything.tar.gz"), None);
+    }
+
+    #[test]
+    fn ignores_comments_and_blank_lines() {
+        let content = "# checksums for tool\n\nabc123  tool.tar.gz\n";
+        assert_eq!(
+            find_checksum(content, "tool.tar.gz"),
+            Some("abc123".to_string())
+        );
+    }
+}
@@ -1,66 +1,270 @@
-use crate::configuration;
+use crate::adapter::Adapter;
+use crate::configuration::expand_config::expand_download_urls_of_all_hosts;
+use crate::configuration::{DownloadArtifact, ToolToolConfiguration};
+use crate::hash::ChecksumAlgorithm;
 use crate::workspace::Workspace;
 use kdl::{KdlDocument, KdlNode};
-use std::collections::BTreeMap;
-use tool_tool_base::result::{Context, ToolToolResult, bail, err};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use tool_tool_base::result::{Context, HelpError, ToolToolError, ToolToolResult, bail, err};
 use tracing::info;
 
-#[derive(Debug, Default, Clone)]
+/// Name of the block holding checksums tagged with their algorithm (`"url" "hash" algorithm="sha256"`)
+const TAGGED_CHECKSUMS_NODE: &str = "checksums";
+
+/// Checksums of download artifacts, keyed by url and algorithm
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Checksums {
-    pub(crate) sha512sums: BTreeMap<String, String>,
+    pub(crate) entries: BTreeMap<String, BTreeMap<ChecksumAlgorithm, String>>,
+}
+
+impl Checksums {
+    pub fn get(&self, url: &str, algorithm: ChecksumAlgorithm) -> Option<&str> {
+        self.entries.get(url)?.get(&algorithm).map(String::as_str)
+    }
+
+    /// All checksums recorded for the url
+    pub fn get_all(&self, url: &str) -> impl Iterator<Item = (ChecksumAlgorithm, &str)> {
+        self.entries
+            .get(url)
+            .into_iter()
+            .flatten()
+            .map(|(algorithm, checksum)| (*algorithm, checksum.as_str()))
+    }
+
+    /// The checksums an artifact is verified against
+    ///
+    /// Checksums pinned in the configuration are authoritative, the checksums file is only
+    /// consulted if there are none
+    pub fn expected<'a>(
+        &'a self,
+        artifact: &'a DownloadArtifact,
+    ) -> Vec<(ChecksumAlgorithm, &'a str)> {
+        if artifact.checksums.is_empty() {
+            self.get_all(&artifact.url).collect()
+        } else {
+            artifact
+                .checksums
+                .iter()
+                .map(|(algorithm, checksum)| (*algorithm, checksum.as_str()))
+                .collect()
+        }
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.entries.get(url).is_some_and(|sums| !sums.is_empty())
+    }
+
+    /// The checksum of the strongest algorithm recorded for the url
+    pub fn strongest(&self, url: &str) -> Option<(ChecksumAlgorithm, &str)> {
+        self.get_all(url).last()
+    }
+
+    /// Urls with recorded checksums that are not contained in the given set of urls
+    pub fn stale_urls(&self, configured_urls: &BTreeSet<String>) -> Vec<String> {
+        self.entries
+            .keys()
+            .filter(|url| !configured_urls.contains(*url))
+            .cloned()
+            .collect()
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        self.entries.remove(url);
+    }
+
+    pub fn insert(
+        &mut self,
+        url: impl Into<String>,
+        algorithm: ChecksumAlgorithm,
+        checksum: String,
+    ) {
+        self.entries
+            .entry(url.into())
+            .or_default()
+            .insert(algorithm, checksum);
+    }
+
+    /// Adds all checksums of `other`, replacing existing entries of the same url and algorithm
+    pub fn extend(&mut self, other: Checksums) {
+        for (url, checksums) in other.entries {
+            self.entries.entry(url).or_default().extend(checksums);
+        }
+    }
+}
+
+/// Download urls of all tools on every platform
+///
+/// Computed from the unexpanded urls of the configuration, since the expanded urls only cover the
+/// host platform
+pub fn configured_urls(
+    config: &ToolToolConfiguration,
+    adapter: &dyn Adapter,
+) -> ToolToolResult<BTreeSet<String>> {
+    let mut urls = BTreeSet::new();
+    for tool in &config.tools {
+        urls.extend(expand_download_urls_of_all_hosts(config, tool, adapter)?);
+        urls.extend(
+            tool.download_urls
+                .values()
+                .chain(tool.default_download_artifact.iter())
+                .map(|artifact| artifact.url.clone()),
+        );
+    }
+    Ok(urls)
+}
+
+/// Removes the checksums of urls that no longer appear in the configuration
+pub fn run_prune_checksums_task(workspace: &mut Workspace, dry_run: bool) -> ToolToolResult<()> {
+    let checksums_path = workspace.checksums_path();
+    let stale_urls = workspace
+        .checksums
+        .stale_urls(&configured_urls(workspace.config(), workspace.adapter())?);
+    let adapter = workspace.adapter();
+    if stale_urls.is_empty() {
+        adapter.print(&format!("No stale checksums in '{checksums_path}'\n"));
+        return Ok(());
+    }
+    let listing = format!(
+        "{} stale checksums from '{checksums_path}':\n\t{}\n",
+        stale_urls.len(),
+        stale_urls.join("\n\t")
+    );
+    if dry_run {
+        adapter.print(&format!("Would remove {listing}"));
+        return Ok(());
+    }
+    if workspace.options().frozen {
+        bail!("Checksums cannot be pruned in frozen mode");
+    }
+    for url in &stale_urls {
+        workspace.checksums.remove(url);
+    }
+    save_checksums(workspace)?;
+    workspace.adapter().print(&format!("Removed {listing}"));
+    Ok(())
 }
 
 pub fn load_checksums(workspace: &mut Workspace) -> ToolToolResult<()> {
-    let checksums_filename = workspace
-        .tool_tool_dir()
-        .join(configuration::CHECKSUM_FILE_NAME);
-    let mut sha512sums = BTreeMap::new();
+    let checksums_filename = workspace.checksums_path();
+    let mut checksums = Checksums::default();
     if let Ok(checksum_file) = workspace.adapter().read_file(&checksums_filename) {
         let checksum_kdl = std::io::read_to_string(checksum_file)?;
         let result = checksum_kdl
             .parse::<KdlDocument>()
             .with_context(|| format!("Could not parse '{checksums_filename}'"))?;
         for node in result.nodes() {
-            match node.name().value() {
-                "sha512sums" => {
-                    for child in node.children().iter().flat_map(|entry| entry.nodes()) {
-                        let url = child.name().value().to_string();
-                        let checksum = child
-                            .get(0)
-                            .ok_or_else(|| err!("expected checksum"))?
-                            .as_string()
-                            .ok_or_else(|| err!("expected checksum to be a string"))?
-                            .to_string();
-                        sha512sums.insert(url, checksum);
-                    }
-                }
+            let node_name = node.name().value();
+            let block_algorithm = match node_name {
+                TAGGED_CHECKSUMS_NODE => None,
                 other => {
-                    bail!("Unknown node '{other}' in checksums file '{checksums_filename}'");
+                    let algorithm = other
+                        .strip_suffix("sums")
+                        .and_then(|algorithm| ChecksumAlgorithm::from_str(algorithm).ok());
+                    let Some(algorithm) = algorithm else {
+                        bail!("Unknown node '{other}' in checksums file '{checksums_filename}'");
+                    };
+                    Some(algorithm)
                 }
+            };
+            for child in node.children().iter().flat_map(|entry| entry.nodes()) {
+                let url = child.name().value().to_string();
+                let checksum = child
+                    .get(0)
+                    .ok_or_else(|| err!("expected checksum"))?
+                    .as_string()
+                    .ok_or_else(|| err!("expected checksum to be a string"))?
+                    .to_string();
+                let algorithm = match block_algorithm {
+                    Some(algorithm) => algorithm,
+                    None => tagged_algorithm(child, &checksum)
+                        .with_context(|| format!("Invalid checksum for '{url}'"))?,
+                };
+                checksums.insert(url, algorithm, checksum);
             }
         }
     } else {
         info!("Checksums file '{checksums_filename}' creating a new one");
     }
 
-    workspace.checksums = Checksums { sha512sums };
+    workspace.checksums = checksums;
     Ok(())
 }
 
+/// Algorithm of an entry in the tagged checksums block, guessed from the checksum if not given
+fn tagged_algorithm(node: &KdlNode, checksum: &str) -> ToolToolResult<ChecksumAlgorithm> {
+    match node.get("algorithm") {
+        Some(algorithm) => algorithm
+            .as_string()
+            .ok_or_else(|| err!("expected algorithm to be a string"))?
+            .parse(),
+        None => ChecksumAlgorithm::from_checksum(checksum)
+            .ok_or_else(|| err!("Could not determine the algorithm of checksum '{checksum}'")),
+    }
+}
+
+/// Fails if a checksum pinned in the configuration differs from the one in the checksums file
+pub fn verify_inline_checksums(workspace: &Workspace) -> ToolToolResult<()> {
+    let mut conflicts = vec![];
+    for tool in &workspace.config().tools {
+        let artifacts = tool
+            .download_urls
+            .values()
+            .chain(tool.default_download_artifact.iter());
+        for artifact in artifacts {
+            for (algorithm, inline_checksum) in &artifact.checksums {
+                if let Some(checksum) = workspace.checksums.get(&artifact.url, *algorithm)
+                    && !checksum.eq_ignore_ascii_case(inline_checksum)
+                {
+                    conflicts.push(format!(
+                        "{} ({algorithm}):\n\t\tconfiguration:  {inline_checksum}\n\t\tchecksums file: {checksum}",
+                        artifact.url
+                    ));
+                }
+            }
+        }
+    }
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+    Err(ToolToolError::from(HelpError::new(
+        format!(
+            "Checksums in the configuration conflict with '{}':\n\t{}",
+            workspace.checksums_path(),
+            conflicts.join("\n\t")
+        ),
+        format!(
+            "The checksums in the configuration take precedence, update or remove the conflicting entries in '{}'",
+            workspace.checksums_path()
+        ),
+    )))
+}
+
+/// Writes the checksums file, leaving out the checksums of urls no longer in the configuration
 pub fn save_checksums(workspace: &Workspace) -> ToolToolResult<()> {
-    let checksums_filename = workspace
-        .tool_tool_dir()
-        .join(configuration::CHECKSUM_FILE_NAME);
+    let checksums_filename = workspace.checksums_path();
+    let mut checksums = workspace.checksums.clone();
+    for url in checksums.stale_urls(&configured_urls(workspace.config(), workspace.adapter())?) {
+        info!("Pruning stale checksum for '{url}'");
+        checksums.remove(&url);
+    }
     let mut document = KdlDocument::new();
-    let mut children = KdlDocument::new();
-    for (url, checksum) in workspace.checksums.sha512sums.iter() {
-        let mut entry = KdlNode::new(url.as_str());
-        entry.insert(0, checksum.clone());
-        children.nodes_mut().push(entry);
-    }
-    let mut sums_node = KdlNode::new("sha512sums");
-    sums_node.set_children(children);
-    document.nodes_mut().push(sums_node);
+    for algorithm in ChecksumAlgorithm::VALUES {
+        let mut children = KdlDocument::new();
+        for (url, sums) in checksums.entries.iter() {
+            if let Some(checksum) = sums.get(&algorithm) {
+                let mut entry = KdlNode::new(url.as_str());
+                entry.insert(0, checksum.clone());
+                children.nodes_mut().push(entry);
+            }
+        }
+        if children.nodes().is_empty() {
+            continue;
+        }
+        let mut sums_node = KdlNode::new(format!("{algorithm}sums"));
+        sums_node.set_children(children);
+        document.nodes_mut().push(sums_node);
+    }
     let mut checksums_file = workspace.adapter().create_file(&checksums_filename)?;
     checksums_file.write_all(document.to_string().as_bytes())?;
     Ok(())
@@ -74,7 +278,7 @@ mod tests {
     use crate::mock_adapter::MockAdapter;
     use crate::runner_initial::load_config;
     use expect_test::expect;
-    use std::rc::Rc;
+    use std::sync::Arc;
 
     #[test]
     fn test_load_checksums_no_file() -> ToolToolResult<()> {
@@ -82,11 +286,11 @@ mod tests {
         adapter.try_lock()?;
         let config = load_config(&adapter)?;
 
-        let mut workspace = Workspace::new(config, Rc::new(adapter));
+        let mut workspace = Workspace::new(config, Arc::new(adapter));
         load_checksums(&mut workspace)?;
         expect![[r#"
             Checksums {
-                sha512sums: {},
+                entries: {},
             }
         "#]]
         .assert_debug_eq(&workspace.checksums);
@@ -108,16 +312,193 @@ mod tests {
 
         let config = load_config(&adapter)?;
 
-        let mut workspace = Workspace::new(config, Rc::new(adapter));
+        let mut workspace = Workspace::new(config, Arc::new(adapter));
         load_checksums(&mut workspace)?;
         expect![[r#"
             Checksums {
-                sha512sums: {
-                    "foo": "bar",
+                entries: {
+                    "foo": {
+                        Sha512: "bar",
+                    },
                 },
             }
         "#]]
         .assert_debug_eq(&workspace.checksums);
         Ok(())
     }
+
+    fn load_checksums_from(content: &str) -> ToolToolResult<Workspace> {
+        let adapter = MockAdapter::new();
+        adapter.try_lock()?;
+        adapter.set_file(
+            &format!("{TOOL_TOOL_DIRECTORY}/{CHECKSUM_FILE_NAME}"),
+            content,
+        );
+        let config = load_config(&adapter)?;
+        let mut workspace = Workspace::new(config, Arc::new(adapter));
+        load_checksums(&mut workspace)?;
+        Ok(workspace)
+    }
+
+    #[test]
+    fn test_load_multiple_algorithms() -> ToolToolResult<()> {
+        let workspace = load_checksums_from(
+            r#"
+            sha256sums {
+                "https://example.com/a.tar.gz" "aaaa"
+            }
+            sha512sums {
+                "https://example.com/a.tar.gz" "bbbb"
+            }
+            checksums {
+                "https://example.com/b.zip" "cccc" algorithm="sha256"
+                "https://example.com/c.zip" "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+            }
+        "#,
+        )?;
+        expect![[r#"
+            Checksums {
+                entries: {
+                    "https://example.com/a.tar.gz": {
+                        Sha256: "aaaa",
+                        Sha512: "bbbb",
+                    },
+                    "https://example.com/b.zip": {
+                        Sha256: "cccc",
+                    },
+                    "https://example.com/c.zip": {
+                        Sha256: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
+                    },
+                },
+            }
+        "#]]
+        .assert_debug_eq(&workspace.checksums);
+        let checksums = &workspace.checksums;
+        assert_eq!(
+            checksums.strongest("https://example.com/a.tar.gz"),
+            Some((ChecksumAlgorithm::Sha512, "bbbb"))
+        );
+        assert_eq!(
+            checksums.get("https://example.com/b.zip", ChecksumAlgorithm::Sha256),
+            Some("cccc")
+        );
+        assert!(!checksums.contains("https://example.com/d.zip"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_checksums_round_trip() -> ToolToolResult<()> {
+        let workspace = load_checksums_from(
+            r#"
+            checksums {
+                "https://example.com/test-1.2.3.zip" "cccc" algorithm="sha256"
+            }
+            sha512sums {
+                "https://example.com/test-1.2.3.tar.gz" "bbbb"
+            }
+        "#,
+        )?;
+        save_checksums(&workspace)?;
+        let saved =
+            std::io::read_to_string(workspace.adapter().read_file(&workspace.checksums_path())?)?;
+        expect![[r#"
+            sha256sums{
+            "https://example.com/test-1.2.3.zip" cccc
+            }
+            sha512sums{
+            "https://example.com/test-1.2.3.tar.gz" bbbb
+            }
+        "#]]
+        .assert_eq(&saved);
+        let reloaded = load_checksums_from(&saved)?;
+        assert_eq!(reloaded.checksums, workspace.checksums);
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_checksums_prunes_stale_urls() -> ToolToolResult<()> {
+        let workspace = load_checksums_from(
+            r#"
+            sha512sums {
+                "https://example.com/test-1.2.3.tar.gz" "bbbb"
+                "https://example.com/test-1.2.2.tar.gz" "aaaa"
+            }
+        "#,
+        )?;
+        save_checksums(&workspace)?;
+        let saved =
+            std::io::read_to_string(workspace.adapter().read_file(&workspace.checksums_path())?)?;
+        expect![[r#"
+            sha512sums{
+            "https://example.com/test-1.2.3.tar.gz" bbbb
+            }
+        "#]]
+        .assert_eq(&saved);
+        Ok(())
+    }
+
+    #[test]
+    fn test_configured_urls_of_all_platforms() -> ToolToolResult<()> {
+        let adapter = MockAdapter::new();
+        adapter.try_lock()?;
+        adapter.set_configuration(
+            r#"
+            tools {
+                node "22.0.0" {
+                    download {
+                        linux-musl "https://example.com/node-${version}-linux-${arch}-musl.tar.xz"
+                        windows "https://example.com/node-${version}-win.zip"
+                        default "https://example.com/node-${version}-${os}-${arch}.tar.gz"
+                    }
+                }
+            }
+        "#,
+        );
+        let config = load_config(&adapter)?;
+        expect![[r#"
+            {
+                "https://example.com/node-22.0.0-linux-aarch64-musl.tar.xz",
+                "https://example.com/node-22.0.0-linux-aarch64.tar.gz",
+                "https://example.com/node-22.0.0-linux-x86_64-musl.tar.xz",
+                "https://example.com/node-22.0.0-linux-x86_64.tar.gz",
+                "https://example.com/node-22.0.0-macos-aarch64.tar.gz",
+                "https://example.com/node-22.0.0-macos-x86_64.tar.gz",
+                "https://example.com/node-22.0.0-win.zip",
+            }
+        "#]]
+        .assert_debug_eq(&configured_urls(&config, &adapter)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_unknown_algorithm() {
+        let error = load_checksums_from(
+            r#"
+            md5sums {
+                "https://example.com/a.tar.gz" "aaaa"
+            }
+        "#,
+        )
+        .err()
+        .unwrap();
+        expect!["Unknown node 'md5sums' in checksums file '.tool-tool/v2/checksums.kdl'"]
+            .assert_eq(&format!("{error:#}"));
+    }
+
+    #[test]
+    fn test_load_untagged_checksum_with_unknown_algorithm() {
+        let error = load_checksums_from(
+            r#"
+            checksums {
+                "https://example.com/a.tar.gz" "aaaa"
+            }
+        "#,
+        )
+        .err()
+        .unwrap();
+        expect![
+            "Invalid checksum for 'https://example.com/a.tar.gz': Could not determine the algorithm of checksum 'aaaa'"
+        ]
+        .assert_eq(&format!("{error:#}"));
+    }
 }
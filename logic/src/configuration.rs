@@ -1,9 +1,13 @@
-use crate::configuration::platform::DownloadPlatform;
+use crate::configuration::platform::{HostPlatform, PlatformKey};
+use crate::file_type::FileType;
+use crate::hash::ChecksumAlgorithm;
 use crate::help::generate_available_commands_message;
+use crate::redact::redact_url;
+use crate::signature::PublicKey;
 use crate::types::Env;
 use std::collections::BTreeMap;
 use std::fmt::Display;
-use tool_tool_base::result::{HelpError, ToolToolError, ToolToolResult};
+use tool_tool_base::result::{Context, HelpError, ToolToolError, ToolToolResult};
 
 pub mod expand_config;
 pub mod parse_config;
@@ -17,6 +21,76 @@ pub const CHECKSUM_FILE_NAME: &str = "checksums.kdl";
 #[derive(Debug, Clone)]
 pub struct DownloadArtifact {
     pub url: String,
+    /// The url as configured, before its template expressions were expanded
+    pub url_template: String,
+    /// Explicit archive format, overrides detection from the url and content
+    pub format: Option<FileType>,
+    /// Url of an upstream checksum file listing the checksum of this artifact
+    pub checksum_url: Option<String>,
+    /// The checksum url as configured, before its template expressions were expanded
+    pub checksum_url_template: Option<String>,
+    /// Algorithm of the upstream checksum, guessed from the checksum length if not given
+    pub checksum_format: Option<ChecksumAlgorithm>,
+    /// Url of a detached signature of this artifact, verified with the tool's public key
+    pub signature_url: Option<String>,
+    /// The signature url as configured, before its template expressions were expanded
+    pub signature_url_template: Option<String>,
+    /// Checksums pinned in the configuration, these take precedence over the checksums file
+    pub checksums: BTreeMap<ChecksumAlgorithm, String>,
+    /// Additional HTTP headers sent when downloading the artifact (and its checksum file and
+    /// signature), template expressions in the values are only expanded right before downloading
+    pub headers: Vec<(String, String)>,
+}
+
+/// Controls which entries of a downloaded archive are extracted and where they end up
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractConfiguration {
+    /// Number of leading path components to remove from each archive entry
+    pub strip_components: usize,
+    /// Only extract entries below this archive directory (applied after stripping components)
+    pub subdir: Option<String>,
+    /// Glob patterns of files to extract, if empty all files are extracted
+    pub include: Vec<GlobPattern>,
+    /// Glob patterns of files and directories to skip
+    pub exclude: Vec<GlobPattern>,
+}
+
+impl Default for ExtractConfiguration {
+    fn default() -> Self {
+        Self {
+            strip_components: 1,
+            subdir: None,
+            include: vec![],
+            exclude: vec![],
+        }
+    }
+}
+
+/// Glob pattern of archive entries, compiled when the configuration is parsed
+#[derive(Clone, PartialEq)]
+pub struct GlobPattern(glob::Pattern);
+
+impl GlobPattern {
+    pub fn new(pattern: &str) -> ToolToolResult<Self> {
+        glob::Pattern::new(pattern)
+            .map(Self)
+            .with_context(|| format!("Invalid glob pattern: '{pattern}'"))
+    }
+
+    /// Whether the path matches the pattern, `*` and `?` do not match path separators
+    pub fn matches(&self, path: &str) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..glob::MatchOptions::default()
+        };
+        self.0.matches_with(path, options)
+    }
+}
+
+impl std::fmt::Debug for GlobPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self.0.as_str(), f)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -41,19 +115,34 @@ pub struct ToolConfiguration {
     pub name: String,
     pub version: String,
     pub default_download_artifact: Option<DownloadArtifact>,
-    pub download_urls: BTreeMap<DownloadPlatform, DownloadArtifact>,
+    pub download_urls: BTreeMap<PlatformKey, DownloadArtifact>,
+    pub extract: ExtractConfiguration,
+    /// Key trusted to sign the tool's artifacts
+    pub public_key: Option<PublicKey>,
     pub commands: Vec<Command>,
     pub env: Env,
 }
 
+impl ToolConfiguration {
+    /// Finds the download artifact for the host, preferring the most specific platform key
+    pub fn download_artifact(&self, host_platform: &HostPlatform) -> Option<&DownloadArtifact> {
+        host_platform
+            .platform_keys()
+            .iter()
+            .find_map(|platform_key| self.download_urls.get(platform_key))
+            .or(self.default_download_artifact.as_ref())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ToolToolConfiguration {
     pub tools: Vec<ToolConfiguration>,
 }
 
+/// Displays the url with its credentials redacted
 impl Display for DownloadArtifact {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.url)
+        write!(f, "{}", redact_url(&self.url))
     }
 }
 
@@ -78,3 +167,41 @@ pub fn find_command<'a>(
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::CONFIGURATION_FILE_NAME;
+    use crate::configuration::parse_config::parse_configuration_from_kdl;
+    use crate::configuration::platform::{Architecture, DownloadPlatform, HostPlatform};
+    use tool_tool_base::result::ToolToolResult;
+
+    #[test]
+    fn download_artifact_fallback() -> ToolToolResult<()> {
+        let config = parse_configuration_from_kdl(
+            CONFIGURATION_FILE_NAME,
+            r#"tools {
+                node "22.0.0" {
+                    download {
+                        linux-aarch64 "linux-aarch64"
+                        linux "linux"
+                        macos-aarch64 "macos-aarch64"
+                        default "default"
+                    }
+                }
+            }"#,
+        )?;
+        let tool = &config.tools[0];
+        let url = |os, architecture| {
+            tool.download_artifact(&HostPlatform::new(os, architecture))
+                .map(|artifact| artifact.url.as_str())
+        };
+        use Architecture::*;
+        use DownloadPlatform::*;
+        assert_eq!(url(Linux, Aarch64), Some("linux-aarch64"));
+        assert_eq!(url(Linux, X86_64), Some("linux"));
+        assert_eq!(url(MacOS, Aarch64), Some("macos-aarch64"));
+        assert_eq!(url(MacOS, X86_64), Some("default"));
+        assert_eq!(url(Windows, X86_64), Some("default"));
+        Ok(())
+    }
+}
@@ -1,22 +1,36 @@
 use crate::adapter::Adapter;
-use crate::configuration::platform::DownloadPlatform;
-use crate::configuration::{ToolToolConfiguration, find_command};
+use crate::configuration::platform::{DownloadPlatform, HostPlatform, Libc, PlatformKey};
+use crate::configuration::{
+    DownloadArtifact, ToolConfiguration, ToolToolConfiguration, find_command,
+};
+use crate::redact::redact_url;
 use crate::template_expander::TemplateExpander;
 use crate::template_string::TemplateString;
-use tool_tool_base::result::{ToolToolResult, err};
+use std::collections::BTreeSet;
+use tool_tool_base::result::{Context, ToolToolResult, err};
 
 pub fn expand_configuration_template_expressions(
     configuration: &mut ToolToolConfiguration,
     adapter: &dyn Adapter,
 ) -> ToolToolResult<()> {
     let original_configuration = configuration.clone();
-    let mut expander = create_expander(&original_configuration, adapter);
+    let host_platform = HostPlatform::from_adapter(adapter);
+    let mut expander = create_expander(&original_configuration, adapter, host_platform);
     for tool in &mut configuration.tools {
         expander.add_replace_fn("version", |_| Ok(tool.version.clone()));
-        for download_artifact in tool.download_urls.values_mut() {
-            let template_string = TemplateString::try_from(download_artifact.url.as_str())?;
-            let new_url = expander.expand(template_string)?;
-            download_artifact.url = new_url;
+        for (platform_key, download_artifact) in tool.download_urls.iter_mut() {
+            // Every download entry is expanded for its own platform, e.g. `${arch}` of a
+            // `linux-aarch64` entry is `aarch64`, whatever the host's architecture
+            let mut key_expander = create_expander(
+                &original_configuration,
+                adapter,
+                key_platform(platform_key, host_platform),
+            );
+            key_expander.add_replace_fn("version", |_| Ok(tool.version.clone()));
+            expand_download_artifact(download_artifact, &mut key_expander)?;
+        }
+        if let Some(download_artifact) = tool.default_download_artifact.as_mut() {
+            expand_download_artifact(download_artifact, &mut expander)?;
         }
         for command in tool.commands.iter_mut() {
             let template_string = TemplateString::try_from(command.command_string.as_str())?;
@@ -32,12 +46,119 @@ pub fn expand_configuration_template_expressions(
     Ok(())
 }
 
+fn expand_download_artifact(
+    download_artifact: &mut DownloadArtifact,
+    expander: &mut TemplateExpander,
+) -> ToolToolResult<()> {
+    download_artifact.url =
+        expander.expand(TemplateString::try_from(download_artifact.url.as_str())?)?;
+    for additional_url in [
+        &mut download_artifact.checksum_url,
+        &mut download_artifact.signature_url,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        *additional_url = expander.expand(TemplateString::try_from(additional_url.as_str())?)?;
+    }
+    Ok(())
+}
+
+/// The platform a download entry is expanded for: the os, architecture and libc of its key,
+/// falling back to the host's architecture (and libc) where the key does not specify them
+fn key_platform(platform_key: &PlatformKey, host_platform: HostPlatform) -> HostPlatform {
+    let architecture = platform_key
+        .architecture
+        .unwrap_or(host_platform.architecture);
+    let libc = if platform_key.os == DownloadPlatform::Linux {
+        platform_key
+            .libc
+            .or(host_platform
+                .libc
+                .filter(|_| host_platform.os == DownloadPlatform::Linux))
+            .or(Some(Libc::Gnu))
+    } else {
+        None
+    };
+    HostPlatform::new(platform_key.os, architecture).with_libc(libc)
+}
+
+/// Expands the template expressions in the header values of a download artifact
+///
+/// Headers usually carry credentials (e.g. `${env:ARTIFACTORY_TOKEN}`), so they are not expanded
+/// with the rest of the configuration: the variables are only required when actually downloading,
+/// and the values never show up in `--expand-config`
+pub(crate) fn expand_download_headers(
+    config: &ToolToolConfiguration,
+    tool: &ToolConfiguration,
+    artifact: &DownloadArtifact,
+    adapter: &dyn Adapter,
+) -> ToolToolResult<Vec<(String, String)>> {
+    let mut expander = create_expander(config, adapter, HostPlatform::from_adapter(adapter));
+    expander.add_replace_fn("version", |_| Ok(tool.version.clone()));
+    artifact
+        .headers
+        .iter()
+        .map(|(name, value)| {
+            let value = expander
+                .expand(TemplateString::try_from(value.as_str())?)
+                .with_context(|| {
+                    format!(
+                        "Failed to expand header '{name}' for download '{}'",
+                        redact_url(&artifact.url)
+                    )
+                })?;
+            Ok((name.clone(), value))
+        })
+        .collect()
+}
+
+/// Expands the download artifact every supported host platform would use for the tool
+pub(crate) fn expand_download_artifacts_of_all_hosts(
+    config: &ToolToolConfiguration,
+    tool: &ToolConfiguration,
+    adapter: &dyn Adapter,
+) -> ToolToolResult<Vec<(HostPlatform, DownloadArtifact)>> {
+    let mut artifacts = vec![];
+    for host_platform in HostPlatform::all() {
+        let Some(download_artifact) = tool.download_artifact(&host_platform) else {
+            continue;
+        };
+        let mut expander = create_expander(config, adapter, host_platform);
+        expander.add_replace_fn("version", |_| Ok(tool.version.clone()));
+        let mut download_artifact = DownloadArtifact {
+            url: download_artifact.url_template.clone(),
+            checksum_url: download_artifact.checksum_url_template.clone(),
+            signature_url: download_artifact.signature_url_template.clone(),
+            ..download_artifact.clone()
+        };
+        expand_download_artifact(&mut download_artifact, &mut expander)?;
+        artifacts.push((host_platform, download_artifact));
+    }
+    Ok(artifacts)
+}
+
+/// Expands the download url every supported host platform would use for the tool
+pub(crate) fn expand_download_urls_of_all_hosts(
+    config: &ToolToolConfiguration,
+    tool: &ToolConfiguration,
+    adapter: &dyn Adapter,
+) -> ToolToolResult<BTreeSet<String>> {
+    Ok(
+        expand_download_artifacts_of_all_hosts(config, tool, adapter)?
+            .into_iter()
+            .map(|(_, download_artifact)| download_artifact.url)
+            .collect(),
+    )
+}
+
 fn create_expander<'a>(
     config: &'a ToolToolConfiguration,
     adapter: &'a dyn Adapter,
+    host_platform: HostPlatform,
 ) -> TemplateExpander<'a> {
     let mut expander = TemplateExpander::default();
-    expander.add_replace_fn("dir", |substitution| {
+    expander.add_replace_fn("dir", move |substitution| {
         let tool_name = &substitution.arguments[0];
         let tool = config
             .tools
@@ -50,16 +171,23 @@ fn create_expander<'a>(
             adapter.get_base_path(),
             tool.name,
             tool.version,
-            adapter.get_platform()
+            host_platform
         ))
     });
     expander.add_replace_fn(
         "base_path",
         move |_substitution| Ok(adapter.get_base_path()),
     );
-    let host_platform = adapter.get_platform();
+    expander.add_replace_fn("os", move |_| Ok(host_platform.os.to_string()));
+    expander.add_replace_fn("arch", move |_| Ok(host_platform.architecture.to_string()));
+    expander.add_replace_fn("libc", move |_| {
+        Ok(host_platform
+            .libc
+            .map(|libc| libc.to_string())
+            .unwrap_or_default())
+    });
     for platform in DownloadPlatform::VALUES {
-        if platform == host_platform {
+        if platform == host_platform.os {
             expander.add_replace_fn(platform.as_str(), |substitution| {
                 Ok(substitution.arguments[0].clone())
             });
@@ -68,7 +196,7 @@ fn create_expander<'a>(
         }
     }
     expander.add_replace_fn("cmd", move |substitution| {
-        expand_command(&substitution.arguments[0], config, adapter)
+        expand_command(&substitution.arguments[0], config, adapter, host_platform)
     });
     expander.add_replace_fn("env", move |substitution| {
         let arg = &substitution.arguments[0];
@@ -88,9 +216,10 @@ fn expand_command(
     command_name: &str,
     config: &ToolToolConfiguration,
     adapter: &dyn Adapter,
+    host_platform: HostPlatform,
 ) -> ToolToolResult<String> {
     let (tool_config, command_config) = find_command(command_name, config)?;
-    let mut expander = create_expander(config, adapter);
+    let mut expander = create_expander(config, adapter, host_platform);
     expander.add_replace_fn("version", |_| Ok(tool_config.version.clone()));
     expander.expand(TemplateString::try_from(
         command_config.command_string.as_str(),
@@ -100,7 +229,9 @@ fn expand_command(
 #[cfg(test)]
 mod tests {
     use crate::configuration::CONFIGURATION_FILE_NAME;
-    use crate::configuration::expand_config::expand_configuration_template_expressions;
+    use crate::configuration::expand_config::{
+        expand_configuration_template_expressions, expand_download_headers,
+    };
     use crate::configuration::parse_config::parse_configuration_from_kdl;
     use crate::mock_adapter::MockAdapter;
     use expect_test::{Expect, expect};
@@ -153,25 +284,50 @@ mod tests {
                         download_urls: {
                             Linux: DownloadArtifact {
                                 url: "https://github.com/Peltoche/lsd/releases/download/0.17.0/lsd-0.17.0-x86_64-unknown-linux-gnu.tar.gz",
+                                url_template: "https://github.com/Peltoche/lsd/releases/download/${version}/lsd-${version}-x86_64-unknown-linux-gnu.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
                             },
                             Windows: DownloadArtifact {
                                 url: "https://github.com/Peltoche/lsd/releases/download/0.17.0/lsd-0.17.0-x86_64-pc-windows-msvc.zip",
+                                url_template: "https://github.com/Peltoche/lsd/releases/download/${version}/lsd-${version}-x86_64-pc-windows-msvc.zip",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
                             },
                         },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
                         commands: [
                             Command {
                                 name: "lsd",
-                                command_string: "bin/lsd <base_path>/.tool-tool/v2/cache/foo-1.2.3-linux",
+                                command_string: "bin/lsd <base_path>/.tool-tool/v2/cache/foo-1.2.3-linux-x86_64",
                                 description: "",
                             },
                             Command {
                                 name: "take2",
-                                command_string: "bin/lsd <base_path>/.tool-tool/v2/cache/foo-1.2.3-linux one two",
+                                command_string: "bin/lsd <base_path>/.tool-tool/v2/cache/foo-1.2.3-linux-x86_64 one two",
                                 description: "",
                             },
                             Command {
                                 name: "take1",
-                                command_string: "bin/lsd <base_path>/.tool-tool/v2/cache/foo-1.2.3-linux one",
+                                command_string: "bin/lsd <base_path>/.tool-tool/v2/cache/foo-1.2.3-linux-x86_64 one",
                                 description: "",
                             },
                         ],
@@ -182,6 +338,13 @@ mod tests {
                         version: "1.2.3",
                         default_download_artifact: None,
                         download_urls: {},
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
                         commands: [],
                         env: [
                             EnvPair {
@@ -194,4 +357,159 @@ mod tests {
             }
         "#]]
     );
+
+    test_parse_and_expand!(
+        test_expand_os_and_arch,
+        r#"tools {
+                zig "0.13.0" {
+                    download {
+                        default "https://ziglang.org/download/${version}/zig-${os}-${arch}-${version}.tar.xz"
+                        linux-aarch64 "https://example.com/zig-${arch}.tar.xz"
+                    }
+                }
+            }"#,
+        expect![[r#"
+            ToolToolConfiguration {
+                tools: [
+                    ToolConfiguration {
+                        name: "zig",
+                        version: "0.13.0",
+                        default_download_artifact: Some(
+                            DownloadArtifact {
+                                url: "https://ziglang.org/download/0.13.0/zig-linux-x86_64-0.13.0.tar.xz",
+                                url_template: "https://ziglang.org/download/${version}/zig-${os}-${arch}-${version}.tar.xz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                        ),
+                        download_urls: {
+                            Linux-Aarch64: DownloadArtifact {
+                                url: "https://example.com/zig-aarch64.tar.xz",
+                                url_template: "https://example.com/zig-${arch}.tar.xz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                        },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
+                        commands: [],
+                        env: [],
+                    },
+                ],
+            }
+        "#]]
+    );
+
+    test_parse_and_expand!(
+        test_expand_libc,
+        r#"tools {
+                rg "14.1.0" {
+                    download {
+                        linux "https://example.com/rg-${version}-${arch}-unknown-linux-${libc}.tar.gz"
+                    }
+                }
+            }"#,
+        expect![[r#"
+            ToolToolConfiguration {
+                tools: [
+                    ToolConfiguration {
+                        name: "rg",
+                        version: "14.1.0",
+                        default_download_artifact: None,
+                        download_urls: {
+                            Linux: DownloadArtifact {
+                                url: "https://example.com/rg-14.1.0-x86_64-unknown-linux-gnu.tar.gz",
+                                url_template: "https://example.com/rg-${version}-${arch}-unknown-linux-${libc}.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                        },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
+                        commands: [],
+                        env: [],
+                    },
+                ],
+            }
+        "#]]
+    );
+
+    #[test]
+    fn test_expand_download_headers() -> ToolToolResult<()> {
+        let mock_adapter = MockAdapter::new();
+        mock_adapter.add_env("FIZZ", "BUZZ");
+        let mut config = parse_configuration_from_kdl(
+            CONFIGURATION_FILE_NAME,
+            r#"tools {
+                internal "1.2.3" {
+                    download {
+                        linux "https://example.com/internal-${version}.tar.gz" {
+                            headers {
+                                X-Token "${env:FIZZ}"
+                                X-Version "${version}"
+                                X-Missing "${env:MISSING}"
+                            }
+                        }
+                    }
+                }
+            }"#,
+        )?;
+        expand_configuration_template_expressions(&mut config, &mock_adapter)?;
+        let tool = &config.tools[0];
+        let mut artifact = tool.download_urls.values().next().unwrap().clone();
+        // Headers are only expanded right before downloading
+        assert_eq!(artifact.headers[0].1, "${env:FIZZ}");
+        let error = expand_download_headers(&config, tool, &artifact, &mock_adapter)
+            .expect_err("Expected error");
+        expect![[r#"Failed to expand header 'X-Missing' for download 'https://example.com/internal-1.2.3.tar.gz': Could not find environment variable 'MISSING'"#]]
+            .assert_eq(&format!("{error:#}"));
+        artifact.headers.pop();
+        expect![[r#"
+            [
+                (
+                    "X-Token",
+                    "BUZZ",
+                ),
+                (
+                    "X-Version",
+                    "1.2.3",
+                ),
+            ]
+        "#]]
+        .assert_debug_eq(&expand_download_headers(
+            &config,
+            tool,
+            &artifact,
+            &mock_adapter,
+        )?);
+        Ok(())
+    }
 }
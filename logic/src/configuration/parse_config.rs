@@ -1,5 +1,11 @@
-use crate::configuration::platform::DownloadPlatform;
-use crate::configuration::{Command, DownloadArtifact, ToolConfiguration, ToolToolConfiguration};
+use crate::configuration::platform::PlatformKey;
+use crate::configuration::{
+    Command, DownloadArtifact, ExtractConfiguration, GlobPattern, ToolConfiguration,
+    ToolToolConfiguration,
+};
+use crate::file_type::get_file_type_from_format;
+use crate::hash::ChecksumAlgorithm;
+use crate::signature::PublicKey;
 use crate::types::EnvPair;
 use kdl::{KdlDocument, KdlNode};
 use miette::{LabeledSpan, Severity, miette};
@@ -27,7 +33,9 @@ pub fn parse_configuration_from_kdl(
             match document_node.name().value() {
                 "tools" => {
                     for tool_node in children(document_node) {
-                        let tool = parse_tool(tool_node).expect("TODO: Handle error");
+                        let tool = parse_tool(tool_node).with_context(|| {
+                            format!("Failed to parse tool '{}'", tool_node.name().value())
+                        })?;
                         tools.push(tool);
                     }
                 }
@@ -70,6 +78,8 @@ fn parse_tool(tool_node: &KdlNode) -> ToolToolResult<ToolConfiguration> {
     let mut commands = vec![];
     let mut env = vec![];
     let mut default_download_artifact = None;
+    let mut extract = ExtractConfiguration::default();
+    let mut public_key = None;
     for tool_child in children(tool_node) {
         match tool_child.name().value() {
             "download" => {
@@ -82,14 +92,57 @@ fn parse_tool(tool_node: &KdlNode) -> ToolToolResult<ToolConfiguration> {
                         .as_string()
                         .expect("Expected download url to be a string")
                         .to_string();
+                    let format = string_property(download_child, "format")?
+                        .map(get_file_type_from_format)
+                        .transpose()?;
+                    let checksum_url =
+                        string_property(download_child, "checksum_url")?.map(str::to_string);
+                    let checksum_format = string_property(download_child, "checksum_format")?
+                        .map(ChecksumAlgorithm::from_str)
+                        .transpose()?;
+                    if checksum_format.is_some() && checksum_url.is_none() {
+                        bail!("'checksum_format' requires a 'checksum_url' for download '{url}'");
+                    }
+                    let signature_url =
+                        string_property(download_child, "signature_url")?.map(str::to_string);
+                    let checksums = parse_inline_checksums(download_child, &url)?;
+                    let mut headers = vec![];
+                    for download_entry_child in children(download_child) {
+                        match download_entry_child.name().value() {
+                            "headers" => headers.extend(parse_headers(download_entry_child)?),
+                            other => bail!("Unknown child of download '{url}': '{other}'"),
+                        }
+                    }
+                    let download_artifact = DownloadArtifact {
+                        url_template: url.clone(),
+                        url,
+                        format,
+                        checksum_url_template: checksum_url.clone(),
+                        checksum_url,
+                        checksum_format,
+                        signature_url_template: signature_url.clone(),
+                        signature_url,
+                        checksums,
+                        headers,
+                    };
                     if os == "default" {
-                        default_download_artifact = Some(DownloadArtifact { url });
+                        default_download_artifact = Some(download_artifact);
                     } else {
-                        download_urls
-                            .insert(DownloadPlatform::from_str(&os)?, DownloadArtifact { url });
+                        download_urls.insert(PlatformKey::from_str(&os)?, download_artifact);
                     }
                 }
             }
+            "extract" => {
+                extract = parse_extract(tool_child)?;
+            }
+            "public_key" => {
+                let key = tool_child
+                    .entry(0)
+                    .and_then(|entry| entry.value().as_string())
+                    .ok_or_else(|| err!("Expected 'public_key' to be a string"))?;
+                let namespace = string_property(tool_child, "namespace")?;
+                public_key = Some(PublicKey::parse(key, namespace)?);
+            }
             "commands" => {
                 for command_child in children(tool_child) {
                     let command_name = command_child.name().value().to_string();
@@ -128,17 +181,134 @@ fn parse_tool(tool_node: &KdlNode) -> ToolToolResult<ToolConfiguration> {
             // TODO: use miette spans for better error messages
         }
     }
+    if public_key.is_none()
+        && let Some(artifact) = download_urls
+            .values()
+            .chain(default_download_artifact.iter())
+            .find(|artifact| artifact.signature_url.is_some())
+    {
+        bail!(
+            "'signature_url' for download '{}' requires a 'public_key' for the tool",
+            artifact.url
+        );
+    }
     let tool = ToolConfiguration {
         name,
         version: version.to_string(),
         default_download_artifact,
         download_urls,
+        extract,
+        public_key,
         commands,
         env,
     };
     Ok(tool)
 }
 
+/// Parses the checksums pinned on a download entry (e.g. `sha256="..."`)
+fn parse_inline_checksums(
+    download_node: &KdlNode,
+    url: &str,
+) -> ToolToolResult<BTreeMap<ChecksumAlgorithm, String>> {
+    let mut checksums = BTreeMap::new();
+    for algorithm in ChecksumAlgorithm::VALUES {
+        let Some(checksum) = string_property(download_node, algorithm.as_str())? else {
+            continue;
+        };
+        if ChecksumAlgorithm::from_checksum(checksum) != Some(algorithm) {
+            bail!(
+                "'{algorithm}' for download '{url}' is not a valid {algorithm} checksum: '{checksum}'"
+            );
+        }
+        checksums.insert(algorithm, checksum.to_ascii_lowercase());
+    }
+    Ok(checksums)
+}
+
+/// Parses the HTTP headers of a download entry, e.g. `X-JFrog-Art-Api "${env:ARTIFACTORY_TOKEN}"`
+fn parse_headers(headers_node: &KdlNode) -> ToolToolResult<Vec<(String, String)>> {
+    let mut headers = vec![];
+    for header_node in children(headers_node) {
+        let name = header_node.name().value();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+        {
+            bail!("Invalid header name: '{name}'");
+        }
+        let value = header_node
+            .entry(0)
+            .and_then(|entry| entry.value().as_string())
+            .ok_or_else(|| err!("Expected header '{name}' to have a string value"))?;
+        headers.push((name.to_string(), value.to_string()));
+    }
+    Ok(headers)
+}
+
+fn parse_extract(extract_node: &KdlNode) -> ToolToolResult<ExtractConfiguration> {
+    let mut extract = ExtractConfiguration::default();
+    if let Some(strip_components) = extract_node.get("strip_components") {
+        let strip_components = strip_components
+            .as_integer()
+            .ok_or_else(|| err!("Expected 'strip_components' to be an integer"))?;
+        extract.strip_components = usize::try_from(strip_components)
+            .with_context(|| format!("Invalid 'strip_components' value: {strip_components}"))?;
+    }
+    for extract_child in children(extract_node) {
+        let values = extract_child
+            .entries()
+            .iter()
+            .filter(|entry| entry.name().is_none())
+            .map(|entry| {
+                entry
+                    .value()
+                    .as_string()
+                    .map(|value| value.to_string())
+                    .ok_or_else(|| {
+                        err!(
+                            "Expected '{}' values to be strings",
+                            extract_child.name().value()
+                        )
+                    })
+            })
+            .collect::<ToolToolResult<Vec<String>>>()?;
+        match extract_child.name().value() {
+            "subdir" => {
+                let [subdir] = values.as_slice() else {
+                    bail!("Expected exactly one directory for 'subdir'");
+                };
+                extract.subdir = Some(subdir.trim_matches('/').to_string());
+            }
+            "include" => {
+                extract.include.extend(parse_glob_patterns(&values)?);
+            }
+            "exclude" => {
+                extract.exclude.extend(parse_glob_patterns(&values)?);
+            }
+            other => bail!("Unknown extract child: '{other}'"),
+        }
+    }
+    Ok(extract)
+}
+
+fn parse_glob_patterns(patterns: &[String]) -> ToolToolResult<Vec<GlobPattern>> {
+    patterns
+        .iter()
+        .map(|pattern| GlobPattern::new(pattern))
+        .collect()
+}
+
+fn string_property<'a>(node: &'a KdlNode, name: &str) -> ToolToolResult<Option<&'a str>> {
+    node.get(name)
+        .map(|value| {
+            value
+                .as_string()
+                .ok_or_else(|| err!("Expected '{name}' to be a string"))
+        })
+        .transpose()
+}
+
 fn children(node: &KdlNode) -> impl IntoIterator<Item = &KdlNode> + '_ {
     node.children().map(|doc| doc.nodes()).into_iter().flatten()
 }
@@ -197,6 +367,13 @@ mod tests {
                         version: "0.17.0",
                         default_download_artifact: None,
                         download_urls: {},
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
                         commands: [],
                         env: [],
                     },
@@ -225,11 +402,36 @@ mod tests {
                         download_urls: {
                             Linux: DownloadArtifact {
                                 url: "https://github.com/Peltoche/lsd/releases/download/0.17.0/lsd-0.17.0-x86_64-unknown-linux-gnu.tar.gz",
+                                url_template: "https://github.com/Peltoche/lsd/releases/download/0.17.0/lsd-0.17.0-x86_64-unknown-linux-gnu.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
                             },
                             Windows: DownloadArtifact {
                                 url: "https://github.com/Peltoche/lsd/releases/download/0.17.0/lsd-0.17.0-x86_64-pc-windows-msvc.zip",
+                                url_template: "https://github.com/Peltoche/lsd/releases/download/0.17.0/lsd-0.17.0-x86_64-pc-windows-msvc.zip",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
                             },
                         },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
                         commands: [],
                         env: [],
                     },
@@ -263,9 +465,25 @@ mod tests {
                         default_download_artifact: Some(
                             DownloadArtifact {
                                 url: "https://github.com/Peltoche/lsd/releases/download/0.17.0/lsd-0.17.0-x86_64-unknown-linux-gnu.tar.gz",
+                                url_template: "https://github.com/Peltoche/lsd/releases/download/0.17.0/lsd-0.17.0-x86_64-unknown-linux-gnu.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
                             },
                         ),
                         download_urls: {},
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
                         commands: [
                             Command {
                                 name: "foo",
@@ -290,6 +508,478 @@ mod tests {
         "#]]
     );
 
+    test_parse!(
+        extract,
+        r#"tools {
+            lua "5.4.6" {
+                extract strip_components=0 {
+                    subdir "/lua/"
+                    include "bin" "*.dll"
+                    exclude "**/*.txt"
+                }
+            }
+        }"#,
+        expect![[r#"
+            ToolToolConfiguration {
+                tools: [
+                    ToolConfiguration {
+                        name: "lua",
+                        version: "5.4.6",
+                        default_download_artifact: None,
+                        download_urls: {},
+                        extract: ExtractConfiguration {
+                            strip_components: 0,
+                            subdir: Some(
+                                "lua",
+                            ),
+                            include: [
+                                "bin",
+                                "*.dll",
+                            ],
+                            exclude: [
+                                "**/*.txt",
+                            ],
+                        },
+                        public_key: None,
+                        commands: [],
+                        env: [],
+                    },
+                ],
+            }
+        "#]]
+    );
+
+    test_parse!(
+        download_format,
+        r#"tools {
+            lsd "0.17.0" {
+                download {
+                    linux "https://example.com/releases/latest/download?asset=linux" format="tar.gz"
+                    default "https://example.com/releases/latest/download"
+                }
+            }
+        }"#,
+        expect![[r#"
+            ToolToolConfiguration {
+                tools: [
+                    ToolConfiguration {
+                        name: "lsd",
+                        version: "0.17.0",
+                        default_download_artifact: Some(
+                            DownloadArtifact {
+                                url: "https://example.com/releases/latest/download",
+                                url_template: "https://example.com/releases/latest/download",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                        ),
+                        download_urls: {
+                            Linux: DownloadArtifact {
+                                url: "https://example.com/releases/latest/download?asset=linux",
+                                url_template: "https://example.com/releases/latest/download?asset=linux",
+                                format: Some(
+                                    TarGz,
+                                ),
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                        },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
+                        commands: [],
+                        env: [],
+                    },
+                ],
+            }
+        "#]]
+    );
+
+    test_parse!(
+        download_architectures,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    linux-x86_64 "https://example.com/node-linux-x64.tar.xz"
+                    linux-aarch64 "https://example.com/node-linux-arm64.tar.xz"
+                    macos-aarch64 "https://example.com/node-darwin-arm64.tar.gz"
+                    macos "https://example.com/node-darwin-x64.tar.gz"
+                }
+            }
+        }"#,
+        expect![[r#"
+            ToolToolConfiguration {
+                tools: [
+                    ToolConfiguration {
+                        name: "node",
+                        version: "22.0.0",
+                        default_download_artifact: None,
+                        download_urls: {
+                            Linux-X86_64: DownloadArtifact {
+                                url: "https://example.com/node-linux-x64.tar.xz",
+                                url_template: "https://example.com/node-linux-x64.tar.xz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                            Linux-Aarch64: DownloadArtifact {
+                                url: "https://example.com/node-linux-arm64.tar.xz",
+                                url_template: "https://example.com/node-linux-arm64.tar.xz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                            MacOS: DownloadArtifact {
+                                url: "https://example.com/node-darwin-x64.tar.gz",
+                                url_template: "https://example.com/node-darwin-x64.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                            MacOS-Aarch64: DownloadArtifact {
+                                url: "https://example.com/node-darwin-arm64.tar.gz",
+                                url_template: "https://example.com/node-darwin-arm64.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                        },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
+                        commands: [],
+                        env: [],
+                    },
+                ],
+            }
+        "#]]
+    );
+
+    test_parse!(
+        download_libc,
+        r#"tools {
+            rg "14.1.0" {
+                download {
+                    linux-musl "https://example.com/rg-x86_64-unknown-linux-musl.tar.gz"
+                    linux-aarch64-gnu "https://example.com/rg-aarch64-unknown-linux-gnu.tar.gz"
+                    linux "https://example.com/rg-x86_64-unknown-linux-gnu.tar.gz"
+                }
+            }
+        }"#,
+        expect![[r#"
+            ToolToolConfiguration {
+                tools: [
+                    ToolConfiguration {
+                        name: "rg",
+                        version: "14.1.0",
+                        default_download_artifact: None,
+                        download_urls: {
+                            Linux: DownloadArtifact {
+                                url: "https://example.com/rg-x86_64-unknown-linux-gnu.tar.gz",
+                                url_template: "https://example.com/rg-x86_64-unknown-linux-gnu.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                            Linux-Musl: DownloadArtifact {
+                                url: "https://example.com/rg-x86_64-unknown-linux-musl.tar.gz",
+                                url_template: "https://example.com/rg-x86_64-unknown-linux-musl.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                            Linux-Aarch64-Gnu: DownloadArtifact {
+                                url: "https://example.com/rg-aarch64-unknown-linux-gnu.tar.gz",
+                                url_template: "https://example.com/rg-aarch64-unknown-linux-gnu.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                        },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
+                        commands: [],
+                        env: [],
+                    },
+                ],
+            }
+        "#]]
+    );
+
+    test_parse!(
+        download_checksum_url,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    linux "https://nodejs.org/dist/v22.0.0/node-v22.0.0-linux-x64.tar.xz" checksum_url="https://nodejs.org/dist/v22.0.0/SHASUMS256.txt" checksum_format="sha256"
+                }
+            }
+        }"#,
+        expect![[r#"
+            ToolToolConfiguration {
+                tools: [
+                    ToolConfiguration {
+                        name: "node",
+                        version: "22.0.0",
+                        default_download_artifact: None,
+                        download_urls: {
+                            Linux: DownloadArtifact {
+                                url: "https://nodejs.org/dist/v22.0.0/node-v22.0.0-linux-x64.tar.xz",
+                                url_template: "https://nodejs.org/dist/v22.0.0/node-v22.0.0-linux-x64.tar.xz",
+                                format: None,
+                                checksum_url: Some(
+                                    "https://nodejs.org/dist/v22.0.0/SHASUMS256.txt",
+                                ),
+                                checksum_url_template: Some(
+                                    "https://nodejs.org/dist/v22.0.0/SHASUMS256.txt",
+                                ),
+                                checksum_format: Some(
+                                    Sha256,
+                                ),
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [],
+                            },
+                        },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
+                        commands: [],
+                        env: [],
+                    },
+                ],
+            }
+        "#]]
+    );
+
+    test_parse!(
+        download_inline_checksums,
+        r#"tools {
+            lsd "1.2.3" {
+                download {
+                    linux "https://example.com/test-1.2.3.tar.gz" sha256="39D71CC8A2182D078EE1BF131D988FC389A703185AEA9A1260BC3C890F61C628"
+                }
+            }
+        }"#,
+        expect![[r#"
+            ToolToolConfiguration {
+                tools: [
+                    ToolConfiguration {
+                        name: "lsd",
+                        version: "1.2.3",
+                        default_download_artifact: None,
+                        download_urls: {
+                            Linux: DownloadArtifact {
+                                url: "https://example.com/test-1.2.3.tar.gz",
+                                url_template: "https://example.com/test-1.2.3.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {
+                                    Sha256: "39d71cc8a2182d078ee1bf131d988fc389a703185aea9a1260bc3c890f61c628",
+                                },
+                                headers: [],
+                            },
+                        },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
+                        commands: [],
+                        env: [],
+                    },
+                ],
+            }
+        "#]]
+    );
+
+    test_parse!(
+        download_signature,
+        r#"tools {
+            lsd "1.2.3" {
+                public_key "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIIpMQy1uTPJ3sYQkT4QYWs5JH8+6zJcQf3GqTXpRcyy5" namespace="releases"
+                download {
+                    linux "https://example.com/test-1.2.3.tar.gz" signature_url="https://example.com/test-1.2.3.tar.gz.sig"
+                }
+            }
+        }"#,
+        expect![[r#"
+            ToolToolConfiguration {
+                tools: [
+                    ToolConfiguration {
+                        name: "lsd",
+                        version: "1.2.3",
+                        default_download_artifact: None,
+                        download_urls: {
+                            Linux: DownloadArtifact {
+                                url: "https://example.com/test-1.2.3.tar.gz",
+                                url_template: "https://example.com/test-1.2.3.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: Some(
+                                    "https://example.com/test-1.2.3.tar.gz.sig",
+                                ),
+                                signature_url_template: Some(
+                                    "https://example.com/test-1.2.3.tar.gz.sig",
+                                ),
+                                checksums: {},
+                                headers: [],
+                            },
+                        },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: Some(
+                            PublicKey {
+                                format: Ssh,
+                                key: "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIIpMQy1uTPJ3sYQkT4QYWs5JH8+6zJcQf3GqTXpRcyy5",
+                                namespace: Some(
+                                    "releases",
+                                ),
+                            },
+                        ),
+                        commands: [],
+                        env: [],
+                    },
+                ],
+            }
+        "#]]
+    );
+
+    test_parse!(
+        download_headers,
+        r#"tools {
+            internal "1.2.3" {
+                download {
+                    linux "https://artifactory.example.com/internal-${version}.tar.gz" {
+                        headers {
+                            X-JFrog-Art-Api "${env:ARTIFACTORY_TOKEN}"
+                            Accept "application/octet-stream"
+                        }
+                    }
+                }
+            }
+        }"#,
+        expect![[r#"
+            ToolToolConfiguration {
+                tools: [
+                    ToolConfiguration {
+                        name: "internal",
+                        version: "1.2.3",
+                        default_download_artifact: None,
+                        download_urls: {
+                            Linux: DownloadArtifact {
+                                url: "https://artifactory.example.com/internal-${version}.tar.gz",
+                                url_template: "https://artifactory.example.com/internal-${version}.tar.gz",
+                                format: None,
+                                checksum_url: None,
+                                checksum_url_template: None,
+                                checksum_format: None,
+                                signature_url: None,
+                                signature_url_template: None,
+                                checksums: {},
+                                headers: [
+                                    (
+                                        "X-JFrog-Art-Api",
+                                        "${env:ARTIFACTORY_TOKEN}",
+                                    ),
+                                    (
+                                        "Accept",
+                                        "application/octet-stream",
+                                    ),
+                                ],
+                            },
+                        },
+                        extract: ExtractConfiguration {
+                            strip_components: 1,
+                            subdir: None,
+                            include: [],
+                            exclude: [],
+                        },
+                        public_key: None,
+                        commands: [],
+                        env: [],
+                    },
+                ],
+            }
+        "#]]
+    );
+
     fn test_parse_fail(kdl: &str, expected: Expect) -> ToolToolResult<()> {
         let error =
             parse_configuration_from_kdl(CONFIGURATION_FILE_NAME, kdl).expect_err("Expected error");
@@ -305,6 +995,224 @@ mod tests {
             }
             });
 
+    /// Like `test_parse_fail`, but also asserts the causes of the error
+    fn test_parse_fail_with_causes(kdl: &str, expected: Expect) -> ToolToolResult<()> {
+        let error =
+            parse_configuration_from_kdl(CONFIGURATION_FILE_NAME, kdl).expect_err("Expected error");
+        expected.assert_eq(&format!("{error:#}"));
+        Ok(())
+    }
+
+    macro_rules! test_parse_fail_with_causes(
+        ($name:ident, $kdl:expr, $expected:expr) => {
+            #[test]
+            fn $name() -> ToolToolResult<()> {
+                test_parse_fail_with_causes($kdl, $expected)
+            }
+            });
+
+    test_parse_fail_with_causes!(
+        fail_extract_negative_strip_components,
+        r#"tools {
+            lua "5.4.6" {
+                extract strip_components=-1
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'lua': Invalid 'strip_components' value: -1: out of range integral type conversion attempted"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_extract_invalid_glob,
+        r#"tools {
+            lua "5.4.6" {
+                extract {
+                    include "[bin"
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'lua': Invalid glob pattern: '[bin': Pattern syntax error near position 0: invalid range pattern"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_unknown_format,
+        r#"tools {
+            lsd "0.17.0" {
+                download {
+                    linux "https://example.com/lsd" format="rar"
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'lsd': Unknown download format 'rar', expected one of: zip, tar, tar.gz, tgz, tar.xz, txz, tar.bz2, tbz2, tar.zst, tzst, gz, xz, bz2, zst, exe, binary"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_unknown_architecture,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    linux-sparc "https://example.com/node"
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'node': Unknown architecture: 'sparc'"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_libc_on_windows,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    windows-musl "https://example.com/node"
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'node': The libc qualifier 'musl' is only supported for linux, not 'windows'"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_checksum_url_not_a_string,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    linux "https://example.com/node" checksum_url=42
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'node': Expected 'checksum_url' to be a string"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_unknown_child,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    linux "https://example.com/node" {
+                        header "Accept" "application/octet-stream"
+                    }
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'node': Unknown child of download 'https://example.com/node': 'header'"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_invalid_header_name,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    linux "https://example.com/node" {
+                        headers {
+                            "Private Token" "secret"
+                        }
+                    }
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'node': Invalid header name: 'Private Token'"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_header_without_value,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    linux "https://example.com/node" {
+                        headers {
+                            Private-Token
+                        }
+                    }
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'node': Expected header 'Private-Token' to have a string value"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_checksum_format_without_url,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    linux "https://example.com/node" checksum_format="sha256"
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'node': 'checksum_format' requires a 'checksum_url' for download 'https://example.com/node'"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_unknown_checksum_format,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    linux "https://example.com/node" checksum_url="https://example.com/sums" checksum_format="md5"
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'node': Unknown checksum algorithm 'md5', expected one of: sha256, sha512"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_invalid_inline_checksum,
+        r#"tools {
+            node "22.0.0" {
+                download {
+                    linux "https://example.com/node" sha512="39d71cc8a2182d078ee1bf131d988fc389a703185aea9a1260bc3c890f61c628"
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'node': 'sha512' for download 'https://example.com/node' is not a valid sha512 checksum: '39d71cc8a2182d078ee1bf131d988fc389a703185aea9a1260bc3c890f61c628'"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_download_signature_url_without_public_key,
+        r#"tools {
+            lsd "1.2.3" {
+                download {
+                    linux "https://example.com/test-1.2.3.tar.gz" signature_url="https://example.com/test-1.2.3.tar.gz.minisig"
+                }
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'lsd': 'signature_url' for download 'https://example.com/test-1.2.3.tar.gz' requires a 'public_key' for the tool"
+        ]
+    );
+
+    test_parse_fail_with_causes!(
+        fail_invalid_public_key,
+        r#"tools {
+            lsd "1.2.3" {
+                public_key "RWQ"
+            }
+        }"#,
+        expect![
+            "Failed to parse KDL file '.tool-tool/tool-tool.v2.kdl': Failed to parse tool 'lsd': Invalid minisign public key 'RWQ': Invalid encoding in minisign data"
+        ]
+    );
+
     test_parse_fail!(
         fail_misquote,
         r#""open quote only"#,
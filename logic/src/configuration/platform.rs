@@ -1,3 +1,4 @@
+use crate::adapter::Adapter;
 use std::fmt::Display;
 use std::str::FromStr;
 use tool_tool_base::result::{ToolToolError, bail};
@@ -45,3 +46,306 @@ impl Display for DownloadPlatform {
         write!(f, "{}", self.as_str())
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
+pub enum Architecture {
+    X86_64,
+    Aarch64,
+}
+
+impl Architecture {
+    pub const VALUES: [Self; 2] = [Self::X86_64, Self::Aarch64];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Architecture::X86_64 => "x86_64",
+            Architecture::Aarch64 => "aarch64",
+        }
+    }
+}
+
+impl FromStr for Architecture {
+    type Err = ToolToolError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "x86_64" => Ok(Architecture::X86_64),
+            "aarch64" => Ok(Architecture::Aarch64),
+            other => bail!("Unknown architecture: '{other}'"),
+        }
+    }
+}
+
+impl Display for Architecture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// C library flavour of a linux host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
+pub enum Libc {
+    Gnu,
+    Musl,
+}
+
+impl Libc {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Libc::Gnu => "gnu",
+            Libc::Musl => "musl",
+        }
+    }
+}
+
+impl FromStr for Libc {
+    type Err = ToolToolError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gnu" => Ok(Libc::Gnu),
+            "musl" => Ok(Libc::Musl),
+            other => bail!("Unknown libc: '{other}'"),
+        }
+    }
+}
+
+impl Display for Libc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Key of a download entry: an os, optionally qualified by an architecture and (on linux) a libc,
+/// e.g. `linux`, `linux-aarch64`, `linux-musl` or `linux-x86_64-musl`
+#[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
+pub struct PlatformKey {
+    pub os: DownloadPlatform,
+    pub architecture: Option<Architecture>,
+    pub libc: Option<Libc>,
+}
+
+impl PlatformKey {
+    pub fn new(os: DownloadPlatform, architecture: Option<Architecture>) -> Self {
+        Self {
+            os,
+            architecture,
+            libc: None,
+        }
+    }
+
+    pub fn with_libc(self, libc: Option<Libc>) -> Self {
+        Self { libc, ..self }
+    }
+}
+
+impl From<DownloadPlatform> for PlatformKey {
+    fn from(os: DownloadPlatform) -> Self {
+        Self::new(os, None)
+    }
+}
+
+impl FromStr for PlatformKey {
+    type Err = ToolToolError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('-');
+        let os: DownloadPlatform = parts.next().unwrap_or_default().parse()?;
+        let mut key = PlatformKey::new(os, None);
+        for part in parts {
+            if key.libc.is_none()
+                && let Ok(libc) = part.parse::<Libc>()
+            {
+                if os != DownloadPlatform::Linux {
+                    bail!("The libc qualifier '{libc}' is only supported for linux, not '{os}'");
+                }
+                key.libc = Some(libc);
+            } else if key.architecture.is_none() && key.libc.is_none() {
+                key.architecture = Some(part.parse()?);
+            } else {
+                bail!("Unexpected platform qualifier '{part}' in '{s}'");
+            }
+        }
+        Ok(key)
+    }
+}
+
+impl Display for PlatformKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.os)?;
+        if let Some(architecture) = self.architecture {
+            write!(f, "-{architecture}")?;
+        }
+        if let Some(libc) = self.libc {
+            write!(f, "-{libc}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for PlatformKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.os)?;
+        if let Some(architecture) = self.architecture {
+            write!(f, "-{architecture:?}")?;
+        }
+        if let Some(libc) = self.libc {
+            write!(f, "-{libc:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The platform tool-tool is currently running on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HostPlatform {
+    pub os: DownloadPlatform,
+    pub architecture: Architecture,
+    pub libc: Option<Libc>,
+}
+
+impl HostPlatform {
+    pub fn new(os: DownloadPlatform, architecture: Architecture) -> Self {
+        Self {
+            os,
+            architecture,
+            libc: None,
+        }
+    }
+
+    pub fn with_libc(self, libc: Option<Libc>) -> Self {
+        Self { libc, ..self }
+    }
+
+    /// All supported host platforms
+    pub fn all() -> Vec<Self> {
+        let mut platforms = vec![];
+        for os in DownloadPlatform::VALUES {
+            for architecture in Architecture::VALUES {
+                let host_platform = Self::new(os, architecture);
+                if os == DownloadPlatform::Linux {
+                    platforms.push(host_platform.with_libc(Some(Libc::Gnu)));
+                    platforms.push(host_platform.with_libc(Some(Libc::Musl)));
+                } else {
+                    platforms.push(host_platform);
+                }
+            }
+        }
+        platforms
+    }
+
+    pub fn from_adapter(adapter: &dyn Adapter) -> Self {
+        Self::new(adapter.get_platform(), adapter.get_architecture()).with_libc(adapter.get_libc())
+    }
+
+    /// Download keys matching this host, from most to least specific
+    pub fn platform_keys(&self) -> Vec<PlatformKey> {
+        let mut keys = Vec::with_capacity(4);
+        if let Some(libc) = self.libc {
+            keys.push(PlatformKey::new(self.os, Some(self.architecture)).with_libc(Some(libc)));
+            keys.push(PlatformKey::new(self.os, None).with_libc(Some(libc)));
+        }
+        keys.push(PlatformKey::new(self.os, Some(self.architecture)));
+        keys.push(PlatformKey::new(self.os, None));
+        keys
+    }
+}
+
+impl Display for HostPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.os, self.architecture)?;
+        // glibc is the default linux flavour, leaving it out keeps existing cache directories valid
+        if self.libc == Some(Libc::Musl) {
+            write!(f, "-musl")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tool_tool_base::result::ToolToolResult;
+
+    #[test]
+    fn parse_platform_key() -> ToolToolResult<()> {
+        assert_eq!(
+            "linux".parse::<PlatformKey>()?,
+            PlatformKey::new(DownloadPlatform::Linux, None)
+        );
+        assert_eq!(
+            "macos-aarch64".parse::<PlatformKey>()?,
+            PlatformKey::new(DownloadPlatform::MacOS, Some(Architecture::Aarch64))
+        );
+        assert_eq!(
+            "linux-x86_64".parse::<PlatformKey>()?.to_string(),
+            "linux-x86_64"
+        );
+        assert_eq!(
+            format!("{:#}", "linux-sparc".parse::<PlatformKey>().unwrap_err()),
+            "Unknown architecture: 'sparc'"
+        );
+        assert_eq!(
+            format!("{:#}", "beos".parse::<PlatformKey>().unwrap_err()),
+            "Unknown download platform: 'beos'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_platform_key_with_libc() -> ToolToolResult<()> {
+        assert_eq!(
+            "linux-musl".parse::<PlatformKey>()?,
+            PlatformKey::new(DownloadPlatform::Linux, None).with_libc(Some(Libc::Musl))
+        );
+        assert_eq!(
+            "linux-aarch64-gnu".parse::<PlatformKey>()?,
+            PlatformKey::new(DownloadPlatform::Linux, Some(Architecture::Aarch64))
+                .with_libc(Some(Libc::Gnu))
+        );
+        assert_eq!(
+            "linux-x86_64-musl".parse::<PlatformKey>()?.to_string(),
+            "linux-x86_64-musl"
+        );
+        assert_eq!(
+            format!(
+                "{:#}",
+                "linux-musl-x86_64".parse::<PlatformKey>().unwrap_err()
+            ),
+            "Unexpected platform qualifier 'x86_64' in 'linux-musl-x86_64'"
+        );
+        assert_eq!(
+            format!("{:#}", "windows-musl".parse::<PlatformKey>().unwrap_err()),
+            "The libc qualifier 'musl' is only supported for linux, not 'windows'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn host_platform_keys() {
+        let host = HostPlatform::new(DownloadPlatform::Linux, Architecture::Aarch64);
+        let keys: Vec<_> = host
+            .platform_keys()
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        assert_eq!(keys, ["linux-aarch64", "linux"]);
+        assert_eq!(host.to_string(), "linux-aarch64");
+    }
+
+    #[test]
+    fn host_platform_keys_with_libc() {
+        let host = HostPlatform::new(DownloadPlatform::Linux, Architecture::X86_64)
+            .with_libc(Some(Libc::Musl));
+        let keys: Vec<_> = host
+            .platform_keys()
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        assert_eq!(
+            keys,
+            ["linux-x86_64-musl", "linux-musl", "linux-x86_64", "linux"]
+        );
+        assert_eq!(host.to_string(), "linux-x86_64-musl");
+        let host = host.with_libc(Some(Libc::Gnu));
+        assert_eq!(host.platform_keys()[1].to_string(), "linux-gnu");
+        assert_eq!(host.to_string(), "linux-x86_64");
+    }
+}
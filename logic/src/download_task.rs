@@ -1,140 +1,547 @@
-use crate::checksums::save_checksums;
-use crate::configuration::ToolConfiguration;
-use crate::file_type::{FileType, get_file_type_from_url};
-use crate::hash::compute_sha512;
+use crate::checksum_file::UpstreamChecksum;
+use crate::checksums::{Checksums, save_checksums};
+use crate::configuration::expand_config::{
+    expand_download_artifacts_of_all_hosts, expand_download_headers,
+};
+use crate::configuration::platform::DownloadPlatform;
+use crate::configuration::{DownloadArtifact, ToolConfiguration};
+use crate::extract::{extract_single_file, extract_tar, extract_zip};
+use crate::file_type::{Compression, FileType, get_file_type_from_content, get_file_type_from_url};
+use crate::hash::{ChecksumAlgorithm, compute_sha512};
+use crate::manifest::Manifest;
+use crate::options::OFFLINE_ENV_VAR;
+use crate::origin::Origin;
+use crate::parallel::map_parallel;
+use crate::progress::download_with_progress;
+use crate::redact::redact_url;
+use crate::types::FilePath;
 use crate::workspace::Workspace;
-use flate2::read::GzDecoder;
 use relative_path::RelativePathBuf;
-use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::io::Read;
-use tar::EntryType;
-use tool_tool_base::result::{ToolToolResult, err};
-use tracing::{debug, info};
+use tool_tool_base::logging::{BufferedOutput, buffer_output};
+use tool_tool_base::result::{Context, HelpError, ToolToolError, ToolToolResult, bail, err};
+use tracing::{debug, info, info_span, warn};
 
-type Sha512Sums = BTreeMap<String, String>;
+/// Marker file in the tool directory, holding the checksum the tool was installed from
+///
+/// This is the sha512 checksum, unless only checksums of other algorithms were recorded for the
+/// artifact, in which case it is the strongest of those
+pub(crate) const CHECKSUM_MARKER_FILE_NAME: &str = ".tool-tool.sha512";
 
 pub fn run_download_task(workspace: &mut Workspace) -> ToolToolResult<()> {
-    let adapter = workspace.adapter();
-    let sha512sums = &workspace.checksums.sha512sums;
-    let mut new_sha512sums = sha512sums.clone();
-    // create .tool-tool directory if it doesn't exist
-    let config = workspace.config();
-    // Download artifacts for current host
-    for tool in config.tools.iter() {
-        download_tool(workspace, tool, &mut new_sha512sums)?;
+    remove_stale_temp_dirs(workspace)?;
+    let mut new_checksums = workspace.checksums.clone();
+    if workspace.options().frozen {
+        verify_checksums_complete(workspace)?;
+    }
+    // Download artifacts for current host, every tool records its checksums separately so they
+    // can be merged in configuration order
+    let tools = &workspace.config().tools;
+    let parallel = workspace.options().jobs() > 1 && tools.len() > 1;
+    let results = map_parallel(workspace.options().jobs(), tools, |tool| {
+        let download = || {
+            let _span = parallel.then(|| info_span!("tool", name = %tool.name).entered());
+            let mut tool_checksums = Checksums::default();
+            download_tool(workspace, tool, &mut tool_checksums).map(|()| tool_checksums)
+        };
+        if parallel {
+            // Buffered, so the output of each tool is written in configuration order
+            buffer_output(download)
+        } else {
+            (download(), BufferedOutput::default())
+        }
+    });
+    let mut failures = vec![];
+    for (tool, (result, output)) in tools.iter().zip(results) {
+        output.flush();
+        match result {
+            Ok(tool_checksums) => new_checksums.extend(tool_checksums),
+            Err(error) => failures.push((tool.name.clone(), error)),
+        }
+    }
+    // Keep the checksums of the tools that were installed, even if others failed
+    update_checksums(workspace, new_checksums)?;
+    if failures.len() > 1 {
+        let messages: Vec<String> = failures
+            .iter()
+            .map(|(name, error)| format!("{name}: {error:#}"))
+            .collect();
+        bail!(
+            "Failed to download {} tools:\n\t{}",
+            failures.len(),
+            messages.join("\n\t")
+        );
+    }
+    match failures.pop() {
+        Some((_, error)) => Err(error),
+        None => Ok(()),
     }
+}
 
-    // Download missing artifacts to complete checksums
-    for tool in config.tools.iter() {
-        for (platform, artifact) in tool.download_urls.iter() {
-            if !new_sha512sums.contains_key(&artifact.url) {
-                let temp_dir = workspace.create_temp_dir(&tool.name)?;
+/// Adds the missing checksums of all platforms' artifacts, preferring upstream checksum files
+/// over downloading the artifact itself
+pub fn run_complete_checksums_task(workspace: &mut Workspace) -> ToolToolResult<()> {
+    if workspace.options().offline {
+        bail!("Checksums cannot be completed in offline mode");
+    }
+    if workspace.options().frozen {
+        bail!("Checksums cannot be completed in frozen mode");
+    }
+    remove_stale_temp_dirs(workspace)?;
+    let adapter = workspace.adapter();
+    let mut new_checksums = workspace.checksums.clone();
+    for tool in workspace.config().tools.iter() {
+        // Templated urls differ per platform, so the artifacts of every host are covered, just
+        // like the configured urls that pruning keeps
+        let artifacts = tool
+            .download_urls
+            .iter()
+            .map(|(platform, artifact)| (platform.to_string(), artifact.clone()))
+            .chain(
+                tool.default_download_artifact
+                    .iter()
+                    .map(|artifact| ("default".to_string(), artifact.clone())),
+            )
+            .chain(
+                expand_download_artifacts_of_all_hosts(workspace.config(), tool, adapter)?
+                    .into_iter()
+                    .map(|(host_platform, artifact)| (host_platform.to_string(), artifact)),
+            )
+            .collect::<Vec<_>>();
+        for (platform, artifact) in &artifacts {
+            if !artifact.checksums.is_empty() || new_checksums.contains(&artifact.url) {
+                continue;
+            }
+            let headers = expand_download_headers(workspace.config(), tool, artifact, adapter)?;
+            let temp_dir = workspace.create_temp_dir(&tool.name)?;
+            let upstream_checksum = artifact
+                .checksum_url
+                .as_ref()
+                .map(|checksum_url| {
+                    let checksum_path = temp_dir.join(format!(
+                        "checksums-{}-{}-{}",
+                        tool.name, tool.version, platform
+                    ));
+                    UpstreamChecksum::fetch(
+                        workspace,
+                        artifact,
+                        &headers,
+                        checksum_url,
+                        &checksum_path,
+                    )
+                })
+                .transpose()?;
+            let (algorithm, checksum) = if let Some(upstream_checksum) = upstream_checksum {
+                (upstream_checksum.algorithm, upstream_checksum.checksum)
+            } else {
                 let download_path = temp_dir.join(format!(
                     "download-{}-{}-{}",
                     tool.name, tool.version, platform
                 ));
                 info!(
                     "Downloading {} to {} for checksum generation",
-                    artifact.url, download_path
+                    redact_url(&artifact.url),
+                    download_path
                 );
-                adapter.download_file(&artifact.url, &download_path)?;
+                download_from_mirrors(workspace, &artifact.url, &headers, &download_path)?;
+                verify_signature(
+                    workspace,
+                    tool,
+                    artifact,
+                    &headers,
+                    &download_path,
+                    platform,
+                )?;
                 let mut download_file = adapter.read_file(&download_path)?;
-                let sha512 = compute_sha512(download_file.as_mut())?;
-                new_sha512sums.insert(artifact.url.clone(), sha512);
-                adapter.delete_directory_all(&temp_dir)?;
-            }
+                (
+                    ChecksumAlgorithm::Sha512,
+                    compute_sha512(download_file.as_mut())?,
+                )
+            };
+            new_checksums.insert(artifact.url.clone(), algorithm, checksum);
+            adapter.delete_directory_all(&temp_dir)?;
         }
     }
-    if &new_sha512sums != sha512sums {
-        workspace.checksums.sha512sums = new_sha512sums;
+    update_checksums(workspace, new_checksums)
+}
+
+/// Removes temporary directories (and temporary files of the shared cache) left behind by
+/// interrupted runs
+///
+/// Temporary directories are only created while holding the lock, so any existing ones are stale
+pub(crate) fn remove_stale_temp_dirs(workspace: &Workspace) -> ToolToolResult<()> {
+    let adapter = workspace.adapter();
+    let temp_dir = workspace.temp_dir();
+    if adapter.file_exists(&temp_dir)? {
+        info!("Removing stale temporary directory {temp_dir}");
+        adapter.delete_directory_all(&temp_dir)?;
+    }
+    if let Some(shared_cache) = workspace.shared_cache()
+        && let Err(error) = shared_cache.remove_stale_temp_files(adapter)
+    {
+        warn!(
+            "Failed to remove stale temporary files from the shared cache {}: {error:#}",
+            shared_cache.dir()
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn update_checksums(
+    workspace: &mut Workspace,
+    new_checksums: Checksums,
+) -> ToolToolResult<()> {
+    if new_checksums != workspace.checksums {
+        workspace.checksums = new_checksums;
         save_checksums(workspace)?;
     }
     Ok(())
 }
 
-fn download_tool(
+/// Fails if the checksum of any host artifact is missing, used in frozen mode
+fn verify_checksums_complete(workspace: &Workspace) -> ToolToolResult<()> {
+    let host_platform = workspace.host_platform();
+    let checksums = &workspace.checksums;
+    let missing_urls: BTreeSet<String> = workspace
+        .config()
+        .tools
+        .iter()
+        .filter_map(|tool| tool.download_artifact(&host_platform))
+        .filter(|artifact| checksums.expected(artifact).is_empty())
+        .map(|artifact| redact_url(&artifact.url))
+        .collect();
+    if missing_urls.is_empty() {
+        return Ok(());
+    }
+    let urls: Vec<String> = missing_urls.into_iter().collect();
+    Err(ToolToolError::from(HelpError::new(
+        format!(
+            "Checksums are missing in frozen mode for:\n\t{}",
+            urls.join("\n\t")
+        ),
+        format!(
+            "Run 'tool-tool --download' without --frozen to add the missing checksums to '{}'",
+            workspace.checksums_path()
+        ),
+    )))
+}
+
+pub(crate) fn download_tool(
     workspace: &Workspace,
     tool: &ToolConfiguration,
-    new_sha512sums: &mut Sha512Sums,
+    new_checksums: &mut Checksums,
 ) -> ToolToolResult<()> {
-    let host_platform = workspace.adapter().get_platform();
-    let sha512sums = &workspace.checksums.sha512sums;
     let adapter = workspace.adapter();
-    let tool_path = workspace.tool_dir(tool);
-    let download_artifact = tool
-        .download_urls
-        .get(&host_platform)
-        .or(tool.default_download_artifact.as_ref())
-        .ok_or_else(|| {
-            err!(
-                "No download url found for tool '{}' on platform '{host_platform}'",
-                tool.name
-            )
-        })?;
+    let download_artifact = host_download_artifact(workspace, tool)?;
     // Determine if tool is already downloaded
-    let checksum_path = tool_path.join(".tool-tool.sha512");
-    if let Some(expected_sha512) = sha512sums.get(&download_artifact.url)
+    let expected_checksums = workspace.checksums.expected(download_artifact);
+    let checksum_path = workspace.tool_dir(tool).join(CHECKSUM_MARKER_FILE_NAME);
+    if let Some((_, expected_checksum)) = expected_checksums.last()
         && adapter.file_exists(&checksum_path)?
     {
         let mut checksum_file = adapter.read_file(&checksum_path)?;
         let mut checksum = String::new();
         checksum_file.read_to_string(&mut checksum)?;
-        if checksum != *expected_sha512 {
+        if !checksum.eq_ignore_ascii_case(expected_checksum) {
             info!("Checksum mismatch for tool '{}', re-downloading", tool.name);
         } else {
             info!("Checksum match for tool '{}', skipping download", tool.name);
             return Ok(());
         }
     }
+    install_tool(workspace, tool, new_checksums)
+}
+
+fn host_download_artifact<'a>(
+    workspace: &Workspace,
+    tool: &'a ToolConfiguration,
+) -> ToolToolResult<&'a DownloadArtifact> {
+    let host_platform = workspace.host_platform();
+    tool.download_artifact(&host_platform).ok_or_else(|| {
+        err!(
+            "No download url found for tool '{}' on platform '{host_platform}'",
+            tool.name
+        )
+    })
+}
+
+/// Downloads and installs the tool even if it is already installed
+///
+/// The existing tool directory is only replaced once the new installation is complete, so it is
+/// kept if the download fails
+pub(crate) fn install_tool(
+    workspace: &Workspace,
+    tool: &ToolConfiguration,
+    new_checksums: &mut Checksums,
+) -> ToolToolResult<()> {
+    let host_platform = workspace.host_platform();
+    let adapter = workspace.adapter();
+    let tool_path = workspace.tool_dir(tool);
+    let download_artifact = host_download_artifact(workspace, tool)?;
+    let expected_checksums = workspace.checksums.expected(download_artifact);
+    let shared_cache = workspace.shared_cache();
+    let shared_cache_artifact = match (&shared_cache, expected_checksums.last()) {
+        (Some(shared_cache), Some((algorithm, checksum)))
+            if shared_cache.contains(adapter, *algorithm, checksum)? =>
+        {
+            Some((shared_cache, *algorithm, *checksum))
+        }
+        _ => None,
+    };
+    if workspace.options().offline && shared_cache_artifact.is_none() {
+        return Err(offline_error(tool));
+    }
+    // Credentials in the headers are only required once the tool actually needs to be installed
+    let headers = expand_download_headers(workspace.config(), tool, download_artifact, adapter)?;
     let temp_dir = workspace.create_temp_dir(&tool.name)?;
     if adapter.file_exists(&temp_dir)? {
         adapter.delete_directory_all(&temp_dir)?;
     }
     adapter.create_directory_all(&temp_dir)?;
-    if adapter.file_exists(&tool_path)? {
-        adapter.delete_directory_all(&tool_path)?;
-    }
-    adapter.create_directory_all(&tool_path)?;
     let download_path = temp_dir.join(format!(
         "download-{}-{}-{}",
         tool.name, tool.version, host_platform
     ));
-    info!("Downloading {} to {}", download_artifact.url, download_path);
-    adapter.download_file(&download_artifact.url, &download_path)?;
+    let from_shared_cache = match shared_cache_artifact {
+        Some((shared_cache, algorithm, checksum)) => {
+            shared_cache.fetch(adapter, algorithm, checksum, &download_path)?
+        }
+        None => false,
+    };
+    if !from_shared_cache {
+        if workspace.options().offline {
+            return Err(offline_error(tool));
+        }
+        info!(
+            "Downloading {} to {}",
+            redact_url(&download_artifact.url),
+            download_path
+        );
+        download_from_mirrors(workspace, &download_artifact.url, &headers, &download_path)?;
+    }
     let mut download_file = adapter.read_file(&download_path)?;
-    // Compute and verify checksum
+    // Compute and verify checksums
     let sha512 = compute_sha512(download_file.as_mut())?;
     debug!("Checksum for tool '{}': {}", tool.name, sha512);
-    if let Some(expected_sha512) = sha512sums.get(&download_artifact.url) {
-        if sha512 != *expected_sha512 {
-            return Err(err!(
-                "Checksum mismatch for tool '{}'\nExpected: {}\nActual:   {}",
-                tool.name,
-                expected_sha512,
-                sha512
-            ));
+    let installed_checksum = if let Some((_, strongest_checksum)) = expected_checksums.last() {
+        for (algorithm, expected_checksum) in &expected_checksums {
+            let checksum = match algorithm {
+                ChecksumAlgorithm::Sha512 => sha512.clone(),
+                other => other.compute(adapter.read_file(&download_path)?)?,
+            };
+            if !checksum.eq_ignore_ascii_case(expected_checksum) {
+                return Err(err!(
+                    "Checksum mismatch for tool '{}'\nExpected: {}\nActual:   {}",
+                    tool.name,
+                    expected_checksum,
+                    checksum
+                ));
+            }
         }
+        strongest_checksum.to_string()
     } else {
+        if let Some(checksum_url) = &download_artifact.checksum_url {
+            let checksum_path = temp_dir.join(format!(
+                "checksums-{}-{}-{}",
+                tool.name, tool.version, host_platform
+            ));
+            UpstreamChecksum::fetch(
+                workspace,
+                download_artifact,
+                &headers,
+                checksum_url,
+                &checksum_path,
+            )?
+            .verify(adapter, &tool.name, &download_path)?;
+        }
         info!(
             "Checksum not found for tool '{}' ({}) adding it",
             tool.name, host_platform
         );
-        new_sha512sums.insert(download_artifact.url.clone(), sha512.clone());
+        new_checksums.insert(
+            download_artifact.url.clone(),
+            ChecksumAlgorithm::Sha512,
+            sha512.clone(),
+        );
+        sha512.clone()
+    };
+
+    verify_signature(
+        workspace,
+        tool,
+        download_artifact,
+        &headers,
+        &download_path,
+        &host_platform.to_string(),
+    )?;
+
+    if let Some(shared_cache) = &shared_cache
+        && !from_shared_cache
+    {
+        let mut cache_keys = expected_checksums.clone();
+        if !cache_keys
+            .iter()
+            .any(|(algorithm, _)| *algorithm == ChecksumAlgorithm::Sha512)
+        {
+            cache_keys.push((ChecksumAlgorithm::Sha512, &sha512));
+        }
+        // The shared cache is only an optimization, so failing to populate it is not fatal
+        if let Err(error) = shared_cache.store(adapter, &download_path, &cache_keys) {
+            warn!(
+                "Failed to add tool '{}' to the shared cache {}: {error:#}",
+                tool.name,
+                shared_cache.dir()
+            );
+        }
     }
 
-    adapter.delete_directory_all(&tool_path)?;
-    // get file type
-    let file_type = get_file_type_from_url(&download_artifact.url);
+    // Extract into a staging directory first, so the tool directory is never half-populated
+    let staging_path = temp_dir.join("staging");
+    adapter.create_directory_all(&staging_path)?;
+    let file_type = determine_file_type(workspace, download_artifact, &download_path)?;
     debug!("Extracting tool '{}'", tool.name);
-    extract_tool(workspace, tool, &tool_path, &download_path, file_type)?;
+    extract_tool(workspace, tool, &staging_path, &download_path, file_type)
+        .with_context(|| format!("Failed to extract tool '{}'", tool.name))?;
+    Manifest::from_directory(adapter, &staging_path)?.save(adapter, &staging_path)?;
+    let mut checksum_file = adapter.create_file(&staging_path.join(CHECKSUM_MARKER_FILE_NAME))?;
+    checksum_file.write_all(installed_checksum.as_bytes())?;
+    drop(checksum_file);
 
+    // Move the previous installation out of the way instead of deleting it, so the tool directory
+    // is only missing between two renames, and the old files are deleted with the temp directory
+    if adapter.file_exists(&tool_path)? {
+        adapter.rename(&tool_path, &temp_dir.join("previous"))?;
+    }
+    adapter.rename(&staging_path, &tool_path)?;
     adapter.delete_directory_all(&temp_dir)?;
-    // Last step is to create the checksum file
-    let mut checksum_file = adapter.create_file(&checksum_path)?;
-    checksum_file.write_all(sha512.as_bytes())?;
     Ok(())
 }
 
+fn offline_error(tool: &ToolConfiguration) -> ToolToolError {
+    ToolToolError::from(HelpError::new(
+        format!(
+            "Tool '{}' {} is not available in the cache and cannot be downloaded in offline mode",
+            tool.name, tool.version
+        ),
+        format!(
+            "Run 'tool-tool --download' without --offline (and without {OFFLINE_ENV_VAR} set) while connected to the network to populate the cache"
+        ),
+    ))
+}
+
+/// Downloads the url, from its mirrors in order if any are configured
+///
+/// If a mirror fails the next one is tried. Callers keep using the original url for everything
+/// else, so the keys in the checksums file are the same with and without mirrors.
+pub(crate) fn download_from_mirrors(
+    workspace: &Workspace,
+    url: &str,
+    headers: &[(String, String)],
+    destination_path: &FilePath,
+) -> ToolToolResult<()> {
+    let adapter = workspace.adapter();
+    let candidates = workspace.mirrors()?.candidates(url);
+    let mut errors = vec![];
+    for (index, candidate) in candidates.iter().enumerate() {
+        if candidate != url {
+            info!(
+                "Using mirror {} for {}",
+                redact_url(candidate),
+                redact_url(url)
+            );
+        }
+        // Configured headers may hold credentials for the original host, mirrors on other hosts
+        // authenticate with their own credentials (TOOL_TOOL_AUTH_<HOST> or netrc)
+        let headers = if Origin::of(candidate) == Origin::of(url) {
+            headers
+        } else {
+            &[]
+        };
+        match download_with_progress(adapter, candidate, headers, destination_path) {
+            Ok(()) => return Ok(()),
+            Err(error) if candidates.len() == 1 => return Err(error),
+            Err(error) => {
+                if index + 1 < candidates.len() {
+                    warn!("{error:#}, trying the next mirror");
+                }
+                errors.push(format!("{error:#}"));
+            }
+        }
+    }
+    bail!(
+        "Failed to download '{}' from all {} mirrors:\n\t{}",
+        redact_url(url),
+        candidates.len(),
+        errors.join("\n\t")
+    )
+}
+
+/// Downloads the detached signature of the artifact (if configured) and verifies the downloaded
+/// file against the tool's public key
+fn verify_signature(
+    workspace: &Workspace,
+    tool: &ToolConfiguration,
+    artifact: &DownloadArtifact,
+    headers: &[(String, String)],
+    download_path: &RelativePathBuf,
+    platform: &str,
+) -> ToolToolResult<()> {
+    let Some(signature_url) = &artifact.signature_url else {
+        return Ok(());
+    };
+    let public_key = tool
+        .public_key
+        .as_ref()
+        .ok_or_else(|| err!("No public key configured for tool '{}'", tool.name))?;
+    let adapter = workspace.adapter();
+    let signature_path = download_path.with_file_name(format!(
+        "signature-{}-{}-{platform}",
+        tool.name, tool.version
+    ));
+    let signature_url_redacted = redact_url(signature_url);
+    info!(
+        "Downloading signature {signature_url_redacted} for {}",
+        redact_url(&artifact.url)
+    );
+    download_from_mirrors(workspace, signature_url, headers, &signature_path)?;
+    let signature = std::io::read_to_string(adapter.read_file(&signature_path)?)?;
+    let content = adapter.read_file(download_path)?;
+    public_key.verify(content, &signature).with_context(|| {
+        format!(
+            "Signature verification failed for tool '{}' ({} signature from '{signature_url_redacted}')",
+            tool.name, public_key.format
+        )
+    })
+}
+
+fn determine_file_type(
+    workspace: &Workspace,
+    download_artifact: &DownloadArtifact,
+    download_path: &RelativePathBuf,
+) -> ToolToolResult<FileType> {
+    if let Some(format) = &download_artifact.format {
+        return Ok(format.clone());
+    }
+    let file_type = get_file_type_from_url(&download_artifact.url);
+    // Redirect and API urls often have no usable extension, so look at the content instead
+    if matches!(
+        file_type,
+        FileType::None | FileType::Unknown | FileType::Other(_)
+    ) {
+        let mut download_file = workspace.adapter().read_file(download_path)?;
+        let content_file_type = get_file_type_from_content(download_file.as_mut())?;
+        debug!(
+            "Detected file type {content_file_type:?} from content of {}",
+            redact_url(&download_artifact.url)
+        );
+        if content_file_type != FileType::Unknown {
+            return Ok(content_file_type);
+        }
+    }
+    Ok(file_type)
+}
+
 fn extract_tool(
     workspace: &Workspace,
     tool: &ToolConfiguration,
@@ -142,22 +549,54 @@ fn extract_tool(
     download_path: &RelativePathBuf,
     file_type: FileType,
 ) -> ToolToolResult<()> {
+    let untar = |compression: Compression| {
+        extract_tar(
+            workspace,
+            &tool.extract,
+            compression,
+            download_path,
+            tool_path,
+        )
+    };
+    // Single compressed binaries carry no reliable extension, so name them after the tool
+    let single_file_path = || {
+        if workspace.adapter().get_platform() == DownloadPlatform::Windows {
+            tool_path.join(format!("{}.exe", tool.name))
+        } else {
+            tool_path.join(&tool.name)
+        }
+    };
+    let decompress = |compression: Compression| {
+        extract_single_file(workspace, compression, download_path, &single_file_path())
+    };
     match file_type {
         FileType::Zip => {
-            extract_zip(workspace, download_path, tool_path)?;
-        }
-        FileType::TarGz => {
-            extract_targz(workspace, download_path, tool_path)?;
+            extract_zip(workspace, &tool.extract, download_path, tool_path)?;
         }
+        FileType::Tar => untar(Compression::None)?,
+        FileType::TarGz => untar(Compression::Gzip)?,
+        FileType::TarXz => untar(Compression::Xz)?,
+        FileType::TarBz2 => untar(Compression::Bzip2)?,
+        FileType::TarZst => untar(Compression::Zstd)?,
+        FileType::Gz => decompress(Compression::Gzip)?,
+        FileType::Xz => decompress(Compression::Xz)?,
+        FileType::Bz2 => decompress(Compression::Bzip2)?,
+        FileType::Zst => decompress(Compression::Zstd)?,
         FileType::Exe => {
-            extract_exe(
+            extract_single_file(
                 workspace,
+                Compression::None,
                 download_path,
                 &tool_path.join(format!("{}.exe", tool.name)),
             )?;
         }
         FileType::None => {
-            extract_exe(workspace, download_path, &tool_path.join(&tool.name))?;
+            extract_single_file(
+                workspace,
+                Compression::None,
+                download_path,
+                &tool_path.join(&tool.name),
+            )?;
         }
         FileType::Unknown => {
             return Err(err!(
@@ -171,91 +610,3 @@ fn extract_tool(
     }
     Ok(())
 }
-
-fn extract_zip(
-    workspace: &Workspace,
-    zip_path: &RelativePathBuf,
-    destination_path: &RelativePathBuf,
-) -> ToolToolResult<()> {
-    let adapter = workspace.adapter();
-    let mut archive = zip::ZipArchive::new(adapter.read_file(zip_path)?)?;
-
-    for i in 0..archive.len() {
-        let mut zip_entry = archive.by_index(i).unwrap();
-        let outpath = match zip_entry.enclosed_name() {
-            Some(path) => path,
-            None => continue,
-        };
-
-        // TODO: check file does not escape
-        let relative_path_buf = RelativePathBuf::from_path(outpath)?;
-        // TODO: make skip_components configurable
-        let mut components = relative_path_buf.components();
-        components.next();
-        let relative_path_buf = components.as_relative_path();
-        let joined_path = destination_path.join(relative_path_buf);
-        if zip_entry.is_dir() {
-            adapter.create_directory_all(&joined_path)?;
-        } else {
-            if let Some(parent_path) = joined_path.parent() {
-                adapter.create_directory_all(&parent_path.to_relative_path_buf())?;
-            }
-            let mut outfile = adapter.create_file(&joined_path)?;
-            std::io::copy(&mut zip_entry, &mut outfile)?;
-        }
-    }
-    Ok(())
-}
-
-fn extract_targz(
-    workspace: &Workspace,
-    targz_path: &RelativePathBuf,
-    destination_path: &RelativePathBuf,
-) -> ToolToolResult<()> {
-    let adapter = workspace.adapter();
-    let mut archive = tar::Archive::new(GzDecoder::new(adapter.read_file(targz_path)?));
-    for archive_entry in archive.entries()? {
-        let mut archive_entry = archive_entry?;
-        let outpath = archive_entry.path()?;
-
-        // TODO: check file does not escape
-        let relative_path_buf = RelativePathBuf::from_path(outpath)?;
-        // TODO: make skip_components configurable
-        let mut components = relative_path_buf.components();
-        components.next();
-        let relative_path_buf = components.as_relative_path();
-        let joined_path = destination_path.join(relative_path_buf);
-        match archive_entry.header().entry_type() {
-            EntryType::Directory => {
-                adapter.create_directory_all(&joined_path)?;
-            }
-            EntryType::Regular => {
-                if let Some(parent_path) = joined_path.parent() {
-                    adapter.create_directory_all(&parent_path.to_relative_path_buf())?;
-                }
-                let mut outfile = adapter.create_file(&joined_path)?;
-                std::io::copy(&mut archive_entry, &mut outfile)?;
-            }
-            _ => {}
-        }
-    }
-    Ok(())
-}
-
-fn extract_exe(
-    workspace: &Workspace,
-    output_directory: &RelativePathBuf,
-    destination_path: &RelativePathBuf,
-) -> ToolToolResult<()> {
-    let adapter = workspace.adapter();
-    if let Some(parent_path) = destination_path.parent() {
-        adapter.create_directory_all(&parent_path.to_relative_path_buf())?;
-    }
-    {
-        let mut infile = adapter.read_file(output_directory)?;
-        let mut outfile = adapter.create_file(destination_path)?;
-        std::io::copy(&mut infile, &mut outfile)?;
-    }
-    adapter.make_file_executable(destination_path)?;
-    Ok(())
-}
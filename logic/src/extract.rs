@@ -0,0 +1,1087 @@
+use crate::adapter::Adapter;
+use crate::configuration::platform::DownloadPlatform;
+use crate::configuration::{ExtractConfiguration, GlobPattern};
+use crate::file_type::{Compression, EXECUTABLE_HEADER_SIZE, is_native_executable, read_prefix};
+use crate::workspace::Workspace;
+use relative_path::{Component, RelativePath, RelativePathBuf};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use tar::EntryType;
+use tool_tool_base::result::{ToolToolResult, bail, err};
+use tracing::{debug, warn};
+
+/// Format-independent kind of an archive entry
+pub(crate) enum ArchiveEntryKind {
+    Directory,
+    /// Regular file, with its unix permission bits if the archive records them
+    File {
+        mode: Option<u32>,
+    },
+    /// Symbolic link, the target is relative to the directory containing the link
+    Symlink {
+        target: String,
+    },
+    /// Hard link, the target is the path of an earlier entry in the archive
+    Hardlink {
+        target: String,
+        mode: Option<u32>,
+    },
+}
+
+/// Decides which archive entries get extracted, and maps them to their destination path
+struct ArchiveEntryMapper<'a> {
+    extract: &'a ExtractConfiguration,
+}
+
+impl<'a> ArchiveEntryMapper<'a> {
+    fn new(extract: &'a ExtractConfiguration) -> Self {
+        Self { extract }
+    }
+
+    /// Returns the path relative to the destination directory, or `None` if the entry should be skipped
+    fn map(&self, entry_path: &RelativePath) -> Option<RelativePathBuf> {
+        let mut components = entry_path.components();
+        for _ in 0..self.extract.strip_components {
+            components.next()?;
+        }
+        let mut path = components.as_relative_path();
+        if let Some(subdir) = &self.extract.subdir {
+            path = path.strip_prefix(subdir).ok()?;
+        }
+        if path.as_str().is_empty() {
+            return None;
+        }
+        // A pattern matching a directory applies to everything below it
+        let matches_any = |patterns: &[GlobPattern]| {
+            let mut candidate = Some(path);
+            while let Some(current) = candidate.filter(|current| !current.as_str().is_empty()) {
+                if patterns
+                    .iter()
+                    .any(|pattern| pattern.matches(current.as_str()))
+                {
+                    return true;
+                }
+                candidate = current.parent();
+            }
+            false
+        };
+        if !self.extract.include.is_empty() && !matches_any(&self.extract.include) {
+            return None;
+        }
+        if matches_any(&self.extract.exclude) {
+            return None;
+        }
+        Some(path.to_relative_path_buf())
+    }
+}
+
+/// Writes archive entries below a destination directory, refusing any entry that could end up outside of it
+struct ArchiveExtractor<'a> {
+    adapter: &'a dyn Adapter,
+    entry_mapper: ArchiveEntryMapper<'a>,
+    destination_path: &'a RelativePath,
+    /// Extracted symbolic links, keyed by their path in lower case on platforms whose file
+    /// systems are usually case-insensitive, where `LIB/file` would be written through `lib`
+    symlinks: BTreeMap<RelativePathBuf, RelativePathBuf>,
+    case_insensitive: bool,
+}
+
+impl<'a> ArchiveExtractor<'a> {
+    fn new(
+        workspace: &'a Workspace,
+        extract: &'a ExtractConfiguration,
+        destination_path: &'a RelativePath,
+    ) -> ToolToolResult<Self> {
+        Ok(Self {
+            adapter: workspace.adapter(),
+            entry_mapper: ArchiveEntryMapper::new(extract),
+            destination_path,
+            symlinks: BTreeMap::new(),
+            case_insensitive: matches!(
+                workspace.adapter().get_platform(),
+                DownloadPlatform::MacOS | DownloadPlatform::Windows
+            ),
+        })
+    }
+
+    fn symlink_key(&self, path: &RelativePath) -> RelativePathBuf {
+        if self.case_insensitive {
+            RelativePathBuf::from(path.as_str().to_lowercase())
+        } else {
+            path.to_relative_path_buf()
+        }
+    }
+
+    fn extract_entry(
+        &mut self,
+        entry_name: &str,
+        kind: ArchiveEntryKind,
+        content: &mut dyn Read,
+    ) -> ToolToolResult<()> {
+        let entry_path = sanitize_archive_path(entry_name)
+            .map_err(|reason| err!("Unsafe archive entry '{entry_name}': path {reason}"))?;
+        let Some(relative_path) = self.entry_mapper.map(&entry_path) else {
+            return Ok(());
+        };
+        let key = self.symlink_key(&relative_path);
+        if let Some((_, symlink)) = self
+            .symlinks
+            .iter()
+            .find(|(symlink_key, _)| key.starts_with(symlink_key))
+        {
+            bail!("Unsafe archive entry '{entry_name}': path traverses symbolic link '{symlink}'");
+        }
+        let joined_path = self.destination_path.join(&relative_path);
+        match kind {
+            ArchiveEntryKind::Directory => {
+                self.adapter.create_directory_all(&joined_path)?;
+            }
+            ArchiveEntryKind::File { mode } => {
+                self.create_parent_directory(&joined_path)?;
+                let header = read_prefix(content, EXECUTABLE_HEADER_SIZE)?;
+                {
+                    let mut outfile = self.adapter.create_file(&joined_path)?;
+                    outfile.write_all(&header)?;
+                    std::io::copy(content, &mut outfile)?;
+                }
+                self.set_mode(&joined_path, mode)?;
+                // Archives created on Windows often lack execute permissions, binaries need them to be runnable
+                if is_native_executable(&header) && mode.is_none_or(|mode| mode & 0o111 == 0) {
+                    self.adapter.make_file_executable(&joined_path)?;
+                }
+            }
+            ArchiveEntryKind::Symlink { target } => {
+                check_symlink_target(entry_name, &relative_path, &target)?;
+                self.create_parent_directory(&joined_path)?;
+                self.adapter.create_symlink(&joined_path, &target)?;
+                self.symlinks.insert(key, relative_path);
+            }
+            ArchiveEntryKind::Hardlink { target, mode } => {
+                let target_path = sanitize_archive_path(&target).map_err(|reason| {
+                    err!(
+                        "Unsafe archive entry '{entry_name}': hard link target '{target}' {reason}"
+                    )
+                })?;
+                let Some(target_path) = self.entry_mapper.map(&target_path) else {
+                    warn!(
+                        "Skipping hard link '{entry_name}', since its target '{target}' is not extracted"
+                    );
+                    return Ok(());
+                };
+                // Hard links are reproduced as copies, so they work on every platform and file system
+                self.create_parent_directory(&joined_path)?;
+                {
+                    let mut infile = self
+                        .adapter
+                        .read_file(&self.destination_path.join(target_path))?;
+                    let mut outfile = self.adapter.create_file(&joined_path)?;
+                    std::io::copy(&mut infile, &mut outfile)?;
+                }
+                self.set_mode(&joined_path, mode)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn create_parent_directory(&self, path: &RelativePath) -> ToolToolResult<()> {
+        if let Some(parent_path) = path.parent() {
+            self.adapter
+                .create_directory_all(&parent_path.to_relative_path_buf())?;
+        }
+        Ok(())
+    }
+
+    /// Applies the permission bits recorded in the archive, the owner always keeps read and write access
+    fn set_mode(&self, path: &RelativePath, mode: Option<u32>) -> ToolToolResult<()> {
+        let Some(mode) = mode.map(|mode| mode & 0o777).filter(|mode| *mode != 0) else {
+            return Ok(());
+        };
+        self.adapter
+            .set_file_mode(&path.to_relative_path_buf(), mode | 0o600)
+    }
+}
+
+fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/')
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+/// Converts a path inside an archive to a relative path, rejecting paths that could escape the destination directory
+///
+/// On failure the reason is returned, so callers can name the offending entry
+fn sanitize_archive_path(path: &str) -> Result<RelativePathBuf, &'static str> {
+    let path = path.replace('\\', "/");
+    if path.contains('\0') {
+        return Err("contains a NUL byte");
+    }
+    if is_absolute(&path) {
+        return Err("is absolute");
+    }
+    let path = RelativePathBuf::from(path);
+    if path
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return Err("contains '..'");
+    }
+    Ok(path)
+}
+
+/// Ensures a symbolic link at `link_path` (relative to the destination directory) does not point outside of it
+///
+/// Only leading `..` components are allowed, so the target can be resolved without knowing about other links
+fn check_symlink_target(
+    entry_name: &str,
+    link_path: &RelativePath,
+    target: &str,
+) -> ToolToolResult<()> {
+    let target = target.replace('\\', "/");
+    if target.is_empty() || target.contains('\0') {
+        bail!("Unsafe archive entry '{entry_name}': invalid symbolic link target '{target}'");
+    }
+    if is_absolute(&target) {
+        bail!("Unsafe archive entry '{entry_name}': symbolic link target '{target}' is absolute");
+    }
+    let mut depth = link_path
+        .parent()
+        .map(|parent| parent.components().count())
+        .unwrap_or(0);
+    let mut seen_name = false;
+    for component in RelativePath::new(&target).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if seen_name => {
+                bail!(
+                    "Unsafe archive entry '{entry_name}': symbolic link target '{target}' contains '..' after a directory name"
+                );
+            }
+            Component::ParentDir => {
+                if depth == 0 {
+                    bail!(
+                        "Unsafe archive entry '{entry_name}': symbolic link target '{target}' points outside the tool directory"
+                    );
+                }
+                depth -= 1;
+            }
+            Component::Normal(_) => {
+                seen_name = true;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn extract_zip(
+    workspace: &Workspace,
+    extract: &ExtractConfiguration,
+    zip_path: &RelativePathBuf,
+    destination_path: &RelativePathBuf,
+) -> ToolToolResult<()> {
+    let mut archive = zip::ZipArchive::new(workspace.adapter().read_file(zip_path)?)?;
+    let mut extractor = ArchiveExtractor::new(workspace, extract, destination_path)?;
+    for i in 0..archive.len() {
+        let mut zip_entry = archive.by_index(i)?;
+        let entry_name = zip_entry.name().to_string();
+        let kind = if zip_entry.is_dir() {
+            ArchiveEntryKind::Directory
+        } else if zip_entry.is_symlink() {
+            let mut target = String::new();
+            zip_entry.read_to_string(&mut target)?;
+            ArchiveEntryKind::Symlink { target }
+        } else {
+            ArchiveEntryKind::File {
+                mode: zip_entry.unix_mode(),
+            }
+        };
+        extractor.extract_entry(&entry_name, kind, &mut zip_entry)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn extract_tar(
+    workspace: &Workspace,
+    extract: &ExtractConfiguration,
+    compression: Compression,
+    tar_path: &RelativePathBuf,
+    destination_path: &RelativePathBuf,
+) -> ToolToolResult<()> {
+    let adapter = workspace.adapter();
+    let mut archive = tar::Archive::new(compression.decompress(adapter.read_file(tar_path)?)?);
+    let mut extractor = ArchiveExtractor::new(workspace, extract, destination_path)?;
+    for archive_entry in archive.entries()? {
+        let mut archive_entry = archive_entry?;
+        let entry_name = String::from_utf8(archive_entry.path_bytes().into_owned())
+            .map_err(|_| err!("Archive entry path is not valid UTF-8"))?;
+        let link_target = || -> ToolToolResult<String> {
+            let link_name = archive_entry
+                .link_name_bytes()
+                .ok_or_else(|| err!("Archive entry '{entry_name}' has no link target"))?;
+            String::from_utf8(link_name.into_owned())
+                .map_err(|_| err!("Link target of archive entry '{entry_name}' is not valid UTF-8"))
+        };
+        let mode = archive_entry.header().mode().ok();
+        let kind = match archive_entry.header().entry_type() {
+            EntryType::Directory => ArchiveEntryKind::Directory,
+            EntryType::Regular | EntryType::Continuous => ArchiveEntryKind::File { mode },
+            EntryType::Symlink => ArchiveEntryKind::Symlink {
+                target: link_target()?,
+            },
+            EntryType::Link => ArchiveEntryKind::Hardlink {
+                target: link_target()?,
+                mode,
+            },
+            EntryType::Char | EntryType::Block | EntryType::Fifo => {
+                bail!(
+                    "Unsafe archive entry '{entry_name}': device nodes and fifos are not supported"
+                );
+            }
+            other => {
+                debug!("Skipping archive entry '{entry_name}' of type {other:?}");
+                continue;
+            }
+        };
+        extractor.extract_entry(&entry_name, kind, &mut archive_entry)?;
+    }
+    Ok(())
+}
+
+/// Decompresses a single file artifact (e.g. `tool.gz`) into an executable
+pub(crate) fn extract_single_file(
+    workspace: &Workspace,
+    compression: Compression,
+    source_path: &RelativePathBuf,
+    destination_path: &RelativePathBuf,
+) -> ToolToolResult<()> {
+    let adapter = workspace.adapter();
+    if let Some(parent_path) = destination_path.parent() {
+        adapter.create_directory_all(&parent_path.to_relative_path_buf())?;
+    }
+    {
+        let mut infile = compression.decompress(adapter.read_file(source_path)?)?;
+        let mut outfile = adapter.create_file(destination_path)?;
+        std::io::copy(&mut infile, &mut outfile)?;
+    }
+    adapter.make_file_executable(destination_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::Adapter;
+    use crate::mock_adapter::MockAdapter;
+    use crate::runner_initial::load_config;
+    use crate::test_util::archive_builder::ArchiveBuilder;
+    use crate::test_util::compressor::{
+        Bzip2Compressor, Compressor, GzipCompressor, NoCompressor, XzCompressor, ZstdCompressor,
+    };
+    use crate::test_util::tar_builder::{
+        CompressedTarBuilder, TarBuilder, TarBz2Builder, TarGzBuilder, TarXzBuilder, TarZstBuilder,
+    };
+    use crate::test_util::zip_builder::ZipBuilder;
+    use expect_test::{Expect, expect};
+    use std::fmt::Write;
+    use std::sync::Arc;
+
+    const ENTRIES: &[&str] = &[
+        "lua-5.4.6/",
+        "lua-5.4.6/bin/lua.exe",
+        "lua-5.4.6/bin/lua54.dll",
+        "lua-5.4.6/doc/readme.txt",
+        "lua-5.4.6/include/lua.h",
+        "lua-5.4.6/license.txt",
+    ];
+
+    fn test_map(extract: ExtractConfiguration, expected: Expect) -> ToolToolResult<()> {
+        let entry_mapper = ArchiveEntryMapper::new(&extract);
+        let mut actual = String::new();
+        for entry in ENTRIES {
+            match entry_mapper.map(RelativePath::new(entry)) {
+                Some(path) => writeln!(actual, "{entry} -> {path}")?,
+                None => writeln!(actual, "{entry} (SKIPPED)")?,
+            }
+        }
+        expected.assert_eq(&actual);
+        Ok(())
+    }
+
+    #[test]
+    fn map_default() -> ToolToolResult<()> {
+        test_map(
+            ExtractConfiguration::default(),
+            expect![[r#"
+                lua-5.4.6/ (SKIPPED)
+                lua-5.4.6/bin/lua.exe -> bin/lua.exe
+                lua-5.4.6/bin/lua54.dll -> bin/lua54.dll
+                lua-5.4.6/doc/readme.txt -> doc/readme.txt
+                lua-5.4.6/include/lua.h -> include/lua.h
+                lua-5.4.6/license.txt -> license.txt
+            "#]],
+        )
+    }
+
+    #[test]
+    fn map_no_strip_components() -> ToolToolResult<()> {
+        test_map(
+            ExtractConfiguration {
+                strip_components: 0,
+                ..ExtractConfiguration::default()
+            },
+            expect![[r#"
+                lua-5.4.6/ -> lua-5.4.6/
+                lua-5.4.6/bin/lua.exe -> lua-5.4.6/bin/lua.exe
+                lua-5.4.6/bin/lua54.dll -> lua-5.4.6/bin/lua54.dll
+                lua-5.4.6/doc/readme.txt -> lua-5.4.6/doc/readme.txt
+                lua-5.4.6/include/lua.h -> lua-5.4.6/include/lua.h
+                lua-5.4.6/license.txt -> lua-5.4.6/license.txt
+            "#]],
+        )
+    }
+
+    #[test]
+    fn map_strip_too_many_components() -> ToolToolResult<()> {
+        test_map(
+            ExtractConfiguration {
+                strip_components: 3,
+                ..ExtractConfiguration::default()
+            },
+            expect![[r#"
+                lua-5.4.6/ (SKIPPED)
+                lua-5.4.6/bin/lua.exe (SKIPPED)
+                lua-5.4.6/bin/lua54.dll (SKIPPED)
+                lua-5.4.6/doc/readme.txt (SKIPPED)
+                lua-5.4.6/include/lua.h (SKIPPED)
+                lua-5.4.6/license.txt (SKIPPED)
+            "#]],
+        )
+    }
+
+    #[test]
+    fn map_subdir() -> ToolToolResult<()> {
+        test_map(
+            ExtractConfiguration {
+                strip_components: 0,
+                subdir: Some("lua-5.4.6/bin".to_string()),
+                ..ExtractConfiguration::default()
+            },
+            expect![[r#"
+                lua-5.4.6/ (SKIPPED)
+                lua-5.4.6/bin/lua.exe -> lua.exe
+                lua-5.4.6/bin/lua54.dll -> lua54.dll
+                lua-5.4.6/doc/readme.txt (SKIPPED)
+                lua-5.4.6/include/lua.h (SKIPPED)
+                lua-5.4.6/license.txt (SKIPPED)
+            "#]],
+        )
+    }
+
+    #[test]
+    fn map_include() -> ToolToolResult<()> {
+        test_map(
+            ExtractConfiguration {
+                include: vec![GlobPattern::new("bin")?, GlobPattern::new("*.txt")?],
+                ..ExtractConfiguration::default()
+            },
+            expect![[r#"
+                lua-5.4.6/ (SKIPPED)
+                lua-5.4.6/bin/lua.exe -> bin/lua.exe
+                lua-5.4.6/bin/lua54.dll -> bin/lua54.dll
+                lua-5.4.6/doc/readme.txt (SKIPPED)
+                lua-5.4.6/include/lua.h (SKIPPED)
+                lua-5.4.6/license.txt -> license.txt
+            "#]],
+        )
+    }
+
+    #[test]
+    fn map_exclude() -> ToolToolResult<()> {
+        test_map(
+            ExtractConfiguration {
+                exclude: vec![GlobPattern::new("doc")?, GlobPattern::new("**/*.dll")?],
+                ..ExtractConfiguration::default()
+            },
+            expect![[r#"
+                lua-5.4.6/ (SKIPPED)
+                lua-5.4.6/bin/lua.exe -> bin/lua.exe
+                lua-5.4.6/bin/lua54.dll (SKIPPED)
+                lua-5.4.6/doc/readme.txt (SKIPPED)
+                lua-5.4.6/include/lua.h -> include/lua.h
+                lua-5.4.6/license.txt -> license.txt
+            "#]],
+        )
+    }
+
+    type ExtractFn<'a> = &'a dyn Fn(
+        &Workspace,
+        &ExtractConfiguration,
+        &RelativePathBuf,
+        &RelativePathBuf,
+    ) -> ToolToolResult<()>;
+
+    fn test_extract(
+        extract_fn: ExtractFn,
+        archive: Vec<u8>,
+        expected: Expect,
+    ) -> ToolToolResult<()> {
+        let adapter = MockAdapter::new();
+        adapter.try_lock()?;
+        adapter.set_file("archive", archive);
+        let workspace = Workspace::new(load_config(&adapter)?, Arc::new(adapter.clone()));
+        adapter.clear_effects();
+        let extract = ExtractConfiguration {
+            strip_components: 0,
+            ..ExtractConfiguration::default()
+        };
+        let result = extract_fn(
+            &workspace,
+            &extract,
+            &RelativePathBuf::from("archive"),
+            &RelativePathBuf::from("tool"),
+        );
+        let mut actual = adapter.get_effects();
+        if let Err(error) = result {
+            writeln!(actual, "ERROR: {error:#}")?;
+        }
+        expected.assert_eq(&actual);
+        Ok(())
+    }
+
+    fn test_extract_tar<C: Compressor>(
+        compression: Compression,
+        build: impl FnOnce(&mut CompressedTarBuilder<C>) -> ToolToolResult<()>,
+        expected: Expect,
+    ) -> ToolToolResult<()> {
+        let mut builder = CompressedTarBuilder::<C>::default();
+        build(&mut builder)?;
+        test_extract(
+            &|workspace, extract, archive_path, destination_path| {
+                extract_tar(
+                    workspace,
+                    extract,
+                    compression,
+                    archive_path,
+                    destination_path,
+                )
+            },
+            builder.build()?,
+            expected,
+        )
+    }
+
+    fn test_extract_targz(
+        build: impl FnOnce(&mut TarGzBuilder) -> ToolToolResult<()>,
+        expected: Expect,
+    ) -> ToolToolResult<()> {
+        test_extract_tar(Compression::Gzip, build, expected)
+    }
+
+    fn test_extract_zip(
+        build: impl FnOnce(&mut ZipBuilder) -> ToolToolResult<()>,
+        expected: Expect,
+    ) -> ToolToolResult<()> {
+        let mut builder = ZipBuilder::default();
+        build(&mut builder)?;
+        test_extract(&extract_zip, builder.build()?, expected)
+    }
+
+    #[test]
+    fn targz_safe_entries() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| {
+                builder.add_directory("bin")?;
+                builder.add_file("bin/tool", b"tool")?;
+                builder.add_symlink("lib/tool", "../bin/tool")?;
+                builder.add_hardlink("bin/tool2", "bin/tool")?;
+                Ok(())
+            },
+            expect![[r#"
+                READ FILE: archive
+                CREATE DIR: tool/bin
+                CREATE DIR: tool/bin
+                CREATE FILE: tool/bin/tool
+                WRITE FILE: tool/bin/tool -> tool
+                CREATE DIR: tool/lib
+                CREATE SYMLINK: tool/lib/tool -> ../bin/tool
+                CREATE DIR: tool/bin
+                READ FILE: tool/bin/tool
+                CREATE FILE: tool/bin/tool2
+                WRITE FILE: tool/bin/tool2 -> tool
+            "#]],
+        )
+    }
+
+    fn build_node_like_archive<T: ArchiveBuilder>(builder: &mut T) -> ToolToolResult<()> {
+        builder.add_executable("node/bin/node", b"node")?;
+        builder.add_file("node/lib/node_modules/npm/bin/npm-cli.js", b"npm")?;
+        builder.add_symlink("node/bin/npm", "../lib/node_modules/npm/bin/npm-cli.js")?;
+        Ok(())
+    }
+
+    #[test]
+    fn targz_preserves_modes_and_symlinks() -> ToolToolResult<()> {
+        test_extract_targz(
+            build_node_like_archive,
+            expect![[r#"
+                READ FILE: archive
+                CREATE DIR: tool/node/bin
+                CREATE FILE: tool/node/bin/node
+                WRITE FILE: tool/node/bin/node -> node
+                SET MODE: tool/node/bin/node 755
+                CREATE DIR: tool/node/lib/node_modules/npm/bin
+                CREATE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js
+                WRITE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js -> npm
+                CREATE DIR: tool/node/bin
+                CREATE SYMLINK: tool/node/bin/npm -> ../lib/node_modules/npm/bin/npm-cli.js
+            "#]],
+        )
+    }
+
+    #[test]
+    fn zip_preserves_modes_and_symlinks() -> ToolToolResult<()> {
+        test_extract_zip(
+            build_node_like_archive,
+            expect![[r#"
+                READ FILE: archive
+                CREATE DIR: tool/node/bin
+                CREATE FILE: tool/node/bin/node
+                WRITE FILE: tool/node/bin/node -> node
+                SET MODE: tool/node/bin/node 755
+                CREATE DIR: tool/node/lib/node_modules/npm/bin
+                CREATE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js
+                WRITE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js -> npm
+                SET MODE: tool/node/lib/node_modules/npm/bin/npm-cli.js 644
+                CREATE DIR: tool/node/bin
+                CREATE SYMLINK: tool/node/bin/npm -> ../lib/node_modules/npm/bin/npm-cli.js
+            "#]],
+        )
+    }
+
+    #[test]
+    fn tar_round_trip() -> ToolToolResult<()> {
+        test_extract_tar(
+            Compression::None,
+            build_node_like_archive::<TarBuilder>,
+            expect![[r#"
+                READ FILE: archive
+                CREATE DIR: tool/node/bin
+                CREATE FILE: tool/node/bin/node
+                WRITE FILE: tool/node/bin/node -> node
+                SET MODE: tool/node/bin/node 755
+                CREATE DIR: tool/node/lib/node_modules/npm/bin
+                CREATE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js
+                WRITE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js -> npm
+                CREATE DIR: tool/node/bin
+                CREATE SYMLINK: tool/node/bin/npm -> ../lib/node_modules/npm/bin/npm-cli.js
+            "#]],
+        )
+    }
+
+    #[test]
+    fn tarxz_round_trip() -> ToolToolResult<()> {
+        test_extract_tar(
+            Compression::Xz,
+            build_node_like_archive::<TarXzBuilder>,
+            expect![[r#"
+                READ FILE: archive
+                CREATE DIR: tool/node/bin
+                CREATE FILE: tool/node/bin/node
+                WRITE FILE: tool/node/bin/node -> node
+                SET MODE: tool/node/bin/node 755
+                CREATE DIR: tool/node/lib/node_modules/npm/bin
+                CREATE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js
+                WRITE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js -> npm
+                CREATE DIR: tool/node/bin
+                CREATE SYMLINK: tool/node/bin/npm -> ../lib/node_modules/npm/bin/npm-cli.js
+            "#]],
+        )
+    }
+
+    #[test]
+    fn tarbz2_round_trip() -> ToolToolResult<()> {
+        test_extract_tar(
+            Compression::Bzip2,
+            build_node_like_archive::<TarBz2Builder>,
+            expect![[r#"
+                READ FILE: archive
+                CREATE DIR: tool/node/bin
+                CREATE FILE: tool/node/bin/node
+                WRITE FILE: tool/node/bin/node -> node
+                SET MODE: tool/node/bin/node 755
+                CREATE DIR: tool/node/lib/node_modules/npm/bin
+                CREATE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js
+                WRITE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js -> npm
+                CREATE DIR: tool/node/bin
+                CREATE SYMLINK: tool/node/bin/npm -> ../lib/node_modules/npm/bin/npm-cli.js
+            "#]],
+        )
+    }
+
+    #[test]
+    fn tarzst_round_trip() -> ToolToolResult<()> {
+        test_extract_tar(
+            Compression::Zstd,
+            build_node_like_archive::<TarZstBuilder>,
+            expect![[r#"
+                READ FILE: archive
+                CREATE DIR: tool/node/bin
+                CREATE FILE: tool/node/bin/node
+                WRITE FILE: tool/node/bin/node -> node
+                SET MODE: tool/node/bin/node 755
+                CREATE DIR: tool/node/lib/node_modules/npm/bin
+                CREATE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js
+                WRITE FILE: tool/node/lib/node_modules/npm/bin/npm-cli.js -> npm
+                CREATE DIR: tool/node/bin
+                CREATE SYMLINK: tool/node/bin/npm -> ../lib/node_modules/npm/bin/npm-cli.js
+            "#]],
+        )
+    }
+
+    fn test_extract_single_file<C: Compressor>(
+        compression: Compression,
+        expected: Expect,
+    ) -> ToolToolResult<()> {
+        test_extract(
+            &|workspace, _extract, source_path, _destination_path| {
+                extract_single_file(
+                    workspace,
+                    compression,
+                    source_path,
+                    &RelativePathBuf::from("tool/bin/tool"),
+                )
+            },
+            C::compress(b"#!/bin/sh\necho tool")?,
+            expected,
+        )
+    }
+
+    #[test]
+    fn single_file_uncompressed() -> ToolToolResult<()> {
+        test_extract_single_file::<NoCompressor>(
+            Compression::None,
+            expect![[r#"
+                CREATE DIR: tool/bin
+                READ FILE: archive
+                CREATE FILE: tool/bin/tool
+                WRITE FILE: tool/bin/tool -> #!/bin/sh
+                echo tool
+                MAKE EXECUTABLE: tool/bin/tool
+            "#]],
+        )
+    }
+
+    #[test]
+    fn single_file_gz() -> ToolToolResult<()> {
+        test_extract_single_file::<GzipCompressor>(
+            Compression::Gzip,
+            expect![[r#"
+                CREATE DIR: tool/bin
+                READ FILE: archive
+                CREATE FILE: tool/bin/tool
+                WRITE FILE: tool/bin/tool -> #!/bin/sh
+                echo tool
+                MAKE EXECUTABLE: tool/bin/tool
+            "#]],
+        )
+    }
+
+    #[test]
+    fn single_file_xz() -> ToolToolResult<()> {
+        test_extract_single_file::<XzCompressor>(
+            Compression::Xz,
+            expect![[r#"
+                CREATE DIR: tool/bin
+                READ FILE: archive
+                CREATE FILE: tool/bin/tool
+                WRITE FILE: tool/bin/tool -> #!/bin/sh
+                echo tool
+                MAKE EXECUTABLE: tool/bin/tool
+            "#]],
+        )
+    }
+
+    #[test]
+    fn single_file_bz2() -> ToolToolResult<()> {
+        test_extract_single_file::<Bzip2Compressor>(
+            Compression::Bzip2,
+            expect![[r#"
+                CREATE DIR: tool/bin
+                READ FILE: archive
+                CREATE FILE: tool/bin/tool
+                WRITE FILE: tool/bin/tool -> #!/bin/sh
+                echo tool
+                MAKE EXECUTABLE: tool/bin/tool
+            "#]],
+        )
+    }
+
+    #[test]
+    fn single_file_zst() -> ToolToolResult<()> {
+        test_extract_single_file::<ZstdCompressor>(
+            Compression::Zstd,
+            expect![[r#"
+                CREATE DIR: tool/bin
+                READ FILE: archive
+                CREATE FILE: tool/bin/tool
+                WRITE FILE: tool/bin/tool -> #!/bin/sh
+                echo tool
+                MAKE EXECUTABLE: tool/bin/tool
+            "#]],
+        )
+    }
+
+    #[test]
+    fn single_file_corrupt() -> ToolToolResult<()> {
+        test_extract(
+            &|workspace, _extract, source_path, destination_path| {
+                extract_single_file(workspace, Compression::Xz, source_path, destination_path)
+            },
+            b"not xz".to_vec(),
+            expect![[r#"
+                CREATE DIR: 
+                READ FILE: archive
+                CREATE FILE: tool
+                WRITE FILE: tool -> 
+                ERROR: invalid XZ magic bytes
+            "#]],
+        )
+    }
+
+    const MACH_O_HEADER: &[u8] = b"\xcf\xfa\xed\xfe\x0c\x00\x00\x01";
+    const UNIVERSAL_BINARY_HEADER: &[u8] = b"\xca\xfe\xba\xbe\x00\x00\x00\x02";
+    const ELF_HEADER: &[u8] = b"\x7fELF\x02\x01\x01\x00";
+
+    #[test]
+    fn targz_marks_native_executables() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| {
+                builder.add_file("bin/tool-macos", MACH_O_HEADER)?;
+                builder.add_file("bin/tool-universal", UNIVERSAL_BINARY_HEADER)?;
+                builder.add_file("bin/tool-linux", ELF_HEADER)?;
+                builder.add_executable("bin/tool-executable", MACH_O_HEADER)?;
+                builder.add_file("bin/Tool.class", b"\xca\xfe\xba\xbe\x00\x00\x00\x41")?;
+                Ok(())
+            },
+            expect![[r#"
+                READ FILE: archive
+                CREATE DIR: tool/bin
+                CREATE FILE: tool/bin/tool-macos
+                WRITE FILE: tool/bin/tool-macos -> <8 bytes of binary data>
+                MAKE EXECUTABLE: tool/bin/tool-macos
+                CREATE DIR: tool/bin
+                CREATE FILE: tool/bin/tool-universal
+                WRITE FILE: tool/bin/tool-universal -> <8 bytes of binary data>
+                MAKE EXECUTABLE: tool/bin/tool-universal
+                CREATE DIR: tool/bin
+                CREATE FILE: tool/bin/tool-linux
+                WRITE FILE: tool/bin/tool-linux -> <8 bytes of binary data>
+                MAKE EXECUTABLE: tool/bin/tool-linux
+                CREATE DIR: tool/bin
+                CREATE FILE: tool/bin/tool-executable
+                WRITE FILE: tool/bin/tool-executable -> <8 bytes of binary data>
+                SET MODE: tool/bin/tool-executable 755
+                CREATE DIR: tool/bin
+                CREATE FILE: tool/bin/Tool.class
+                WRITE FILE: tool/bin/Tool.class -> <8 bytes of binary data>
+            "#]],
+        )
+    }
+
+    #[test]
+    fn zip_marks_native_executables() -> ToolToolResult<()> {
+        test_extract_zip(
+            |builder| builder.add_file("bin/tool", MACH_O_HEADER),
+            expect![[r#"
+                READ FILE: archive
+                CREATE DIR: tool/bin
+                CREATE FILE: tool/bin/tool
+                WRITE FILE: tool/bin/tool -> <8 bytes of binary data>
+                SET MODE: tool/bin/tool 644
+                MAKE EXECUTABLE: tool/bin/tool
+            "#]],
+        )
+    }
+
+    #[test]
+    fn targz_absolute_path() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| builder.add_file_with_raw_path("/etc/passwd", b"evil"),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry '/etc/passwd': path is absolute
+            "#]],
+        )
+    }
+
+    #[test]
+    fn targz_windows_absolute_path() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| builder.add_file_with_raw_path("C:\\Windows\\evil.dll", b"evil"),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry 'C:\Windows\evil.dll': path is absolute
+            "#]],
+        )
+    }
+
+    #[test]
+    fn targz_parent_dir() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| builder.add_file_with_raw_path("foo/../../evil", b"evil"),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry 'foo/../../evil': path contains '..'
+            "#]],
+        )
+    }
+
+    #[test]
+    fn targz_symlink_escape() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| builder.add_symlink("bin/escape", "../../outside"),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry 'bin/escape': symbolic link target '../../outside' points outside the tool directory
+            "#]],
+        )
+    }
+
+    #[test]
+    fn targz_symlink_absolute() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| builder.add_symlink("etc", "/etc"),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry 'etc': symbolic link target '/etc' is absolute
+            "#]],
+        )
+    }
+
+    #[test]
+    fn targz_symlink_non_leading_parent_dir() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| builder.add_symlink("escape", "sub/../.."),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry 'escape': symbolic link target 'sub/../..' contains '..' after a directory name
+            "#]],
+        )
+    }
+
+    #[test]
+    fn targz_write_through_symlink() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| {
+                builder.add_symlink("lib", "bin")?;
+                builder.add_file("lib/evil", b"evil")?;
+                Ok(())
+            },
+            expect![[r#"
+                READ FILE: archive
+                CREATE DIR: tool
+                CREATE SYMLINK: tool/lib -> bin
+                ERROR: Unsafe archive entry 'lib/evil': path traverses symbolic link 'lib'
+            "#]],
+        )
+    }
+
+    #[test]
+    fn targz_write_through_symlink_case_insensitive() -> ToolToolResult<()> {
+        let mut builder = TarGzBuilder::default();
+        builder.add_symlink("lib", "bin")?;
+        builder.add_file("LIB/evil", b"evil")?;
+        let archive = builder.build()?;
+        let extract = |platform| -> ToolToolResult<String> {
+            let adapter = MockAdapter::new();
+            adapter.set_platform(platform);
+            adapter.try_lock()?;
+            adapter.set_file("archive", archive.clone());
+            let workspace = Workspace::new(load_config(&adapter)?, Arc::new(adapter.clone()));
+            let extract = ExtractConfiguration {
+                strip_components: 0,
+                ..ExtractConfiguration::default()
+            };
+            let result = extract_tar(
+                &workspace,
+                &extract,
+                Compression::Gzip,
+                &RelativePathBuf::from("archive"),
+                &RelativePathBuf::from("tool"),
+            );
+            Ok(result.map_or_else(|error| format!("{error:#}"), |_| "OK".to_string()))
+        };
+        expect!["Unsafe archive entry 'LIB/evil': path traverses symbolic link 'lib'"]
+            .assert_eq(&extract(DownloadPlatform::MacOS)?);
+        expect!["Unsafe archive entry 'LIB/evil': path traverses symbolic link 'lib'"]
+            .assert_eq(&extract(DownloadPlatform::Windows)?);
+        // `LIB` is a separate directory on case-sensitive file systems
+        expect!["OK"].assert_eq(&extract(DownloadPlatform::Linux)?);
+        Ok(())
+    }
+
+    #[test]
+    fn targz_hardlink_escape() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| builder.add_hardlink("passwd", "../../etc/passwd"),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry 'passwd': hard link target '../../etc/passwd' contains '..'
+            "#]],
+        )
+    }
+
+    #[test]
+    fn targz_device_node() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| builder.add_entry("dev/sda", tar::EntryType::Block),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry 'dev/sda': device nodes and fifos are not supported
+            "#]],
+        )
+    }
+
+    #[test]
+    fn targz_fifo() -> ToolToolResult<()> {
+        test_extract_targz(
+            |builder| builder.add_entry("pipe", tar::EntryType::Fifo),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry 'pipe': device nodes and fifos are not supported
+            "#]],
+        )
+    }
+
+    #[test]
+    fn zip_absolute_path() -> ToolToolResult<()> {
+        test_extract_zip(
+            |builder| builder.add_file("/etc/passwd", b"evil"),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry '/etc/passwd': path is absolute
+            "#]],
+        )
+    }
+
+    #[test]
+    fn zip_parent_dir() -> ToolToolResult<()> {
+        test_extract_zip(
+            |builder| builder.add_file("../evil", b"evil"),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry '../evil': path contains '..'
+            "#]],
+        )
+    }
+
+    #[test]
+    fn zip_symlink_escape() -> ToolToolResult<()> {
+        test_extract_zip(
+            |builder| builder.add_symlink("escape", "../outside"),
+            expect![[r#"
+                READ FILE: archive
+                ERROR: Unsafe archive entry 'escape': symbolic link target '../outside' points outside the tool directory
+            "#]],
+        )
+    }
+}
@@ -1,14 +1,94 @@
-#[derive(Debug, PartialEq)]
+use crate::adapter::ReadSeek;
+use bzip2::read::MultiBzDecoder;
+use flate2::read::GzDecoder;
+use lzma_rust2::XzReader;
+use ruzstd::decoding::StreamingDecoder;
+use std::io::{Read, SeekFrom};
+use tool_tool_base::result::{ToolToolResult, err};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum FileType {
     Zip,
+    Tar,
     TarGz,
+    TarXz,
+    TarBz2,
+    TarZst,
+    Gz,
+    Xz,
+    Bz2,
+    Zst,
     Exe,
     None,
     Unknown,
     Other(String),
 }
 
-fn get_filename_from_url(url: &str) -> Option<&str> {
+/// Compression applied on top of a tar archive or a single file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Xz,
+    Bzip2,
+    Zstd,
+}
+
+impl Compression {
+    /// Wraps the reader in the matching streaming decompressor
+    pub(crate) fn decompress<'a>(
+        self,
+        reader: impl Read + 'a,
+    ) -> ToolToolResult<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::None => Box::new(reader),
+            Compression::Gzip => Box::new(GzDecoder::new(reader)),
+            Compression::Xz => Box::new(XzReader::new(reader, true)),
+            Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+            Compression::Zstd => Box::new(
+                StreamingDecoder::new(reader)
+                    .map_err(|error| err!("Failed to read zstd stream: {error}"))?,
+            ),
+        })
+    }
+}
+
+/// Format names accepted by the `format` property of a download entry
+const FORMATS: &[(&str, FileType)] = &[
+    ("zip", FileType::Zip),
+    ("tar", FileType::Tar),
+    ("tar.gz", FileType::TarGz),
+    ("tgz", FileType::TarGz),
+    ("tar.xz", FileType::TarXz),
+    ("txz", FileType::TarXz),
+    ("tar.bz2", FileType::TarBz2),
+    ("tbz2", FileType::TarBz2),
+    ("tar.zst", FileType::TarZst),
+    ("tzst", FileType::TarZst),
+    ("gz", FileType::Gz),
+    ("xz", FileType::Xz),
+    ("bz2", FileType::Bz2),
+    ("zst", FileType::Zst),
+    ("exe", FileType::Exe),
+    ("binary", FileType::None),
+];
+
+pub fn get_file_type_from_format(format: &str) -> ToolToolResult<FileType> {
+    let format = format.trim_start_matches('.');
+    FORMATS
+        .iter()
+        .find(|(name, _)| *name == format)
+        .map(|(_, file_type)| file_type.clone())
+        .ok_or_else(|| {
+            let names: Vec<&str> = FORMATS.iter().map(|(name, _)| *name).collect();
+            err!(
+                "Unknown download format '{format}', expected one of: {}",
+                names.join(", ")
+            )
+        })
+}
+
+pub(crate) fn get_filename_from_url(url: &str) -> Option<&str> {
     // Remove any query string or fragment
     let url = url.split('?').next().unwrap_or(url);
     let url = url.split('#').next().unwrap_or(url);
@@ -26,10 +106,24 @@ pub fn get_file_type_from_url(url: &str) -> FileType {
         FileType::Exe
     } else if filename.ends_with(".zip") {
         FileType::Zip
-    } else if filename.ends_with(".tar.gz") {
+    } else if filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
         FileType::TarGz
+    } else if filename.ends_with(".tar.xz") || filename.ends_with(".txz") {
+        FileType::TarXz
+    } else if filename.ends_with(".tar.bz2") || filename.ends_with(".tbz2") {
+        FileType::TarBz2
+    } else if filename.ends_with(".tar.zst") || filename.ends_with(".tzst") {
+        FileType::TarZst
     } else if filename.ends_with(".tar") {
-        FileType::None
+        FileType::Tar
+    } else if filename.ends_with(".gz") {
+        FileType::Gz
+    } else if filename.ends_with(".xz") {
+        FileType::Xz
+    } else if filename.ends_with(".bz2") {
+        FileType::Bz2
+    } else if filename.ends_with(".zst") {
+        FileType::Zst
     } else {
         let Some((_, extension)) = filename.split_once('.') else {
             return FileType::None;
@@ -38,9 +132,189 @@ pub fn get_file_type_from_url(url: &str) -> FileType {
     }
 }
 
+/// Offset of the "ustar" magic in a tar header
+const TAR_MAGIC_OFFSET: usize = 257;
+
+fn is_tar(header: &[u8]) -> bool {
+    header
+        .get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 5)
+        .is_some_and(|magic| magic == b"ustar")
+}
+
+/// Reads up to `limit` bytes, stopping early only at the end of the stream
+pub(crate) fn read_prefix(reader: &mut dyn Read, limit: usize) -> ToolToolResult<Vec<u8>> {
+    let mut prefix = Vec::with_capacity(limit);
+    reader.take(limit as u64).read_to_end(&mut prefix)?;
+    Ok(prefix)
+}
+
+/// Determines the file type from the magic bytes at the start of the file content
+pub fn get_file_type_from_content(reader: &mut dyn ReadSeek) -> ToolToolResult<FileType> {
+    let header = read_prefix(reader, 512)?;
+    let (compression, tar_file_type, file_type) = if header.starts_with(b"\x1f\x8b") {
+        (Compression::Gzip, FileType::TarGz, FileType::Gz)
+    } else if header.starts_with(b"\xfd7zXZ\x00") {
+        (Compression::Xz, FileType::TarXz, FileType::Xz)
+    } else if header.starts_with(b"BZh") {
+        (Compression::Bzip2, FileType::TarBz2, FileType::Bz2)
+    } else if header.starts_with(b"\x28\xb5\x2f\xfd") {
+        (Compression::Zstd, FileType::TarZst, FileType::Zst)
+    } else {
+        return Ok(get_uncompressed_file_type(&header));
+    };
+    // Peek into the compressed stream to tell tarballs from single compressed files
+    reader.seek(SeekFrom::Start(0))?;
+    let decompressed_header =
+        read_prefix(compression.decompress(&mut *reader)?.as_mut(), 512).unwrap_or_default();
+    Ok(if is_tar(&decompressed_header) {
+        tar_file_type
+    } else {
+        file_type
+    })
+}
+
+/// Number of header bytes needed by [`is_native_executable`]
+pub(crate) const EXECUTABLE_HEADER_SIZE: usize = 8;
+
+fn is_mach_o(header: &[u8]) -> bool {
+    const MACH_O_MAGICS: &[&[u8]] = &[
+        b"\xfe\xed\xfa\xce",
+        b"\xfe\xed\xfa\xcf",
+        b"\xce\xfa\xed\xfe",
+        b"\xcf\xfa\xed\xfe",
+    ];
+    MACH_O_MAGICS.iter().any(|magic| header.starts_with(magic))
+}
+
+/// Universal (fat) binaries share their magic with Java class files, which store a version >= 45 instead of a small architecture count
+fn is_universal_binary(header: &[u8]) -> bool {
+    match header {
+        [0xca, 0xfe, 0xba, 0xbe, a, b, c, d, ..] => {
+            (1..=20).contains(&u32::from_be_bytes([*a, *b, *c, *d]))
+        }
+        _ => false,
+    }
+}
+
+/// Whether the header belongs to an ELF, Mach-O or universal binary
+pub(crate) fn is_native_executable(header: &[u8]) -> bool {
+    header.starts_with(b"\x7fELF") || is_mach_o(header) || is_universal_binary(header)
+}
+
+fn get_uncompressed_file_type(header: &[u8]) -> FileType {
+    if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        FileType::Zip
+    } else if header.starts_with(b"MZ") {
+        FileType::Exe
+    } else if is_native_executable(header) {
+        FileType::None
+    } else if is_tar(header) {
+        FileType::Tar
+    } else {
+        FileType::Unknown
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::archive_builder::ArchiveBuilder;
+    use crate::test_util::compressor::{
+        Bzip2Compressor, Compressor, GzipCompressor, XzCompressor, ZstdCompressor,
+    };
+    use crate::test_util::tar_builder::{
+        TarBuilder, TarBz2Builder, TarGzBuilder, TarXzBuilder, TarZstBuilder,
+    };
+    use crate::test_util::zip_builder::ZipBuilder;
+    use std::io::Cursor;
+
+    fn content_type(content: impl Into<Vec<u8>>) -> ToolToolResult<FileType> {
+        get_file_type_from_content(&mut Cursor::new(content.into()))
+    }
+
+    fn build_archive<T: ArchiveBuilder>() -> ToolToolResult<Vec<u8>> {
+        let mut builder = T::default();
+        builder.add_file("bin/tool", b"tool")?;
+        builder.build()
+    }
+
+    #[test]
+    fn test_get_file_type_from_content_archives() -> ToolToolResult<()> {
+        assert_eq!(content_type(build_archive::<ZipBuilder>()?)?, FileType::Zip);
+        assert_eq!(content_type(build_archive::<TarBuilder>()?)?, FileType::Tar);
+        assert_eq!(
+            content_type(build_archive::<TarGzBuilder>()?)?,
+            FileType::TarGz
+        );
+        assert_eq!(
+            content_type(build_archive::<TarXzBuilder>()?)?,
+            FileType::TarXz
+        );
+        assert_eq!(
+            content_type(build_archive::<TarBz2Builder>()?)?,
+            FileType::TarBz2
+        );
+        assert_eq!(
+            content_type(build_archive::<TarZstBuilder>()?)?,
+            FileType::TarZst
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_file_type_from_content_single_files() -> ToolToolResult<()> {
+        let binary = b"\x7fELF\x02\x01\x01";
+        assert_eq!(
+            content_type(GzipCompressor::compress(binary)?)?,
+            FileType::Gz
+        );
+        assert_eq!(content_type(XzCompressor::compress(binary)?)?, FileType::Xz);
+        assert_eq!(
+            content_type(Bzip2Compressor::compress(binary)?)?,
+            FileType::Bz2
+        );
+        assert_eq!(
+            content_type(ZstdCompressor::compress(binary)?)?,
+            FileType::Zst
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_file_type_from_content_executables() -> ToolToolResult<()> {
+        assert_eq!(content_type(b"\x7fELF\x02\x01\x01")?, FileType::None);
+        assert_eq!(content_type(b"MZ\x90\x00")?, FileType::Exe);
+        assert_eq!(content_type(b"\xcf\xfa\xed\xfe\x0c")?, FileType::None);
+        assert_eq!(
+            content_type(b"\xca\xfe\xba\xbe\x00\x00\x00\x02")?,
+            FileType::None
+        );
+        // Java class file
+        assert_eq!(
+            content_type(b"\xca\xfe\xba\xbe\x00\x00\x00\x41")?,
+            FileType::Unknown
+        );
+        assert_eq!(content_type(b"#!/bin/sh")?, FileType::Unknown);
+        assert_eq!(content_type(b"")?, FileType::Unknown);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_file_type_from_content_truncated() -> ToolToolResult<()> {
+        // Corrupt compressed content is reported as a single compressed file, extraction fails later
+        assert_eq!(content_type(b"\x1f\x8bgarbage")?, FileType::Gz);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_file_type_from_format() -> ToolToolResult<()> {
+        assert_eq!(get_file_type_from_format("tar.gz")?, FileType::TarGz);
+        assert_eq!(get_file_type_from_format(".tar.xz")?, FileType::TarXz);
+        assert_eq!(get_file_type_from_format("zip")?, FileType::Zip);
+        assert_eq!(get_file_type_from_format("binary")?, FileType::None);
+        assert!(get_file_type_from_format("rar").is_err());
+        Ok(())
+    }
 
     #[test]
     fn test_get_file_type_from_url() {
@@ -68,6 +342,54 @@ mod tests {
             get_file_type_from_url("https://example.com/file.tar.bzip2"),
             FileType::Other("tar.bzip2".to_string())
         );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/file.tar"),
+            FileType::Tar
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/file.tgz"),
+            FileType::TarGz
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/node-v22.0.0-linux-x64.tar.xz"),
+            FileType::TarXz
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/file.txz"),
+            FileType::TarXz
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/file.tar.bz2"),
+            FileType::TarBz2
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/file.tbz2"),
+            FileType::TarBz2
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/file.tar.zst"),
+            FileType::TarZst
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/file.tzst"),
+            FileType::TarZst
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/tool-linux-x86_64.gz"),
+            FileType::Gz
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/tool-linux-x86_64.xz"),
+            FileType::Xz
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/tool.bz2"),
+            FileType::Bz2
+        );
+        assert_eq!(
+            get_file_type_from_url("https://example.com/tool.zst"),
+            FileType::Zst
+        );
 
         assert_eq!(
             get_file_type_from_url("https://example.com/file.txt?foo=bar/x.zip"),
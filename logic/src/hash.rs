@@ -1,27 +1,98 @@
-use sha2::{Digest, Sha512};
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt::Display;
 use std::io::Read;
-use tool_tool_base::result::ToolToolResult;
+use std::str::FromStr;
+use tool_tool_base::result::{ToolToolError, ToolToolResult, bail};
+
+/// Supported checksum algorithms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl ChecksumAlgorithm {
+    pub const VALUES: [Self; 2] = [Self::Sha256, Self::Sha512];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Guesses the algorithm from the length of a hex encoded checksum
+    pub fn from_checksum(checksum: &str) -> Option<Self> {
+        if !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        match checksum.len() {
+            64 => Some(ChecksumAlgorithm::Sha256),
+            128 => Some(ChecksumAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Computes the checksum of the given content as lowercase hex string
+    pub fn compute<R: Read>(&self, read: R) -> ToolToolResult<String> {
+        match self {
+            ChecksumAlgorithm::Sha256 => compute_sha256(read),
+            ChecksumAlgorithm::Sha512 => compute_sha512(read),
+        }
+    }
+}
+
+impl FromStr for ChecksumAlgorithm {
+    type Err = ToolToolError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(ChecksumAlgorithm::Sha256),
+            "sha512" => Ok(ChecksumAlgorithm::Sha512),
+            other => bail!("Unknown checksum algorithm '{other}', expected one of: sha256, sha512"),
+        }
+    }
+}
+
+impl Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
 
 /// Computes the SHA-512 digest of any type that implements `Read`.
-pub fn compute_sha512<R: Read>(mut read: R) -> ToolToolResult<String> {
-    let mut hasher = Sha512::new();
-    let mut buffer = [0u8; 8192]; // 8 KiB buffer
+pub fn compute_sha512<R: Read>(read: R) -> ToolToolResult<String> {
+    compute_digest::<Sha512, R>(read)
+}
 
+/// Computes the SHA-256 digest of any type that implements `Read`.
+pub fn compute_sha256<R: Read>(read: R) -> ToolToolResult<String> {
+    compute_digest::<Sha256, R>(read)
+}
+
+fn compute_digest<D: Digest, R: Read>(read: R) -> ToolToolResult<String> {
+    let mut hasher = D::new();
+    read_chunks(read, |chunk| hasher.update(chunk))?;
+    let result = hasher.finalize();
+    // return as lowercase hex string
+    Ok(result.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Reads the content in chunks, passing each chunk to `update`, e.g. to hash it without loading
+/// all of it into memory
+pub fn read_chunks<R: Read>(mut read: R, mut update: impl FnMut(&[u8])) -> ToolToolResult<()> {
+    let mut buffer = [0u8; 8192]; // 8 KiB buffer
     loop {
         let n = read.read(&mut buffer)?;
         if n == 0 {
-            break;
+            return Ok(());
         }
-        hasher.update(&buffer[..n]);
+        update(&buffer[..n]);
     }
-
-    let result = hasher.finalize();
-    Ok(format!("{result:x}")) // return as lowercase hex string
 }
 
 #[cfg(test)]
 mod test {
-    use crate::hash::compute_sha512;
+    use crate::hash::{ChecksumAlgorithm, compute_sha256, compute_sha512};
     use std::io::Cursor;
 
     #[test]
@@ -33,4 +104,28 @@ mod test {
             &result
         );
     }
+
+    #[test]
+    fn test_compute_sha256() {
+        let data = b"test data";
+        let result = compute_sha256(Cursor::new(data)).unwrap();
+        assert_eq!(
+            "916f0027a575074ce72a331777c3478d6513f786a591bd892da1a577bf2335f9",
+            &result
+        );
+    }
+
+    #[test]
+    fn test_algorithm_from_checksum() {
+        assert_eq!(
+            ChecksumAlgorithm::from_checksum(&"a".repeat(64)),
+            Some(ChecksumAlgorithm::Sha256)
+        );
+        assert_eq!(
+            ChecksumAlgorithm::from_checksum(&"A".repeat(128)),
+            Some(ChecksumAlgorithm::Sha512)
+        );
+        assert_eq!(ChecksumAlgorithm::from_checksum("abc123"), None);
+        assert_eq!(ChecksumAlgorithm::from_checksum(&"z".repeat(64)), None);
+    }
 }
@@ -10,7 +10,7 @@ pub fn print_help(adapter: &dyn Adapter) {
     let help_text = r#"
 USAGE:
     tool-tool [OPTIONS]
-    tool-tool [COMMAND]
+    tool-tool [GLOBAL OPTIONS] [COMMAND]
 
 OPTIONS:
     --help              Show this help message
@@ -18,6 +18,24 @@ OPTIONS:
     --version           Display version information
     --validate          Validate the tool configuration file
     --expand-config     Expand and display the configuration with all templates resolved
+    --complete-checksums
+                        Add missing checksums for all platforms to the checksums file
+    --prune-checksums [--dry-run]
+                        Remove checksums of urls no longer in the configuration
+    --cache-status      List cached tools with their size and last use
+    --gc [--keep-last N] [--dry-run]
+                        Remove cached tools no longer referenced by the configuration,
+                        keeping the N most recently used versions of each tool
+    --verify [TOOL] [--repair]
+                        Check installed tools for modified, missing or extra files,
+                        --repair reinstalls the affected tools
+
+GLOBAL OPTIONS:
+    --offline           Never access the network, fail if a tool is not cached yet
+                        (can also be enabled by setting TOOL_TOOL_OFFLINE=1)
+    --frozen            Fail instead of updating the checksums file if a checksum is missing
+    --jobs <N>          Download up to N tools in parallel (default 4)
+                        (can also be set with TOOL_TOOL_JOBS=N)
 
 EXAMPLES:
     # Execute the 'foo' command defined in .tool-tool.v2.kdl
@@ -36,6 +54,21 @@ EXAMPLES:
     # View expanded configuration
     tool-tool --expand-config
 
+    # Record checksums for all platforms after bumping a tool version
+    tool-tool --complete-checksums
+
+    # List checksums of old tool versions without removing them
+    tool-tool --prune-checksums --dry-run
+
+    # Remove old tool versions from the cache, keeping the last two of each tool
+    tool-tool --gc --keep-last 2
+
+    # Check installed tools for tampering and reinstall the affected ones
+    tool-tool --verify --repair
+
+    # Execute 'foo' in CI, without modifying checksums or accessing the network
+    tool-tool --frozen --offline foo
+
 CONFIGURATION:
     tool-tool looks for a configuration file named '.tool-tool.v2.kdl' in the current
     directory. This file should contain the tool configuration in KDL format.
@@ -1,19 +1,33 @@
 pub mod adapter;
+mod cache_task;
+mod checksum_file;
 pub mod checksums;
 pub mod configuration;
 mod download_task;
+mod extract;
 pub mod file_type;
 pub mod hash;
 pub mod help;
 pub mod lock_guard;
+pub mod manifest;
+pub mod mirrors;
 #[cfg(test)]
 pub(crate) mod mock_adapter;
+pub mod options;
+pub mod origin;
+mod parallel;
+mod progress;
+pub mod redact;
 pub mod run_command;
 pub mod runner_initial;
+pub mod shared_cache;
+pub mod signature;
 pub mod template_expander;
 pub mod template_string;
 #[cfg(test)]
 pub(crate) mod test_util;
 pub mod types;
+pub mod user_config;
+mod verify_task;
 pub mod version;
 pub mod workspace;
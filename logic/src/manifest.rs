@@ -0,0 +1,249 @@
+use crate::adapter::Adapter;
+use crate::cache_task::LAST_USED_FILE_NAME;
+use crate::download_task::CHECKSUM_MARKER_FILE_NAME;
+use crate::hash::compute_sha512;
+use crate::types::FilePath;
+use kdl::{KdlDocument, KdlEntry, KdlNode, KdlValue};
+use std::collections::BTreeMap;
+use std::io::Read;
+use tool_tool_base::result::{Context, ToolToolResult, err};
+
+/// Manifest file in the tool directory, listing every installed file with its size and checksum
+pub(crate) const MANIFEST_FILE_NAME: &str = ".tool-tool.manifest.kdl";
+
+/// Size and checksum of an installed file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub size: u64,
+    pub sha512: String,
+}
+
+/// All regular files of an installed tool, keyed by their path relative to the tool directory
+///
+/// Symbolic links are not recorded, tool-tool's own bookkeeping files are excluded
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+/// Differences between a recorded manifest and the files actually found on disk
+#[derive(Debug, Default, PartialEq)]
+pub struct ManifestDifferences {
+    pub modified: Vec<String>,
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+impl ManifestDifferences {
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty() && self.extra.is_empty()
+    }
+
+    pub fn count(&self) -> usize {
+        self.modified.len() + self.missing.len() + self.extra.len()
+    }
+}
+
+impl Manifest {
+    /// Computes the manifest of the files currently found in the tool directory
+    pub fn from_directory(adapter: &dyn Adapter, tool_dir: &FilePath) -> ToolToolResult<Self> {
+        let mut files = BTreeMap::new();
+        for path in adapter.list_files(tool_dir)? {
+            if is_bookkeeping_file(&path) {
+                continue;
+            }
+            let mut reader = CountingReader {
+                inner: adapter.read_file(&tool_dir.join(&path))?,
+                count: 0,
+            };
+            let sha512 = compute_sha512(&mut reader)?;
+            files.insert(
+                path.to_string(),
+                ManifestEntry {
+                    size: reader.count,
+                    sha512,
+                },
+            );
+        }
+        Ok(Self { files })
+    }
+
+    /// Loads the manifest of the tool directory, returns `None` if no manifest was written
+    pub fn load(adapter: &dyn Adapter, tool_dir: &FilePath) -> ToolToolResult<Option<Self>> {
+        let manifest_path = tool_dir.join(MANIFEST_FILE_NAME);
+        if !adapter.file_exists(&manifest_path)? {
+            return Ok(None);
+        }
+        let content = std::io::read_to_string(adapter.read_file(&manifest_path)?)?;
+        let document = content
+            .parse::<KdlDocument>()
+            .with_context(|| format!("Could not parse manifest '{manifest_path}'"))?;
+        let mut files = BTreeMap::new();
+        for node in document
+            .get("files")
+            .and_then(KdlNode::children)
+            .iter()
+            .flat_map(|children| children.nodes())
+        {
+            let path = node.name().value().to_string();
+            let size = node
+                .get("size")
+                .and_then(KdlValue::as_integer)
+                .and_then(|size| u64::try_from(size).ok())
+                .ok_or_else(|| err!("Missing size of '{path}' in manifest '{manifest_path}'"))?;
+            let sha512 = node
+                .get("sha512")
+                .and_then(KdlValue::as_string)
+                .ok_or_else(|| err!("Missing sha512 of '{path}' in manifest '{manifest_path}'"))?
+                .to_string();
+            files.insert(path, ManifestEntry { size, sha512 });
+        }
+        Ok(Some(Self { files }))
+    }
+
+    /// Writes the manifest into the tool directory
+    pub fn save(&self, adapter: &dyn Adapter, tool_dir: &FilePath) -> ToolToolResult<()> {
+        let mut children = KdlDocument::new();
+        for (path, entry) in &self.files {
+            let mut node = KdlNode::new(path.as_str());
+            node.push(KdlEntry::new_prop("size", i128::from(entry.size)));
+            node.push(KdlEntry::new_prop("sha512", entry.sha512.clone()));
+            children.nodes_mut().push(node);
+        }
+        let mut files_node = KdlNode::new("files");
+        files_node.set_children(children);
+        let mut document = KdlDocument::new();
+        document.nodes_mut().push(files_node);
+        let mut manifest_file = adapter.create_file(&tool_dir.join(MANIFEST_FILE_NAME))?;
+        manifest_file.write_all(document.to_string().as_bytes())?;
+        Ok(())
+    }
+
+    /// Compares the recorded manifest with the manifest of the files found on disk
+    pub fn compare(&self, actual: &Manifest) -> ManifestDifferences {
+        let mut differences = ManifestDifferences::default();
+        for (path, entry) in &self.files {
+            match actual.files.get(path) {
+                Some(actual_entry) if actual_entry == entry => {}
+                Some(_) => differences.modified.push(path.clone()),
+                None => differences.missing.push(path.clone()),
+            }
+        }
+        differences.extra = actual
+            .files
+            .keys()
+            .filter(|path| !self.files.contains_key(*path))
+            .cloned()
+            .collect();
+        differences
+    }
+}
+
+/// Files written by tool-tool itself into the tool directory
+fn is_bookkeeping_file(path: &FilePath) -> bool {
+    [
+        MANIFEST_FILE_NAME,
+        CHECKSUM_MARKER_FILE_NAME,
+        LAST_USED_FILE_NAME,
+    ]
+    .contains(&path.as_str())
+}
+
+/// Reader counting the bytes read, so files only need to be read once to get size and checksum
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_adapter::MockAdapter;
+    use expect_test::expect;
+
+    fn setup() -> ToolToolResult<(MockAdapter, FilePath)> {
+        let adapter = MockAdapter::new();
+        adapter.try_lock()?;
+        adapter.set_file("tool/bin/tool", "tool binary");
+        adapter.set_file("tool/README", "readme");
+        adapter.set_file("tool/.tool-tool.sha512", "abc");
+        Ok((adapter, FilePath::from("tool")))
+    }
+
+    #[test]
+    fn from_directory() -> ToolToolResult<()> {
+        let (adapter, tool_dir) = setup()?;
+        let manifest = Manifest::from_directory(&adapter, &tool_dir)?;
+        expect![[r#"
+            Manifest {
+                files: {
+                    "README": ManifestEntry {
+                        size: 6,
+                        sha512: "23212176b2645404ff70eaaa2a448ff01241b7bf47589e9446caa22a0fd01d32bb2aaa6aa4a795704a154d74bc209931b3bccacaec1f6746f765ebe4b4c89464",
+                    },
+                    "bin/tool": ManifestEntry {
+                        size: 11,
+                        sha512: "d449d17a88b1bd8ef3b155f8123c22e90c980fe65211ac0c511d3a9f6a05857ba33af3960c919549b28e224543a536f23d516fac1c7951acdcda5b045bd79d2b",
+                    },
+                },
+            }
+        "#]]
+        .assert_debug_eq(&manifest);
+        Ok(())
+    }
+
+    #[test]
+    fn save_and_load() -> ToolToolResult<()> {
+        let (adapter, tool_dir) = setup()?;
+        assert_eq!(Manifest::load(&adapter, &tool_dir)?, None);
+        let manifest = Manifest::from_directory(&adapter, &tool_dir)?;
+        manifest.save(&adapter, &tool_dir)?;
+        expect![[r#"
+            files{
+            README size=6 sha512="23212176b2645404ff70eaaa2a448ff01241b7bf47589e9446caa22a0fd01d32bb2aaa6aa4a795704a154d74bc209931b3bccacaec1f6746f765ebe4b4c89464"
+            "bin/tool" size=11 sha512=d449d17a88b1bd8ef3b155f8123c22e90c980fe65211ac0c511d3a9f6a05857ba33af3960c919549b28e224543a536f23d516fac1c7951acdcda5b045bd79d2b
+            }
+        "#]].assert_eq(&std::io::read_to_string(
+            adapter.read_file(&tool_dir.join(MANIFEST_FILE_NAME))?,
+        )?);
+        assert_eq!(Manifest::load(&adapter, &tool_dir)?, Some(manifest.clone()));
+        // the manifest does not list itself
+        assert_eq!(Manifest::from_directory(&adapter, &tool_dir)?, manifest);
+        Ok(())
+    }
+
+    #[test]
+    fn compare() -> ToolToolResult<()> {
+        let (adapter, tool_dir) = setup()?;
+        let manifest = Manifest::from_directory(&adapter, &tool_dir)?;
+        assert!(manifest.compare(&manifest).is_empty());
+        adapter.set_file("tool/bin/tool", "tampered binary");
+        adapter.remove_file("tool/README");
+        adapter.set_file("tool/bin/extra", "extra");
+        let differences = manifest.compare(&Manifest::from_directory(&adapter, &tool_dir)?);
+        expect![[r#"
+            ManifestDifferences {
+                modified: [
+                    "bin/tool",
+                ],
+                missing: [
+                    "README",
+                ],
+                extra: [
+                    "bin/extra",
+                ],
+            }
+        "#]]
+        .assert_debug_eq(&differences);
+        assert_eq!(differences.count(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use crate::adapter::Adapter;
+use crate::user_config::UserConfig;
+use tool_tool_base::result::{ToolToolResult, bail};
+
+/// Environment variable with mirror rules, taking precedence over the user configuration file
+///
+/// Rules are separated by `;` or newlines, e.g.
+/// `https://github.com/ => https://mirror.corp/github/; https://nodejs.org/ => https://mirror.corp/node/`
+pub const MIRRORS_ENV_VAR: &str = "TOOL_TOOL_MIRRORS";
+
+/// Rewrites download urls starting with `prefix` to start with `mirror` instead
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirrorRule {
+    pub prefix: String,
+    pub mirror: String,
+}
+
+impl MirrorRule {
+    pub fn new(prefix: impl Into<String>, mirror: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            mirror: mirror.into(),
+        }
+    }
+}
+
+/// Ordered mirror rules, several rules for the same prefix are tried one after another
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mirrors {
+    rules: Vec<MirrorRule>,
+}
+
+impl Mirrors {
+    pub fn new(rules: Vec<MirrorRule>) -> Self {
+        Self { rules }
+    }
+
+    /// Reads the rules from `TOOL_TOOL_MIRRORS`, falling back to the `mirrors` block of the user
+    /// configuration file
+    pub fn from_env(adapter: &dyn Adapter) -> ToolToolResult<Self> {
+        let env = adapter.env();
+        if let Some((_, value)) = env
+            .iter()
+            .find(|(key, value)| key == MIRRORS_ENV_VAR && !value.trim().is_empty())
+        {
+            return Ok(Self::new(parse_rules(value)?));
+        }
+        Ok(Self::new(UserConfig::load(adapter)?.mirrors))
+    }
+
+    /// The urls to download the url from in order: the url rewritten by every matching rule, or
+    /// just the url itself if no rule matches
+    pub fn candidates(&self, url: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = vec![];
+        for rule in &self.rules {
+            if let Some(rest) = url.strip_prefix(&rule.prefix) {
+                let candidate = format!("{}{rest}", rule.mirror);
+                if !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        if candidates.is_empty() {
+            candidates.push(url.to_string());
+        }
+        candidates
+    }
+}
+
+/// Parses rules of the form `<prefix> => <mirror>`, separated by `;` or newlines
+fn parse_rules(value: &str) -> ToolToolResult<Vec<MirrorRule>> {
+    let mut rules = vec![];
+    for rule in value.split([';', '\n']).map(str::trim) {
+        if rule.is_empty() {
+            continue;
+        }
+        let Some((prefix, mirror)) = rule.split_once("=>") else {
+            bail!(
+                "Invalid mirror rule '{rule}' in {MIRRORS_ENV_VAR}, expected '<prefix> => <mirror>'"
+            );
+        };
+        let (prefix, mirror) = (prefix.trim(), mirror.trim());
+        if prefix.is_empty() || mirror.is_empty() {
+            bail!(
+                "Invalid mirror rule '{rule}' in {MIRRORS_ENV_VAR}, expected '<prefix> => <mirror>'"
+            );
+        }
+        rules.push(MirrorRule::new(prefix, mirror));
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_adapter::MockAdapter;
+
+    #[test]
+    fn test_parse_rules() -> ToolToolResult<()> {
+        assert_eq!(
+            parse_rules(
+                " https://github.com/ => https://mirror.corp/github/;\nhttps://github.com/=>https://backup.corp/github/;"
+            )?,
+            vec![
+                MirrorRule::new("https://github.com/", "https://mirror.corp/github/"),
+                MirrorRule::new("https://github.com/", "https://backup.corp/github/"),
+            ]
+        );
+        assert_eq!(parse_rules("")?, vec![]);
+        assert_eq!(
+            format!("{:#}", parse_rules("https://github.com/").unwrap_err()),
+            "Invalid mirror rule 'https://github.com/' in TOOL_TOOL_MIRRORS, expected '<prefix> => <mirror>'"
+        );
+        assert_eq!(
+            format!("{:#}", parse_rules("https://github.com/ =>").unwrap_err()),
+            "Invalid mirror rule 'https://github.com/ =>' in TOOL_TOOL_MIRRORS, expected '<prefix> => <mirror>'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mirrors = Mirrors::new(vec![
+            MirrorRule::new("https://github.com/", "https://mirror.corp/github/"),
+            MirrorRule::new("https://nodejs.org/dist/", "https://mirror.corp/node/"),
+            MirrorRule::new("https://github.com/", "https://backup.corp/github/"),
+            MirrorRule::new("https://github.com/", "https://mirror.corp/github/"),
+        ]);
+        assert_eq!(
+            mirrors.candidates("https://github.com/lsd-rs/lsd/releases/download/v1.1.5/lsd.tar.gz"),
+            vec![
+                "https://mirror.corp/github/lsd-rs/lsd/releases/download/v1.1.5/lsd.tar.gz",
+                "https://backup.corp/github/lsd-rs/lsd/releases/download/v1.1.5/lsd.tar.gz",
+            ]
+        );
+        assert_eq!(
+            mirrors.candidates("https://nodejs.org/dist/v22.0.0/node.tar.xz"),
+            vec!["https://mirror.corp/node/v22.0.0/node.tar.xz"]
+        );
+        assert_eq!(
+            mirrors.candidates("https://example.com/tool.zip"),
+            vec!["https://example.com/tool.zip"]
+        );
+    }
+
+    #[test]
+    fn test_from_env() -> ToolToolResult<()> {
+        let adapter = MockAdapter::new();
+        adapter.try_lock()?;
+        assert_eq!(Mirrors::from_env(&adapter)?, Mirrors::default());
+        adapter.add_env("HOME", "/home/user");
+        adapter.set_file(
+            "/home/user/.config/tool-tool/config.kdl",
+            r#"mirrors { "https://github.com/" "https://mirror.corp/github/"; }"#,
+        );
+        assert_eq!(
+            Mirrors::from_env(&adapter)?,
+            Mirrors::new(vec![MirrorRule::new(
+                "https://github.com/",
+                "https://mirror.corp/github/"
+            )])
+        );
+        // The environment variable takes precedence over the file
+        adapter.add_env(
+            MIRRORS_ENV_VAR,
+            "https://github.com/ => https://ci.corp/github/",
+        );
+        assert_eq!(
+            Mirrors::from_env(&adapter)?,
+            Mirrors::new(vec![MirrorRule::new(
+                "https://github.com/",
+                "https://ci.corp/github/"
+            )])
+        );
+        Ok(())
+    }
+}
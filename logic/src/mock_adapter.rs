@@ -1,14 +1,15 @@
-use crate::adapter::{Adapter, ExecutionRequest, ReadSeek};
+use crate::adapter::{Adapter, DownloadProgress, ExecutionRequest, FileMetadata, ReadSeek};
 use crate::configuration::CONFIGURATION_FILE_NAME;
-use crate::configuration::platform::DownloadPlatform;
+use crate::configuration::platform::{Architecture, DownloadPlatform, Libc};
 use crate::types::FilePath;
 use expect_test::Expect;
 use indent::indent_all_with;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::io::{Cursor, Write};
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::time::Duration;
-use tool_tool_base::result::{ToolToolResult, err};
+use tool_tool_base::logging::write_output;
+use tool_tool_base::result::{ToolToolResult, bail, err};
 
 #[derive(Clone)]
 pub struct MockAdapter {
@@ -20,14 +21,19 @@ struct MockAdapterInner {
     env: Vec<(String, String)>,
     effects_string: String,
     platform: DownloadPlatform,
+    architecture: Architecture,
+    libc: Libc,
     url_map: HashMap<String, Vec<u8>>,
     file_map: HashMap<FilePath, Vec<u8>>,
+    modified_map: HashMap<FilePath, Duration>,
     exit_code: i32,
     next_random_number: u64,
     now: Duration,
     now_increment: Duration,
+    system_time: Duration,
     is_locked: bool,
     lock_results: Vec<bool>,
+    is_terminal: bool,
 }
 
 impl MockAdapter {
@@ -64,15 +70,20 @@ impl MockAdapter {
                 env: vec![("NO_COLOR".to_string(), "1".to_string())],
                 args: Vec::new(),
                 platform: DownloadPlatform::Linux,
+                architecture: Architecture::X86_64,
+                libc: Libc::Gnu,
                 url_map: HashMap::new(),
                 file_map,
+                modified_map: HashMap::new(),
                 effects_string: String::new(),
                 exit_code: 0,
                 next_random_number: 0,
                 now: Duration::from_secs(42),
                 now_increment: Duration::from_secs(0),
+                system_time: Duration::from_secs(1_700_000_000),
                 lock_results: Vec::new(),
                 is_locked: false,
+                is_terminal: false,
             })),
         }
     }
@@ -90,8 +101,14 @@ impl MockAdapter {
     }
 
     pub(crate) fn log_effect(&self, effect: impl AsRef<str>) {
-        self.write().effects_string.push_str(effect.as_ref());
-        self.write().effects_string.push('\n');
+        // Effects are output like log lines, so parallel downloads log them in a fixed order
+        let adapter = self.clone();
+        let effect = effect.as_ref().to_string();
+        write_output(move || {
+            let mut inner = adapter.write();
+            inner.effects_string.push_str(&effect);
+            inner.effects_string.push('\n');
+        });
     }
 
     pub fn set_args(&self, args: &[&str]) {
@@ -104,14 +121,36 @@ impl MockAdapter {
         self.set_file(CONFIGURATION_FILE_NAME, configuration.into().into_bytes());
     }
 
+    pub fn set_terminal(&self, is_terminal: bool) {
+        self.write().is_terminal = is_terminal;
+    }
+
     pub fn set_platform(&self, platform: DownloadPlatform) {
         self.write().platform = platform;
     }
 
+    pub fn set_architecture(&self, architecture: Architecture) {
+        self.write().architecture = architecture;
+    }
+
+    pub fn set_libc(&self, libc: Libc) {
+        self.write().libc = libc;
+    }
+
     pub fn set_url(&self, url: &str, content: Vec<u8>) {
         self.write().url_map.insert(url.to_string(), content);
     }
 
+    pub fn remove_file(&self, file_path: &str) {
+        self.write().file_map.remove(&FilePath::from(file_path));
+    }
+
+    pub fn set_modified(&self, file_path: &str, modified: Duration) {
+        self.write()
+            .modified_map
+            .insert(FilePath::from(file_path), modified);
+    }
+
     pub fn set_file(&self, file_path: &str, content: impl Into<Vec<u8>>) {
         self.write()
             .file_map
@@ -131,6 +170,10 @@ impl MockAdapter {
         self.write().exit_code = exit_code;
     }
 
+    pub fn set_system_time(&self, system_time: Duration) {
+        self.write().system_time = system_time;
+    }
+
     pub fn set_now_increment(&self, now_increment: Duration) {
         self.write().now_increment = now_increment;
     }
@@ -173,7 +216,12 @@ impl Adapter for MockAdapter {
     fn file_exists(&self, path: &FilePath) -> ToolToolResult<bool> {
         self.assert_locked();
         self.log_effect(format!("FILE EXISTS?: {}", path));
-        Ok(self.read().file_map.contains_key(path))
+        // Directories exist implicitly as long as they contain files
+        Ok(self
+            .read()
+            .file_map
+            .keys()
+            .any(|file_path| file_path.starts_with(path)))
     }
 
     fn read_file(&self, path: &FilePath) -> ToolToolResult<Box<dyn ReadSeek>> {
@@ -211,25 +259,70 @@ impl Adapter for MockAdapter {
         self.log_effect(format!("EXIT: {}", exit_code));
     }
 
-    fn download_file(&self, url: &str, destination_path: &FilePath) -> ToolToolResult<()> {
+    fn download_file(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        destination_path: &FilePath,
+        progress: &mut dyn FnMut(&DownloadProgress),
+    ) -> ToolToolResult<()> {
         self.assert_locked();
-        self.log_effect(format!("DOWNLOAD: {url} -> {destination_path}"));
+        if headers.is_empty() {
+            self.log_effect(format!("DOWNLOAD: {url} -> {destination_path}"));
+        } else {
+            // Only the header names are logged, since the values usually are credentials
+            let names: Vec<&str> = headers.iter().map(|(name, _)| name.as_str()).collect();
+            self.log_effect(format!(
+                "DOWNLOAD: {url} -> {destination_path} (headers: {})",
+                names.join(", ")
+            ));
+        }
         let content = self
             .read()
             .url_map
             .get(url)
             .ok_or_else(|| err!("URL '{url}' does not exist"))?
             .clone();
+        let total = content.len() as u64;
         self.write()
             .file_map
             .insert(destination_path.clone(), content);
+        // Pretend the download took two seconds, receiving half of the content per second
+        for (downloaded, seconds) in [(total / 2, 1), (total, 2)] {
+            progress(&DownloadProgress {
+                downloaded,
+                total: Some(total),
+                elapsed: Duration::from_secs(seconds),
+            });
+        }
         Ok(())
     }
 
+    fn is_terminal(&self) -> bool {
+        self.read().is_terminal
+    }
+
+    fn print_progress(&self, line: &str) {
+        if line.is_empty() {
+            self.log_effect("PROGRESS CLEAR");
+        } else {
+            self.log_effect(format!("PROGRESS: {line}"));
+        }
+    }
+
     fn get_platform(&self) -> DownloadPlatform {
         self.read().platform
     }
 
+    fn get_architecture(&self) -> Architecture {
+        self.read().architecture
+    }
+
+    fn get_libc(&self) -> Option<Libc> {
+        let inner = self.read();
+        (inner.platform == DownloadPlatform::Linux).then_some(inner.libc)
+    }
+
     fn execute(&self, request: ExecutionRequest) -> ToolToolResult<i32> {
         self.log_effect(format!("EXECUTE: {}", request.binary_path));
         for arg in request.args {
@@ -250,12 +343,16 @@ impl Adapter for MockAdapter {
     }
 
     fn now(&self) -> ToolToolResult<Duration> {
-        let old_now = self.read().now;
-        let new_now = old_now + self.read().now_increment;
-        self.write().now = new_now;
+        let mut inner = self.write();
+        let old_now = inner.now;
+        inner.now = old_now + inner.now_increment;
         Ok(old_now)
     }
 
+    fn system_time(&self) -> ToolToolResult<Duration> {
+        Ok(self.read().system_time)
+    }
+
     fn try_lock(&self) -> ToolToolResult<bool> {
         self.assert_unlocked();
         self.log_effect("TRY LOCK");
@@ -282,6 +379,131 @@ impl Adapter for MockAdapter {
         self.log_effect(format!("MAKE EXECUTABLE: {}", path));
         Ok(())
     }
+
+    fn create_symlink(&self, path: &FilePath, target: &str) -> ToolToolResult<()> {
+        self.assert_locked();
+        self.log_effect(format!("CREATE SYMLINK: {path} -> {target}"));
+        Ok(())
+    }
+
+    fn set_file_mode(&self, path: &FilePath, mode: u32) -> ToolToolResult<()> {
+        self.assert_locked();
+        self.log_effect(format!("SET MODE: {path} {mode:o}"));
+        Ok(())
+    }
+
+    fn list_directory(&self, path: &FilePath) -> ToolToolResult<Vec<String>> {
+        self.assert_locked();
+        self.log_effect(format!("LIST DIR: {path}"));
+        let names: BTreeSet<String> = self
+            .read()
+            .file_map
+            .keys()
+            .filter_map(|file_path| file_path.strip_prefix(path).ok())
+            .filter_map(|file_path| file_path.components().next())
+            .map(|component| component.as_str().to_string())
+            .collect();
+        Ok(names.into_iter().collect())
+    }
+
+    fn file_metadata(&self, path: &FilePath) -> ToolToolResult<FileMetadata> {
+        self.assert_locked();
+        self.log_effect(format!("METADATA: {path}"));
+        let inner = self.read();
+        let modified = inner.modified_map.get(path).copied().unwrap_or_default();
+        if let Some(content) = inner.file_map.get(path) {
+            return Ok(FileMetadata {
+                is_directory: false,
+                size: content.len() as u64,
+                modified,
+            });
+        }
+        if !inner
+            .file_map
+            .keys()
+            .any(|file_path| file_path.starts_with(path))
+        {
+            bail!("File '{path}' does not exist");
+        }
+        Ok(FileMetadata {
+            is_directory: true,
+            size: 0,
+            modified,
+        })
+    }
+
+    fn link_file(&self, from: &FilePath, to: &FilePath) -> ToolToolResult<()> {
+        self.assert_locked();
+        self.log_effect(format!("LINK FILE: {from} -> {to}"));
+        let mut inner = self.write();
+        let content = inner
+            .file_map
+            .get(from)
+            .ok_or_else(|| err!("File '{from}' does not exist"))?
+            .clone();
+        inner.file_map.insert(to.clone(), content);
+        Ok(())
+    }
+
+    fn delete_file(&self, path: &FilePath) -> ToolToolResult<()> {
+        self.assert_locked();
+        self.log_effect(format!("DELETE FILE: {path}"));
+        self.write()
+            .file_map
+            .remove(path)
+            .ok_or_else(|| err!("File '{path}' does not exist"))?;
+        Ok(())
+    }
+
+    fn rename(&self, from: &FilePath, to: &FilePath) -> ToolToolResult<()> {
+        self.assert_locked();
+        self.log_effect(format!("RENAME: {from} -> {to}"));
+        let mut inner = self.write();
+        if inner
+            .file_map
+            .keys()
+            .any(|file_path| file_path.starts_with(to))
+        {
+            bail!("Rename destination '{to}' already exists");
+        }
+        let moved_paths: Vec<FilePath> = inner
+            .file_map
+            .keys()
+            .filter(|file_path| file_path.starts_with(from))
+            .cloned()
+            .collect();
+        if moved_paths.is_empty() {
+            bail!("Rename source '{from}' does not exist");
+        }
+        for path in moved_paths {
+            let content = inner.file_map.remove(&path).unwrap_or_default();
+            let relative_path = path
+                .strip_prefix(from)
+                .map_err(|_| err!("'{path}' is not below '{from}'"))?;
+            let new_path = if relative_path.as_str().is_empty() {
+                to.clone()
+            } else {
+                to.join(relative_path)
+            };
+            inner.file_map.insert(new_path, content);
+        }
+        Ok(())
+    }
+
+    fn list_files(&self, path: &FilePath) -> ToolToolResult<Vec<FilePath>> {
+        self.assert_locked();
+        self.log_effect(format!("LIST FILES: {path}"));
+        let mut files: Vec<FilePath> = self
+            .read()
+            .file_map
+            .keys()
+            .filter_map(|file_path| file_path.strip_prefix(path).ok())
+            .filter(|file_path| !file_path.as_str().is_empty())
+            .map(|file_path| file_path.to_relative_path_buf())
+            .collect();
+        files.sort();
+        Ok(files)
+    }
 }
 
 impl std::fmt::Debug for MockAdapter {
@@ -318,11 +540,15 @@ impl Write for MockFile {
 
 impl Drop for MockFile {
     fn drop(&mut self) {
-        self.mock_adapter.log_effect(format!(
-            "WRITE FILE: {} -> {}",
-            self.path,
-            String::from_utf8_lossy(&self.data)
-        ));
+        // Keep control characters of binary content out of the effect snapshots
+        let content = match std::str::from_utf8(&self.data) {
+            Ok(text) if !text.chars().any(|c| c.is_control() && !c.is_whitespace()) => {
+                text.to_string()
+            }
+            _ => format!("<{} bytes of binary data>", self.data.len()),
+        };
+        self.mock_adapter
+            .log_effect(format!("WRITE FILE: {} -> {}", self.path, content));
         self.mock_adapter
             .write()
             .file_map
@@ -0,0 +1,157 @@
+use tool_tool_base::result::{ToolToolResult, bail};
+
+/// Environment variable to enable offline mode, equivalent to `--offline`
+pub const OFFLINE_ENV_VAR: &str = "TOOL_TOOL_OFFLINE";
+
+/// Environment variable setting the number of parallel downloads, equivalent to `--jobs`
+pub const JOBS_ENV_VAR: &str = "TOOL_TOOL_JOBS";
+
+/// Number of tools downloaded in parallel, unless configured otherwise
+pub const DEFAULT_JOBS: usize = 4;
+
+/// Global options, given before the command or task (e.g. `tt --offline yarn install`)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Options {
+    /// Never access the network, fail if a tool is not cached yet
+    pub offline: bool,
+    /// Fail instead of updating the checksums file if a checksum is missing
+    pub frozen: bool,
+    /// Maximum number of tools downloaded in parallel, `None` for the default
+    pub jobs: Option<usize>,
+}
+
+impl Options {
+    pub fn jobs(&self) -> usize {
+        self.jobs.unwrap_or(DEFAULT_JOBS)
+    }
+}
+
+/// Parses the leading global options, returning them together with the remaining arguments
+pub fn parse_options(
+    args: &[String],
+    env: &[(String, String)],
+) -> ToolToolResult<(Options, Vec<String>)> {
+    let mut options = Options {
+        offline: env
+            .iter()
+            .any(|(key, value)| key == OFFLINE_ENV_VAR && is_truthy(value)),
+        ..Options::default()
+    };
+    if let Some((_, jobs)) = env
+        .iter()
+        .find(|(key, value)| key == JOBS_ENV_VAR && !value.is_empty())
+    {
+        options.jobs = Some(parse_jobs(jobs, JOBS_ENV_VAR)?);
+    }
+    let mut remaining = args.iter().peekable();
+    while let Some(arg) = remaining.next_if(|arg| arg.starts_with("--")) {
+        match arg.as_str() {
+            "--offline" => options.offline = true,
+            "--frozen" => options.frozen = true,
+            "--jobs" => {
+                let Some(jobs) = remaining.next() else {
+                    bail!("Missing number for --jobs");
+                };
+                options.jobs = Some(parse_jobs(jobs, "--jobs")?);
+            }
+            _ => {
+                // Not a global option, but a task like --download
+                return Ok((
+                    options,
+                    std::iter::once(arg).chain(remaining).cloned().collect(),
+                ));
+            }
+        }
+    }
+    Ok((options, remaining.cloned().collect()))
+}
+
+fn parse_jobs(value: &str, source: &str) -> ToolToolResult<usize> {
+    match value.trim().parse() {
+        Ok(jobs) if jobs > 0 => Ok(jobs),
+        _ => bail!("Invalid number for {source}: '{value}', expected a positive number"),
+    }
+}
+
+pub(crate) fn is_truthy(value: &str) -> bool {
+    !matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "" | "0" | "false" | "no" | "off"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn env(key: &str, value: &str) -> Vec<(String, String)> {
+        vec![(key.to_string(), value.to_string())]
+    }
+
+    #[test]
+    fn no_options() {
+        let (options, remaining) = parse_options(&args(&["yarn", "--offline"]), &[]).unwrap();
+        assert_eq!(options, Options::default());
+        assert_eq!(remaining, ["yarn", "--offline"]);
+    }
+
+    #[test]
+    fn leading_options() {
+        let (options, remaining) =
+            parse_options(&args(&["--frozen", "--offline", "--download"]), &[]).unwrap();
+        assert_eq!(
+            options,
+            Options {
+                offline: true,
+                frozen: true,
+                jobs: None,
+            }
+        );
+        assert_eq!(remaining, ["--download"]);
+    }
+
+    #[test]
+    fn offline_from_env() {
+        let (options, _) = parse_options(&[], &env(OFFLINE_ENV_VAR, "1")).unwrap();
+        assert!(options.offline);
+        let (options, _) = parse_options(&[], &env(OFFLINE_ENV_VAR, "false")).unwrap();
+        assert!(!options.offline);
+        let (options, _) = parse_options(&[], &env(OFFLINE_ENV_VAR, "")).unwrap();
+        assert!(!options.offline);
+    }
+
+    #[test]
+    fn jobs() {
+        let (options, remaining) =
+            parse_options(&args(&["--jobs", "2", "--download"]), &[]).unwrap();
+        assert_eq!(options.jobs(), 2);
+        assert_eq!(remaining, ["--download"]);
+        let (options, _) = parse_options(&[], &[]).unwrap();
+        assert_eq!(options.jobs(), DEFAULT_JOBS);
+        let (options, _) = parse_options(&[], &env(JOBS_ENV_VAR, "8")).unwrap();
+        assert_eq!(options.jobs(), 8);
+        // the command line takes precedence over the environment
+        let (options, _) = parse_options(&args(&["--jobs", "1"]), &env(JOBS_ENV_VAR, "8")).unwrap();
+        assert_eq!(options.jobs(), 1);
+        let error = parse_options(&args(&["--jobs", "0"]), &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid number for --jobs: '0', expected a positive number"
+        );
+        let error = parse_options(&[], &env(JOBS_ENV_VAR, "many")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid number for TOOL_TOOL_JOBS: 'many', expected a positive number"
+        );
+        assert_eq!(
+            parse_options(&args(&["--jobs"]), &[])
+                .unwrap_err()
+                .to_string(),
+            "Missing number for --jobs"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Applies `f` to all items using up to `jobs` threads, returning the results in item order
+///
+/// Items are processed on the current thread if only a single job is allowed (or needed)
+pub(crate) fn map_parallel<T: Sync, R: Send>(
+    jobs: usize,
+    items: &[T],
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let jobs = jobs.min(items.len());
+    if jobs <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let next_index = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                loop {
+                    let index = next_index.fetch_add(1, Ordering::SeqCst);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    let result = f(item);
+                    results.lock().expect("results lock poisoned")[index] = Some(result);
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .expect("results lock poisoned")
+        .into_iter()
+        .map(|result| result.expect("every item has been processed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::thread::ThreadId;
+
+    #[test]
+    fn results_in_item_order() {
+        let items: Vec<u64> = (0..20).collect();
+        for jobs in [1, 3, 100] {
+            let results = map_parallel(jobs, &items, |item| {
+                // finish later items first
+                std::thread::sleep(std::time::Duration::from_millis(20 - item));
+                item * 2
+            });
+            assert_eq!(
+                results,
+                items.iter().map(|item| item * 2).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn limits_threads() {
+        let items: Vec<u64> = (0..20).collect();
+        let threads = Mutex::new(HashSet::<ThreadId>::new());
+        map_parallel(3, &items, |_| {
+            threads.lock().unwrap().insert(std::thread::current().id());
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        });
+        assert!(threads.lock().unwrap().len() <= 3);
+        let current = std::thread::current().id();
+        map_parallel(1, &items, |_| {
+            assert_eq!(std::thread::current().id(), current)
+        });
+    }
+}
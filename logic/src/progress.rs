@@ -0,0 +1,272 @@
+use crate::adapter::{Adapter, DownloadProgress};
+use crate::cache_task::format_size;
+use crate::file_type::get_filename_from_url;
+use crate::types::FilePath;
+use std::time::Duration;
+use tool_tool_base::logging::{is_output_buffered, unbuffered};
+use tool_tool_base::result::ToolToolResult;
+use tracing::info;
+
+/// Downloads shorter than this do not report progress at all
+const MIN_DURATION: Duration = Duration::from_secs(1);
+
+/// Interval between updates of the progress bar on a terminal
+const RENDER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Interval between progress log lines when stderr is not a terminal (e.g. in CI)
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+const BAR_WIDTH: usize = 20;
+
+/// Downloads a file, rendering a progress bar on a terminal and logging progress otherwise
+pub(crate) fn download_with_progress(
+    adapter: &dyn Adapter,
+    url: &str,
+    headers: &[(String, String)],
+    destination_path: &FilePath,
+) -> ToolToolResult<()> {
+    let label = get_filename_from_url(url).unwrap_or(url);
+    let mut reporter = ProgressReporter::new(adapter, label);
+    let result = adapter.download_file(url, headers, destination_path, &mut |progress| {
+        reporter.update(progress)
+    });
+    reporter.finish();
+    result
+}
+
+struct ProgressReporter<'a> {
+    adapter: &'a dyn Adapter,
+    label: &'a str,
+    is_terminal: bool,
+    last_report: Option<Duration>,
+}
+
+impl<'a> ProgressReporter<'a> {
+    fn new(adapter: &'a dyn Adapter, label: &'a str) -> Self {
+        Self {
+            adapter,
+            label,
+            // Output is buffered while several tools are downloaded in parallel, their progress
+            // bars would overwrite each other, so progress is logged instead
+            is_terminal: adapter.is_terminal() && !is_output_buffered(),
+            last_report: None,
+        }
+    }
+
+    fn update(&mut self, progress: &DownloadProgress) {
+        let interval = if self.is_terminal {
+            RENDER_INTERVAL
+        } else {
+            LOG_INTERVAL
+        };
+        let due = match self.last_report {
+            None => progress.elapsed >= MIN_DURATION,
+            Some(last_report) => progress.elapsed >= last_report + interval,
+        };
+        if !due {
+            return;
+        }
+        self.last_report = Some(progress.elapsed);
+        let line = format_progress(self.label, progress, self.is_terminal);
+        if self.is_terminal {
+            self.adapter.print_progress(&line);
+        } else {
+            // Outdated progress is useless, so it is not buffered
+            unbuffered(|| info!("{line}"));
+        }
+    }
+
+    /// Clears the progress bar, so it does not linger once the download is done
+    fn finish(&self) {
+        if self.is_terminal && self.last_report.is_some() {
+            self.adapter.print_progress("");
+        }
+    }
+}
+
+/// Formats a progress line like `tool.tar.gz [####      ] 10.0 MiB / 50.0 MiB 20% 5.0 MiB/s ETA 8s`
+fn format_progress(label: &str, progress: &DownloadProgress, with_bar: bool) -> String {
+    let seconds = progress.elapsed.as_secs_f64();
+    let rate = if seconds > 0.0 {
+        progress.downloaded as f64 / seconds
+    } else {
+        0.0
+    };
+    let mut line = label.to_string();
+    match progress.total {
+        Some(total) if total > 0 => {
+            let fraction = (progress.downloaded as f64 / total as f64).min(1.0);
+            if with_bar {
+                let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+                line.push_str(&format!(
+                    " [{}{}]",
+                    "#".repeat(filled),
+                    " ".repeat(BAR_WIDTH - filled)
+                ));
+            }
+            line.push_str(&format!(
+                " {} / {} {}%",
+                format_size(progress.downloaded),
+                format_size(total),
+                (fraction * 100.0).floor()
+            ));
+            line.push_str(&format!(" {}/s", format_size(rate as u64)));
+            if rate > 0.0 {
+                let remaining = total.saturating_sub(progress.downloaded) as f64 / rate;
+                line.push_str(&format!(
+                    " ETA {}",
+                    format_duration(Duration::from_secs_f64(remaining.ceil()))
+                ));
+            }
+        }
+        _ => {
+            line.push_str(&format!(
+                " {} {}/s",
+                format_size(progress.downloaded),
+                format_size(rate as u64)
+            ));
+        }
+    }
+    line
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_adapter::MockAdapter;
+    use expect_test::expect;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    fn progress(downloaded: u64, total: Option<u64>, elapsed_millis: u64) -> DownloadProgress {
+        DownloadProgress {
+            downloaded,
+            total,
+            elapsed: Duration::from_millis(elapsed_millis),
+        }
+    }
+
+    #[test]
+    fn test_format_progress() {
+        let mib = 1024 * 1024;
+        assert_eq!(
+            format_progress(
+                "jdk.tar.gz",
+                &progress(10 * mib, Some(50 * mib), 2000),
+                true
+            ),
+            "jdk.tar.gz [####                ] 10.0 MiB / 50.0 MiB 20% 5.0 MiB/s ETA 8s"
+        );
+        assert_eq!(
+            format_progress(
+                "jdk.tar.gz",
+                &progress(10 * mib, Some(50 * mib), 2000),
+                false
+            ),
+            "jdk.tar.gz 10.0 MiB / 50.0 MiB 20% 5.0 MiB/s ETA 8s"
+        );
+        assert_eq!(
+            format_progress("jdk.tar.gz", &progress(mib, Some(600 * mib), 1000), false),
+            "jdk.tar.gz 1.0 MiB / 600.0 MiB 0% 1.0 MiB/s ETA 9m 59s"
+        );
+        assert_eq!(
+            format_progress("jdk.tar.gz", &progress(3 * mib, None, 3000), true),
+            "jdk.tar.gz 3.0 MiB 1.0 MiB/s"
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1m 05s");
+        assert_eq!(format_duration(Duration::from_secs(7384)), "2h 03m");
+    }
+
+    #[test]
+    fn test_reporter_throttles_terminal_updates() {
+        let adapter = MockAdapter::new();
+        adapter.set_terminal(true);
+        let mut reporter = ProgressReporter::new(&adapter, "tool.tar.gz");
+        for (downloaded, elapsed) in [
+            (100, 500),
+            (200, 1000),
+            (250, 1050),
+            (300, 1100),
+            (400, 2000),
+        ] {
+            reporter.update(&progress(downloaded, Some(400), elapsed));
+        }
+        reporter.finish();
+        adapter.verify_effects(expect![[r#"
+            PROGRESS: tool.tar.gz [##########          ] 200 B / 400 B 50% 200 B/s ETA 1s
+            PROGRESS: tool.tar.gz [###############     ] 300 B / 400 B 75% 272 B/s ETA 1s
+            PROGRESS: tool.tar.gz [####################] 400 B / 400 B 100% 200 B/s ETA 0s
+            PROGRESS CLEAR
+        "#]]);
+    }
+
+    /// Writer collecting the log output of a test
+    #[derive(Clone, Default)]
+    struct LogCapture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for LogCapture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_reporter_logs_without_terminal() {
+        let adapter = MockAdapter::new();
+        let capture = LogCapture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .without_time()
+            .with_target(false)
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let mut reporter = ProgressReporter::new(&adapter, "tool.tar.gz");
+            for (downloaded, elapsed) in [
+                (50, 500),
+                (100, 1000),
+                (150, 2000),
+                (200, 5900),
+                (250, 6000),
+                (300, 10000),
+                (400, 11000),
+            ] {
+                reporter.update(&progress(downloaded, Some(400), elapsed));
+            }
+            reporter.finish();
+        });
+        // Progress is only logged, nothing is printed to the terminal
+        adapter.verify_effects(expect![""]);
+        let log = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        let log: String = log
+            .lines()
+            .map(|line| format!("{}\n", line.trim()))
+            .collect();
+        expect![[r#"
+            INFO tool.tar.gz 100 B / 400 B 25% 100 B/s ETA 3s
+            INFO tool.tar.gz 250 B / 400 B 62% 41 B/s ETA 4s
+            INFO tool.tar.gz 400 B / 400 B 100% 36 B/s ETA 0s
+        "#]]
+        .assert_eq(&log);
+    }
+}
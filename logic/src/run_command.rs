@@ -1,4 +1,5 @@
 use crate::adapter::ExecutionRequest;
+use crate::cache_task::LAST_USED_FILE_NAME;
 use crate::configuration::find_command;
 use crate::configuration::platform::DownloadPlatform;
 use crate::lock_guard::LockGuard;
@@ -9,10 +10,8 @@ use std::time::Duration;
 use tool_tool_base::result::{Context, ToolToolResult, bail};
 use tracing::warn;
 
-pub fn run_command(workspace: &mut Workspace) -> ToolToolResult<()> {
-    let mut command_args = workspace.adapter().args();
-    // remove the tool-tool binary name
-    command_args.remove(0);
+/// Runs the command given as first argument, passing on the remaining arguments
+pub fn run_command(workspace: &mut Workspace, mut command_args: Vec<String>) -> ToolToolResult<()> {
     let command_name = command_args.remove(0);
     let config = workspace.config();
     let (tool_config, command_config) = find_command(&command_name, config)?;
@@ -40,6 +39,13 @@ pub fn run_command(workspace: &mut Workspace) -> ToolToolResult<()> {
             }
         }
     }
+    if binary_path_maybe.is_some() {
+        // Record the last use of the tool for the cache status and garbage collection
+        let last_used_path = tool_path.join(LAST_USED_FILE_NAME);
+        if let Err(err) = workspace.adapter().create_file(&last_used_path) {
+            warn!("Could not update '{last_used_path}': {err}");
+        }
+    }
     drop(lock_guard);
     let Some(binary_path) = binary_path_maybe else {
         if errors.is_empty() {
@@ -0,0 +1,307 @@
+use crate::adapter::Adapter;
+use crate::configuration::platform::DownloadPlatform;
+use crate::hash::ChecksumAlgorithm;
+use crate::options::is_truthy;
+use crate::types::FilePath;
+use std::time::Duration;
+use tool_tool_base::result::ToolToolResult;
+use tracing::{debug, info, warn};
+
+/// Environment variable overriding the location of the shared cache
+pub const CACHE_DIR_ENV_VAR: &str = "TOOL_TOOL_CACHE_DIR";
+
+/// Environment variable to enable the shared cache (e.g. `TOOL_TOOL_SHARED_CACHE=1`)
+pub const SHARED_CACHE_ENV_VAR: &str = "TOOL_TOOL_SHARED_CACHE";
+
+/// Age after which temporary files in the shared cache are considered left behind by interrupted
+/// runs, younger files may still be in use by runs in other projects
+const STALE_TEMP_FILE_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// User-level store of downloaded artifacts, shared by all projects and keyed by checksum
+///
+/// Artifacts are stored as `artifacts/<algorithm>/<checksum>` below the cache directory, and are
+/// only added after they passed checksum (and signature) verification
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedCache {
+    dir: FilePath,
+}
+
+impl SharedCache {
+    pub fn new(dir: impl Into<FilePath>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Determines the shared cache from the environment, `None` unless it is enabled with
+    /// `TOOL_TOOL_SHARED_CACHE` and a suitable location could be found
+    ///
+    /// The location is `$TOOL_TOOL_CACHE_DIR`, falling back to `$XDG_CACHE_HOME/tool-tool`,
+    /// `%LOCALAPPDATA%\tool-tool` on windows and `$HOME/.cache/tool-tool` elsewhere
+    pub fn from_env(env: &[(String, String)], platform: DownloadPlatform) -> Option<Self> {
+        let get = |key: &str| {
+            env.iter()
+                .find(|(env_key, value)| env_key == key && !value.is_empty())
+                .map(|(_, value)| value.as_str())
+        };
+        if !get(SHARED_CACHE_ENV_VAR).is_some_and(is_truthy) {
+            return None;
+        }
+        let dir = if let Some(dir) = get(CACHE_DIR_ENV_VAR) {
+            dir.to_string()
+        } else if let Some(cache_home) = get("XDG_CACHE_HOME") {
+            format!("{cache_home}/tool-tool")
+        } else if platform == DownloadPlatform::Windows {
+            format!("{}/tool-tool", get("LOCALAPPDATA")?)
+        } else {
+            format!("{}/.cache/tool-tool", get("HOME")?)
+        };
+        Some(Self::new(dir.replace('\\', "/")))
+    }
+
+    pub fn dir(&self) -> &FilePath {
+        &self.dir
+    }
+
+    fn temp_dir(&self) -> FilePath {
+        self.dir.join("tmp")
+    }
+
+    fn artifact_path(&self, algorithm: ChecksumAlgorithm, checksum: &str) -> FilePath {
+        self.dir
+            .join("artifacts")
+            .join(algorithm.as_str())
+            .join(checksum.to_ascii_lowercase())
+    }
+
+    /// Returns whether an artifact with the given checksum is stored in the cache
+    pub fn contains(
+        &self,
+        adapter: &dyn Adapter,
+        algorithm: ChecksumAlgorithm,
+        checksum: &str,
+    ) -> ToolToolResult<bool> {
+        adapter.file_exists(&self.artifact_path(algorithm, checksum))
+    }
+
+    /// Links (or copies) the artifact with the given checksum to the destination
+    ///
+    /// Returns false if the artifact is not stored, corrupted entries are removed
+    pub fn fetch(
+        &self,
+        adapter: &dyn Adapter,
+        algorithm: ChecksumAlgorithm,
+        checksum: &str,
+        destination: &FilePath,
+    ) -> ToolToolResult<bool> {
+        let artifact_path = self.artifact_path(algorithm, checksum);
+        if !adapter.file_exists(&artifact_path)? {
+            return Ok(false);
+        }
+        adapter.link_file(&artifact_path, destination)?;
+        let actual = algorithm.compute(adapter.read_file(destination)?)?;
+        if !actual.eq_ignore_ascii_case(checksum) {
+            warn!("Removing corrupted artifact {artifact_path} from the shared cache");
+            adapter.delete_file(&artifact_path)?;
+            adapter.delete_file(destination)?;
+            return Ok(false);
+        }
+        info!("Using artifact {artifact_path} from the shared cache");
+        Ok(true)
+    }
+
+    /// Adds a verified artifact to the cache under each of its checksums
+    ///
+    /// Entries are linked into a temporary file first and then renamed, so concurrent runs in
+    /// other projects never see partial artifacts
+    pub fn store(
+        &self,
+        adapter: &dyn Adapter,
+        source: &FilePath,
+        checksums: &[(ChecksumAlgorithm, &str)],
+    ) -> ToolToolResult<()> {
+        for (algorithm, checksum) in checksums {
+            let artifact_path = self.artifact_path(*algorithm, checksum);
+            if adapter.file_exists(&artifact_path)? {
+                continue;
+            }
+            debug!("Adding {source} to the shared cache as {artifact_path}");
+            let temp_dir = self.temp_dir();
+            adapter.create_directory_all(&temp_dir)?;
+            let temp_path = temp_dir.join(adapter.random_string()?);
+            adapter.link_file(source, &temp_path)?;
+            if let Some(parent) = artifact_path.parent() {
+                adapter.create_directory_all(&parent.to_relative_path_buf())?;
+            }
+            if let Err(error) = adapter.rename(&temp_path, &artifact_path) {
+                adapter.delete_file(&temp_path)?;
+                // Another project may have stored the same artifact in the meantime
+                if !adapter.file_exists(&artifact_path)? {
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes temporary files left behind by runs that were interrupted while adding artifacts
+    pub fn remove_stale_temp_files(&self, adapter: &dyn Adapter) -> ToolToolResult<()> {
+        let temp_dir = self.temp_dir();
+        let now = adapter.system_time()?;
+        for name in adapter.list_directory(&temp_dir)? {
+            let path = temp_dir.join(name);
+            if now.saturating_sub(adapter.file_metadata(&path)?.modified) > STALE_TEMP_FILE_AGE {
+                info!("Removing stale temporary file {path} from the shared cache");
+                adapter.delete_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_adapter::MockAdapter;
+    use expect_test::expect;
+
+    fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn cache_dir(pairs: &[(&str, &str)], platform: DownloadPlatform) -> Option<String> {
+        SharedCache::from_env(&env(pairs), platform).map(|cache| cache.dir().to_string())
+    }
+
+    #[test]
+    fn location_from_env() {
+        let linux = DownloadPlatform::Linux;
+        let enabled = (SHARED_CACHE_ENV_VAR, "1");
+        assert_eq!(cache_dir(&[enabled], linux), None);
+        assert_eq!(
+            cache_dir(&[enabled, ("HOME", "/home/user")], linux).as_deref(),
+            Some("/home/user/.cache/tool-tool")
+        );
+        assert_eq!(
+            cache_dir(
+                &[
+                    enabled,
+                    ("HOME", "/home/user"),
+                    ("XDG_CACHE_HOME", "/xdg/cache")
+                ],
+                linux
+            )
+            .as_deref(),
+            Some("/xdg/cache/tool-tool")
+        );
+        assert_eq!(
+            cache_dir(
+                &[
+                    enabled,
+                    ("XDG_CACHE_HOME", "/xdg/cache"),
+                    (CACHE_DIR_ENV_VAR, "/custom")
+                ],
+                linux
+            )
+            .as_deref(),
+            Some("/custom")
+        );
+        assert_eq!(
+            cache_dir(
+                &[enabled, ("LOCALAPPDATA", r"C:\Users\user\AppData\Local")],
+                DownloadPlatform::Windows
+            )
+            .as_deref(),
+            Some("C:/Users/user/AppData/Local/tool-tool")
+        );
+        // The shared cache is opt-in
+        assert_eq!(cache_dir(&[("HOME", "/home/user")], linux), None);
+        assert_eq!(
+            cache_dir(
+                &[("HOME", "/home/user"), (SHARED_CACHE_ENV_VAR, "0")],
+                linux
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn store_and_fetch() -> ToolToolResult<()> {
+        let adapter = MockAdapter::new();
+        adapter.try_lock()?;
+        adapter.set_file("download", "artifact");
+        let cache = SharedCache::new("/cache");
+        let sha256 = ChecksumAlgorithm::Sha256.compute(&b"artifact"[..])?;
+        assert!(!cache.fetch(
+            &adapter,
+            ChecksumAlgorithm::Sha256,
+            &sha256,
+            &FilePath::from("fetched")
+        )?);
+        cache.store(
+            &adapter,
+            &FilePath::from("download"),
+            &[(ChecksumAlgorithm::Sha256, &sha256)],
+        )?;
+        assert!(cache.contains(&adapter, ChecksumAlgorithm::Sha256, &sha256)?);
+        assert!(cache.fetch(
+            &adapter,
+            ChecksumAlgorithm::Sha256,
+            &sha256,
+            &FilePath::from("fetched")
+        )?);
+        adapter.verify_effects(expect![[r#"
+            TRY LOCK
+            FILE EXISTS?: /cache/artifacts/sha256/c7c5c1d70c5dec4416ab6158afd0b223ef40c29b1dc1f97ed9428b94d4cadb1c
+            FILE EXISTS?: /cache/artifacts/sha256/c7c5c1d70c5dec4416ab6158afd0b223ef40c29b1dc1f97ed9428b94d4cadb1c
+            CREATE DIR: /cache/tmp
+            RANDOM STRING
+            LINK FILE: download -> /cache/tmp/rand-0
+            CREATE DIR: /cache/artifacts/sha256
+            RENAME: /cache/tmp/rand-0 -> /cache/artifacts/sha256/c7c5c1d70c5dec4416ab6158afd0b223ef40c29b1dc1f97ed9428b94d4cadb1c
+            FILE EXISTS?: /cache/artifacts/sha256/c7c5c1d70c5dec4416ab6158afd0b223ef40c29b1dc1f97ed9428b94d4cadb1c
+            FILE EXISTS?: /cache/artifacts/sha256/c7c5c1d70c5dec4416ab6158afd0b223ef40c29b1dc1f97ed9428b94d4cadb1c
+            LINK FILE: /cache/artifacts/sha256/c7c5c1d70c5dec4416ab6158afd0b223ef40c29b1dc1f97ed9428b94d4cadb1c -> fetched
+            READ FILE: fetched
+        "#]]);
+        Ok(())
+    }
+
+    #[test]
+    fn fetch_removes_corrupted_artifact() -> ToolToolResult<()> {
+        let adapter = MockAdapter::new();
+        adapter.try_lock()?;
+        let cache = SharedCache::new("/cache");
+        let sha256 = ChecksumAlgorithm::Sha256.compute(&b"artifact"[..])?;
+        adapter.set_file(&format!("/cache/artifacts/sha256/{sha256}"), "corrupted");
+        assert!(!cache.fetch(
+            &adapter,
+            ChecksumAlgorithm::Sha256,
+            &sha256,
+            &FilePath::from("fetched")
+        )?);
+        assert!(!cache.contains(&adapter, ChecksumAlgorithm::Sha256, &sha256)?);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_stale_temp_files() -> ToolToolResult<()> {
+        let adapter = MockAdapter::new();
+        adapter.try_lock()?;
+        adapter.set_file("/cache/tmp/stale", "partial");
+        adapter.set_file("/cache/tmp/recent", "partial");
+        adapter.set_modified("/cache/tmp/stale", Duration::from_secs(1000));
+        adapter.set_modified("/cache/tmp/recent", Duration::from_secs(5000));
+        adapter.set_system_time(Duration::from_secs(7200));
+        SharedCache::new("/cache").remove_stale_temp_files(&adapter)?;
+        adapter.verify_effects(expect![[r#"
+            TRY LOCK
+            LIST DIR: /cache/tmp
+            METADATA: /cache/tmp/recent
+            METADATA: /cache/tmp/stale
+            DELETE FILE: /cache/tmp/stale
+        "#]]);
+        Ok(())
+    }
+}
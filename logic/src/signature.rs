@@ -0,0 +1,263 @@
+use crate::hash::read_chunks;
+use sha2::{Digest, Sha256, Sha512};
+use signature::Verifier;
+use ssh_key::{Algorithm, HashAlg, SshSig};
+use std::fmt::Display;
+use std::io::Read;
+use tool_tool_base::result::{ToolToolResult, bail, err};
+
+/// Namespace used by `ssh-keygen -Y sign -n file` if none is configured
+pub const DEFAULT_SSH_NAMESPACE: &str = "file";
+
+/// Format of a trusted public key and the signatures made with it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureFormat {
+    /// minisign (or signify compatible) Ed25519 signatures
+    Minisign,
+    /// OpenSSH signatures as created by `ssh-keygen -Y sign`
+    Ssh,
+}
+
+impl Display for SignatureFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureFormat::Minisign => write!(f, "minisign"),
+            SignatureFormat::Ssh => write!(f, "ssh"),
+        }
+    }
+}
+
+/// Public key trusted to sign the artifacts of a tool
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicKey {
+    pub format: SignatureFormat,
+    pub key: String,
+    /// Namespace the signature was created for, only used by SSH signatures
+    pub namespace: Option<String>,
+}
+
+impl PublicKey {
+    /// Parses a minisign public key (optionally including the comment line of the `.pub` file)
+    /// or an OpenSSH public key (`ssh-ed25519 AAAA...`)
+    pub fn parse(key: &str, namespace: Option<&str>) -> ToolToolResult<Self> {
+        let key = key.trim();
+        if key.starts_with("ssh-") || key.starts_with("sk-") || key.starts_with("ecdsa-") {
+            let public_key = ssh_key::PublicKey::from_openssh(key)
+                .map_err(|error| err!("Invalid SSH public key: {error}"))?;
+            if public_key.algorithm() != Algorithm::Ed25519 {
+                bail!(
+                    "Unsupported SSH public key algorithm '{}', only ssh-ed25519 keys are supported",
+                    public_key.algorithm()
+                );
+            }
+            return Ok(Self {
+                format: SignatureFormat::Ssh,
+                key: key.to_string(),
+                namespace: Some(namespace.unwrap_or(DEFAULT_SSH_NAMESPACE).to_string()),
+            });
+        }
+        if namespace.is_some() {
+            bail!("A 'namespace' is only supported for SSH public keys");
+        }
+        // The .pub file of minisign starts with an untrusted comment line
+        let key = key
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty())
+            .unwrap_or_default();
+        minisign_verify::PublicKey::from_base64(key)
+            .map_err(|error| err!("Invalid minisign public key '{key}': {error}"))?;
+        Ok(Self {
+            format: SignatureFormat::Minisign,
+            key: key.to_string(),
+            namespace: None,
+        })
+    }
+
+    /// Verifies the signature of the content read from `content`
+    ///
+    /// Both formats sign a digest of the content (BLAKE2b for minisign, SHA-2 for SSH), so the
+    /// content is hashed while it is read instead of being loaded into memory. Legacy minisign
+    /// signatures of the raw content are not supported
+    pub fn verify(&self, content: impl Read, signature: &str) -> ToolToolResult<()> {
+        match self.format {
+            SignatureFormat::Minisign => {
+                let public_key = minisign_verify::PublicKey::from_base64(&self.key)
+                    .map_err(|error| err!("Invalid minisign public key: {error}"))?;
+                let signature = minisign_verify::Signature::decode(signature)
+                    .map_err(|error| err!("Invalid minisign signature: {error}"))?;
+                let mut verifier = public_key
+                    .verify_stream(&signature)
+                    .map_err(|error| err!("{error}"))?;
+                read_chunks(content, |chunk| verifier.update(chunk))?;
+                verifier.finalize().map_err(|error| err!("{error}"))
+            }
+            SignatureFormat::Ssh => {
+                let public_key = ssh_key::PublicKey::from_openssh(&self.key)
+                    .map_err(|error| err!("Invalid SSH public key: {error}"))?;
+                let signature = SshSig::from_pem(signature.trim())
+                    .map_err(|error| err!("Invalid SSH signature: {error}"))?;
+                let namespace = self.namespace.as_deref().unwrap_or(DEFAULT_SSH_NAMESPACE);
+                if public_key.key_data() != signature.public_key() {
+                    bail!("The signature was created with a different key than the one provided");
+                }
+                if namespace != signature.namespace() {
+                    bail!(
+                        "The signature was created for namespace '{}', expected '{namespace}'",
+                        signature.namespace()
+                    );
+                }
+                let hash = match signature.hash_alg() {
+                    HashAlg::Sha256 => digest::<Sha256>(content)?,
+                    HashAlg::Sha512 => digest::<Sha512>(content)?,
+                    other => bail!("Unsupported SSH signature hash algorithm '{other}'"),
+                };
+                let signed_data = ssh_signed_data(&signature, &hash);
+                public_key
+                    .key_data()
+                    .verify(&signed_data, signature.signature())
+                    .map_err(|error| {
+                        err!(
+                            "The signature verification failed: {}",
+                            ssh_key::Error::from(error)
+                        )
+                    })
+            }
+        }
+    }
+}
+
+fn digest<D: Digest>(content: impl Read) -> ToolToolResult<Vec<u8>> {
+    let mut hasher = D::new();
+    read_chunks(content, |chunk| hasher.update(chunk))?;
+    Ok(hasher.finalize().to_vec())
+}
+
+/// The data an SSH signature signs: the namespace and the hash of the content, see the
+/// [SSHSIG format](https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.sshsig)
+fn ssh_signed_data(signature: &SshSig, hash: &[u8]) -> Vec<u8> {
+    let mut signed_data = b"SSHSIG".to_vec();
+    for field in [
+        signature.namespace().as_bytes(),
+        signature.reserved(),
+        signature.hash_alg().as_str().as_bytes(),
+        hash,
+    ] {
+        signed_data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        signed_data.extend_from_slice(field);
+    }
+    signed_data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::signing::{MinisignTestKey, SshTestKey};
+    use expect_test::expect;
+
+    const CONTENT: &[u8] = b"tool content";
+
+    fn verify_error(public_key: &PublicKey, signature: &str) -> String {
+        format!(
+            "{:#}",
+            public_key
+                .verify(CONTENT, signature)
+                .expect_err("Expected error")
+        )
+    }
+
+    #[test]
+    fn minisign_signature() -> ToolToolResult<()> {
+        let key = MinisignTestKey::new(1);
+        let public_key = PublicKey::parse(&key.public_key_file(), None)?;
+        assert_eq!(public_key.format, SignatureFormat::Minisign);
+        assert_eq!(public_key.key, key.public_key());
+        public_key.verify(CONTENT, &key.sign(CONTENT))?;
+        Ok(())
+    }
+
+    #[test]
+    fn minisign_tampered_content() -> ToolToolResult<()> {
+        let key = MinisignTestKey::new(1);
+        let public_key = PublicKey::parse(&key.public_key(), None)?;
+        expect!["The signature verification failed"]
+            .assert_eq(&verify_error(&public_key, &key.sign(b"other content")));
+        Ok(())
+    }
+
+    #[test]
+    fn minisign_wrong_key() -> ToolToolResult<()> {
+        let public_key = PublicKey::parse(&MinisignTestKey::new(1).public_key(), None)?;
+        expect!["The signature was created with a different key than the one provided"].assert_eq(
+            &verify_error(&public_key, &MinisignTestKey::new(2).sign(CONTENT)),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ssh_signature() -> ToolToolResult<()> {
+        let key = SshTestKey::new(1);
+        let public_key = PublicKey::parse(&key.public_key(), None)?;
+        assert_eq!(public_key.format, SignatureFormat::Ssh);
+        assert_eq!(public_key.namespace.as_deref(), Some("file"));
+        public_key.verify(CONTENT, &key.sign("file", CONTENT))?;
+        Ok(())
+    }
+
+    #[test]
+    fn ssh_tampered_content() -> ToolToolResult<()> {
+        let key = SshTestKey::new(1);
+        let public_key = PublicKey::parse(&key.public_key(), None)?;
+        expect!["The signature verification failed: cryptographic error"].assert_eq(&verify_error(
+            &public_key,
+            &key.sign("file", b"other content"),
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn ssh_wrong_key() -> ToolToolResult<()> {
+        let public_key = PublicKey::parse(&SshTestKey::new(1).public_key(), None)?;
+        expect!["The signature was created with a different key than the one provided"].assert_eq(
+            &verify_error(&public_key, &SshTestKey::new(2).sign("file", CONTENT)),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ssh_wrong_namespace() -> ToolToolResult<()> {
+        let key = SshTestKey::new(1);
+        let public_key = PublicKey::parse(&key.public_key(), Some("releases"))?;
+        expect!["The signature was created for namespace 'file', expected 'releases'"]
+            .assert_eq(&verify_error(&public_key, &key.sign("file", CONTENT)));
+        Ok(())
+    }
+
+    #[test]
+    fn signature_of_content_larger_than_a_chunk() -> ToolToolResult<()> {
+        let content = (0..100_000u32).map(|i| i as u8).collect::<Vec<_>>();
+        let minisign_key = MinisignTestKey::new(1);
+        PublicKey::parse(&minisign_key.public_key(), None)?
+            .verify(content.as_slice(), &minisign_key.sign(&content))?;
+        let ssh_key = SshTestKey::new(1);
+        PublicKey::parse(&ssh_key.public_key(), None)?
+            .verify(content.as_slice(), &ssh_key.sign("file", &content))?;
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_public_keys() {
+        let error = |key: &str, namespace: Option<&str>| {
+            format!(
+                "{:#}",
+                PublicKey::parse(key, namespace).expect_err("Expected error")
+            )
+        };
+        expect!["Invalid minisign public key 'RWQnotakey': Invalid encoding in minisign data"]
+            .assert_eq(&error("RWQnotakey", None));
+        expect!["Invalid SSH public key: Base64 encoding error: invalid Base64 encoding"]
+            .assert_eq(&error("ssh-ed25519 notakey", None));
+        expect!["A 'namespace' is only supported for SSH public keys"]
+            .assert_eq(&error(&MinisignTestKey::new(1).public_key(), Some("file")));
+    }
+}
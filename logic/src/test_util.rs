@@ -1,3 +1,5 @@
 pub(crate) mod archive_builder;
-pub(crate) mod targz_builder;
+pub(crate) mod compressor;
+pub(crate) mod signing;
+pub(crate) mod tar_builder;
 pub(crate) mod zip_builder;
@@ -0,0 +1,57 @@
+use bzip2::write::BzEncoder;
+use flate2::write::GzEncoder;
+use lzma_rust2::{XzOptions, XzWriter};
+use ruzstd::encoding::{CompressionLevel, compress_to_vec};
+use std::io::Write;
+use tool_tool_base::result::ToolToolResult;
+
+/// Compression format used to wrap test archives and single file artifacts
+pub trait Compressor {
+    fn compress(data: &[u8]) -> ToolToolResult<Vec<u8>>;
+}
+
+pub struct NoCompressor;
+
+impl Compressor for NoCompressor {
+    fn compress(data: &[u8]) -> ToolToolResult<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+}
+
+pub struct GzipCompressor;
+
+impl Compressor for GzipCompressor {
+    fn compress(data: &[u8]) -> ToolToolResult<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    }
+}
+
+pub struct XzCompressor;
+
+impl Compressor for XzCompressor {
+    fn compress(data: &[u8]) -> ToolToolResult<Vec<u8>> {
+        let mut writer = XzWriter::new(Vec::new(), XzOptions::with_preset(6))?;
+        writer.write_all(data)?;
+        Ok(writer.finish()?)
+    }
+}
+
+pub struct Bzip2Compressor;
+
+impl Compressor for Bzip2Compressor {
+    fn compress(data: &[u8]) -> ToolToolResult<Vec<u8>> {
+        let mut encoder = BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    }
+}
+
+pub struct ZstdCompressor;
+
+impl Compressor for ZstdCompressor {
+    fn compress(data: &[u8]) -> ToolToolResult<Vec<u8>> {
+        Ok(compress_to_vec(data, CompressionLevel::Fastest))
+    }
+}
@@ -0,0 +1,84 @@
+use base64ct::{Base64, Encoding};
+use blake2::{Blake2b512, Digest};
+use ed25519_dalek::{Signer, SigningKey};
+use ssh_key::private::Ed25519Keypair;
+use ssh_key::{HashAlg, LineEnding, PrivateKey};
+
+/// Deterministic minisign key pair for signing test artifacts
+pub struct MinisignTestKey {
+    key_id: [u8; 8],
+    signing_key: SigningKey,
+}
+
+impl MinisignTestKey {
+    pub fn new(seed: u8) -> Self {
+        Self {
+            key_id: [seed; 8],
+            signing_key: SigningKey::from_bytes(&[seed; 32]),
+        }
+    }
+
+    /// Base64 encoded public key, as found in the second line of a minisign `.pub` file
+    pub fn public_key(&self) -> String {
+        let mut bytes = b"Ed".to_vec();
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(self.signing_key.verifying_key().as_bytes());
+        Base64::encode_string(&bytes)
+    }
+
+    /// Content of a minisign `.pub` file
+    pub fn public_key_file(&self) -> String {
+        format!(
+            "untrusted comment: minisign public key {:X}\n{}\n",
+            u64::from_le_bytes(self.key_id),
+            self.public_key()
+        )
+    }
+
+    /// Creates a prehashed minisign signature file, like `minisign -S`
+    pub fn sign(&self, content: &[u8]) -> String {
+        let hash = Blake2b512::digest(content);
+        let signature = self.signing_key.sign(&hash).to_bytes();
+        let mut signature_bytes = b"ED".to_vec();
+        signature_bytes.extend_from_slice(&self.key_id);
+        signature_bytes.extend_from_slice(&signature);
+        let trusted_comment = "timestamp:0\tfile:test";
+        let mut global = signature.to_vec();
+        global.extend_from_slice(trusted_comment.as_bytes());
+        let global_signature = self.signing_key.sign(&global).to_bytes();
+        format!(
+            "untrusted comment: signature from minisign secret key\n{}\ntrusted comment: {trusted_comment}\n{}\n",
+            Base64::encode_string(&signature_bytes),
+            Base64::encode_string(&global_signature)
+        )
+    }
+}
+
+/// Deterministic SSH Ed25519 key pair for signing test artifacts
+pub struct SshTestKey {
+    private_key: PrivateKey,
+}
+
+impl SshTestKey {
+    pub fn new(seed: u8) -> Self {
+        Self {
+            private_key: PrivateKey::from(Ed25519Keypair::from_seed(&[seed; 32])),
+        }
+    }
+
+    /// Public key in OpenSSH format (`ssh-ed25519 AAAA...`)
+    pub fn public_key(&self) -> String {
+        self.private_key
+            .public_key()
+            .to_openssh()
+            .expect("Failed to encode SSH public key")
+    }
+
+    /// Creates a signature like `ssh-keygen -Y sign -n <namespace>`
+    pub fn sign(&self, namespace: &str, content: &[u8]) -> String {
+        self.private_key
+            .sign(namespace, HashAlg::Sha512, content)
+            .and_then(|signature| signature.to_pem(LineEnding::LF))
+            .expect("Failed to create SSH signature")
+    }
+}
@@ -0,0 +1,198 @@
+use crate::test_util::archive_builder::ArchiveBuilder;
+use crate::test_util::compressor::{
+    Bzip2Compressor, Compressor, GzipCompressor, NoCompressor, XzCompressor, ZstdCompressor,
+};
+use std::marker::PhantomData;
+use tar::Header;
+use tool_tool_base::result::ToolToolResult;
+
+pub type TarBuilder = CompressedTarBuilder<NoCompressor>;
+pub type TarGzBuilder = CompressedTarBuilder<GzipCompressor>;
+pub type TarXzBuilder = CompressedTarBuilder<XzCompressor>;
+pub type TarBz2Builder = CompressedTarBuilder<Bzip2Compressor>;
+pub type TarZstBuilder = CompressedTarBuilder<ZstdCompressor>;
+
+pub struct CompressedTarBuilder<C: Compressor> {
+    tar_builder: tar::Builder<Vec<u8>>,
+    compressor: PhantomData<C>,
+}
+
+impl<C: Compressor> Default for CompressedTarBuilder<C> {
+    fn default() -> Self {
+        Self {
+            tar_builder: tar::Builder::new(Vec::new()),
+            compressor: PhantomData,
+        }
+    }
+}
+
+impl<C: Compressor> CompressedTarBuilder<C> {
+    /// Adds a file without any validation of the path, used to create malicious archives
+    pub fn add_file_with_raw_path(
+        &mut self,
+        path: impl AsRef<[u8]>,
+        content: impl AsRef<[u8]>,
+    ) -> ToolToolResult<()> {
+        let mut header = Header::new_gnu();
+        let name = &mut header.as_old_mut().name;
+        name[..path.as_ref().len()].copy_from_slice(path.as_ref());
+        header.set_size(content.as_ref().len() as u64);
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_cksum();
+        self.tar_builder.append(&header, content.as_ref())?;
+        Ok(())
+    }
+
+    pub fn add_hardlink(
+        &mut self,
+        path: impl AsRef<str>,
+        target: impl AsRef<str>,
+    ) -> ToolToolResult<()> {
+        self.add_link(tar::EntryType::Link, path, target)
+    }
+
+    pub fn add_entry(
+        &mut self,
+        path: impl AsRef<str>,
+        entry_type: tar::EntryType,
+    ) -> ToolToolResult<()> {
+        let mut header = Header::new_gnu();
+        header.set_path(path.as_ref())?;
+        header.set_entry_type(entry_type);
+        header.set_size(0);
+        header.set_cksum();
+        self.tar_builder.append(&header, std::io::empty())?;
+        Ok(())
+    }
+
+    fn add_link(
+        &mut self,
+        entry_type: tar::EntryType,
+        path: impl AsRef<str>,
+        target: impl AsRef<str>,
+    ) -> ToolToolResult<()> {
+        let mut header = Header::new_gnu();
+        header.set_path(path.as_ref())?;
+        header.set_link_name_literal(target.as_ref())?;
+        header.set_entry_type(entry_type);
+        header.set_size(0);
+        header.set_cksum();
+        self.tar_builder.append(&header, std::io::empty())?;
+        Ok(())
+    }
+}
+
+impl<C: Compressor> ArchiveBuilder for CompressedTarBuilder<C> {
+    fn add_file(&mut self, path: impl AsRef<str>, content: impl AsRef<[u8]>) -> ToolToolResult<()> {
+        let mut header = Header::new_gnu();
+        header.set_size(content.as_ref().len() as u64);
+        self.tar_builder
+            .append_data(&mut header, path.as_ref(), content.as_ref())?;
+        Ok(())
+    }
+
+    fn add_executable(
+        &mut self,
+        path: impl AsRef<str>,
+        content: impl AsRef<[u8]>,
+    ) -> ToolToolResult<()> {
+        let mut header = Header::new_gnu();
+        header.set_size(content.as_ref().len() as u64);
+        header.set_mode(0o755);
+        self.tar_builder
+            .append_data(&mut header, path.as_ref(), content.as_ref())?;
+        Ok(())
+    }
+
+    fn add_directory(&mut self, path: impl AsRef<str>) -> ToolToolResult<()> {
+        let mut header = Header::new_gnu();
+        header.set_path(path.as_ref())?;
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_cksum();
+        self.tar_builder.append(&header, std::io::empty())?;
+        Ok(())
+    }
+
+    fn add_symlink(
+        &mut self,
+        path: impl AsRef<str>,
+        target: impl AsRef<str>,
+    ) -> ToolToolResult<()> {
+        self.add_link(tar::EntryType::Symlink, path, target)
+    }
+
+    fn build(self) -> ToolToolResult<Vec<u8>> {
+        C::compress(&self.tar_builder.into_inner()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expect_test::expect;
+    use flate2::read::GzDecoder;
+    use std::fmt::Write;
+    use std::io::{Cursor, Read};
+    use tar::Archive;
+
+    #[test]
+    fn test_targz_builder_empty() -> ToolToolResult<()> {
+        let file = TarGzBuilder::default().build()?;
+        let tar = GzDecoder::new(Cursor::new(file));
+        let mut archive = Archive::new(tar);
+        assert!(archive.entries()?.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_targz_builder_with_files() -> ToolToolResult<()> {
+        let mut targz_builder = TarGzBuilder::default();
+        targz_builder.add_file("foo", b"bar")?;
+        targz_builder.add_directory("folder/2/3")?;
+        targz_builder.add_file("fizz/buzz.txt", b"foobar")?;
+        targz_builder.add_symlink("link", "fizz/buzz.txt")?;
+        targz_builder.add_hardlink("hardlink", "foo")?;
+        let file = targz_builder.build()?;
+        let tar = GzDecoder::new(Cursor::new(file));
+        let mut archive = Archive::new(tar);
+        let mut content = String::new();
+        for archive_entry in archive.entries()? {
+            let mut archive_entry = archive_entry?;
+            match archive_entry.header().entry_type() {
+                tar::EntryType::Regular => {
+                    let mut entry_content = String::new();
+                    archive_entry.read_to_string(&mut entry_content)?;
+                    writeln!(content, "{:?}: '{entry_content}'", archive_entry.path()?)?;
+                }
+                tar::EntryType::Directory => {
+                    writeln!(content, "{:?} (DIR)", archive_entry.path()?)?;
+                }
+                tar::EntryType::Symlink | tar::EntryType::Link => {
+                    writeln!(
+                        content,
+                        "{:?} -> {:?} ({:?})",
+                        archive_entry.path()?,
+                        archive_entry.link_name()?.unwrap_or_default(),
+                        archive_entry.header().entry_type()
+                    )?;
+                }
+                _ => {
+                    panic!(
+                        "Unsupported entry type: {:?}",
+                        archive_entry.header().entry_type()
+                    );
+                }
+            }
+        }
+        expect![[r#"
+            "foo": 'bar'
+            "folder/2/3" (DIR)
+            "fizz/buzz.txt": 'foobar'
+            "link" -> "fizz/buzz.txt" (Symlink)
+            "hardlink" -> "foo" (Link)
+        "#]]
+        .assert_eq(&content);
+        Ok(())
+    }
+}
@@ -27,12 +27,38 @@ impl ArchiveBuilder for ZipBuilder {
         Ok(())
     }
 
+    fn add_executable(
+        &mut self,
+        path: impl AsRef<str>,
+        content: impl AsRef<[u8]>,
+    ) -> ToolToolResult<()> {
+        self.zip_writer.start_file(
+            path.as_ref().to_string(),
+            create_file_options().unix_permissions(0o755),
+        )?;
+        self.zip_writer.write_all(content.as_ref())?;
+        Ok(())
+    }
+
     fn add_directory(&mut self, path: impl AsRef<str>) -> ToolToolResult<()> {
         self.zip_writer
             .add_directory(path.as_ref().to_string(), create_file_options())?;
         Ok(())
     }
 
+    fn add_symlink(
+        &mut self,
+        path: impl AsRef<str>,
+        target: impl AsRef<str>,
+    ) -> ToolToolResult<()> {
+        self.zip_writer.add_symlink(
+            path.as_ref().to_string(),
+            target.as_ref().to_string(),
+            create_file_options(),
+        )?;
+        Ok(())
+    }
+
     fn build(self) -> ToolToolResult<Vec<u8>> {
         let cursor = self.zip_writer.finish()?;
         Ok(cursor.into_inner())
@@ -60,12 +86,17 @@ mod test {
         zip_builder.add_file("foo", b"bar")?;
         zip_builder.add_directory("folder/2/3")?;
         zip_builder.add_file("fizz/buzz.txt", b"foobar")?;
+        zip_builder.add_symlink("link", "fizz/buzz.txt")?;
         let file = zip_builder.build()?;
         let mut zip_archive = ZipArchive::new(Cursor::new(file))?;
         let mut content = String::new();
         for i in 0..zip_archive.len() {
             let mut zip_entry = zip_archive.by_index(i)?;
-            if zip_entry.is_file() {
+            if zip_entry.is_symlink() {
+                let mut target = String::new();
+                zip_entry.read_to_string(&mut target)?;
+                writeln!(content, "{} -> {target} (SYMLINK)", zip_entry.name())?;
+            } else if zip_entry.is_file() {
                 let mut entry_content = String::new();
                 zip_entry.read_to_string(&mut entry_content)?;
                 writeln!(content, "{}: '{entry_content}'", zip_entry.name())?;
@@ -77,6 +108,7 @@ mod test {
             foo: 'bar'
             folder/2/3/ (DIR)
             fizz/buzz.txt: 'foobar'
+            link -> fizz/buzz.txt (SYMLINK)
         "#]]
         .assert_eq(&content);
         Ok(())
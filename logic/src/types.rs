@@ -1,3 +1,6 @@
+/// Path relative to the project directory
+///
+/// Absolute paths refer to locations outside of the project, like the shared cache
 pub type FilePath = relative_path::RelativePathBuf;
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,259 @@
+use crate::adapter::Adapter;
+use crate::configuration::platform::DownloadPlatform;
+use crate::mirrors::MirrorRule;
+use crate::types::FilePath;
+use kdl::{KdlDocument, KdlNode, KdlValue};
+use std::time::Duration;
+use tool_tool_base::result::{Context, ToolToolResult, bail};
+
+/// Environment variable overriding the location of the user configuration file
+pub const USER_CONFIG_ENV_VAR: &str = "TOOL_TOOL_USER_CONFIG";
+
+/// Settings of the user (rather than the project), e.g. for the corporate network
+///
+/// ```kdl
+/// network {
+///     ca_bundle "/etc/ssl/certs/corporate-ca.pem"
+///     connect_timeout 10
+///     read_timeout 60
+/// }
+/// // prefix rewrites of download urls, the mirrors are tried in order
+/// mirrors {
+///     "https://github.com/" "https://mirror.corp/github/" "https://backup.corp/github/"
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UserConfig {
+    /// Additional trusted CA certificates (PEM), relative paths are resolved against the
+    /// directory of the configuration file
+    pub ca_bundle: Option<String>,
+    pub connect_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub mirrors: Vec<MirrorRule>,
+}
+
+impl UserConfig {
+    /// Determines the location of the user configuration file from the environment
+    ///
+    /// The location is `$TOOL_TOOL_USER_CONFIG`, falling back to
+    /// `$XDG_CONFIG_HOME/tool-tool/config.kdl`, `%APPDATA%\tool-tool\config.kdl` on windows and
+    /// `$HOME/.config/tool-tool/config.kdl` elsewhere
+    pub fn path_from_env(env: &[(String, String)], platform: DownloadPlatform) -> Option<String> {
+        let get = |key: &str| {
+            env.iter()
+                .find(|(env_key, value)| env_key == key && !value.is_empty())
+                .map(|(_, value)| value.as_str())
+        };
+        let path = if let Some(path) = get(USER_CONFIG_ENV_VAR) {
+            path.to_string()
+        } else if let Some(config_home) = get("XDG_CONFIG_HOME") {
+            format!("{config_home}/tool-tool/config.kdl")
+        } else if platform == DownloadPlatform::Windows {
+            format!("{}/tool-tool/config.kdl", get("APPDATA")?)
+        } else {
+            format!("{}/.config/tool-tool/config.kdl", get("HOME")?)
+        };
+        Some(path.replace('\\', "/"))
+    }
+
+    /// Loads the user configuration file, an empty configuration if there is none
+    pub fn load(adapter: &dyn Adapter) -> ToolToolResult<Self> {
+        let Some(path) = Self::path_from_env(&adapter.env(), adapter.get_platform()) else {
+            return Ok(Self::default());
+        };
+        let file_path = FilePath::from(path.as_str());
+        if !adapter.file_exists(&file_path)? {
+            return Ok(Self::default());
+        }
+        let content = std::io::read_to_string(adapter.read_file(&file_path)?)
+            .with_context(|| format!("Failed to read user configuration '{path}'"))?;
+        Self::parse(&path, &content)
+    }
+
+    pub fn parse(filename: &str, kdl: &str) -> ToolToolResult<Self> {
+        let document = kdl
+            .parse::<KdlDocument>()
+            .with_context(|| format!("Could not parse user configuration '{filename}'"))?;
+        let mut config = Self::default();
+        for node in document.nodes() {
+            match node.name().value() {
+                "network" => {
+                    for setting in node.children().iter().flat_map(|children| children.nodes()) {
+                        match setting.name().value() {
+                            "ca_bundle" => config.ca_bundle = Some(string_value(setting)?),
+                            "connect_timeout" => {
+                                config.connect_timeout = Some(seconds_value(setting)?)
+                            }
+                            "read_timeout" => config.read_timeout = Some(seconds_value(setting)?),
+                            other => bail!(
+                                "Unexpected network setting '{other}' in '{filename}', expected one of: ca_bundle, connect_timeout, read_timeout"
+                            ),
+                        }
+                    }
+                }
+                "mirrors" => {
+                    for rule in node.children().iter().flat_map(|children| children.nodes()) {
+                        let prefix = rule.name().value();
+                        let mirrors = rule
+                            .entries()
+                            .iter()
+                            .map(|entry| entry.value().as_string())
+                            .collect::<Option<Vec<_>>>()
+                            .filter(|mirrors| !mirrors.is_empty());
+                        let Some(mirrors) = mirrors else {
+                            bail!(
+                                "Expected mirror urls for '{prefix}' in '{filename}', e.g. \"{prefix}\" \"https://mirror.example.com/\""
+                            );
+                        };
+                        config.mirrors.extend(
+                            mirrors
+                                .into_iter()
+                                .map(|mirror| MirrorRule::new(prefix, mirror)),
+                        );
+                    }
+                }
+                other => bail!(
+                    "Unexpected item '{other}' in user configuration '{filename}', expected one of: network, mirrors"
+                ),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn string_value(node: &KdlNode) -> ToolToolResult<String> {
+    match node.get(0).and_then(KdlValue::as_string) {
+        Some(value) => Ok(value.to_string()),
+        None => bail!("Expected a string value for '{}'", node.name().value()),
+    }
+}
+
+fn seconds_value(node: &KdlNode) -> ToolToolResult<Duration> {
+    match node
+        .get(0)
+        .and_then(KdlValue::as_integer)
+        .and_then(|seconds| u64::try_from(seconds).ok())
+    {
+        Some(seconds) => Ok(Duration::from_secs(seconds)),
+        None => bail!("Expected a number of seconds for '{}'", node.name().value()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn path_from_env() {
+        let linux = DownloadPlatform::Linux;
+        assert_eq!(UserConfig::path_from_env(&[], linux), None);
+        assert_eq!(
+            UserConfig::path_from_env(&env(&[("HOME", "/home/user")]), linux).as_deref(),
+            Some("/home/user/.config/tool-tool/config.kdl")
+        );
+        assert_eq!(
+            UserConfig::path_from_env(
+                &env(&[("HOME", "/home/user"), ("XDG_CONFIG_HOME", "/xdg")]),
+                linux
+            )
+            .as_deref(),
+            Some("/xdg/tool-tool/config.kdl")
+        );
+        assert_eq!(
+            UserConfig::path_from_env(
+                &env(&[
+                    ("XDG_CONFIG_HOME", "/xdg"),
+                    (USER_CONFIG_ENV_VAR, "/custom.kdl")
+                ]),
+                linux
+            )
+            .as_deref(),
+            Some("/custom.kdl")
+        );
+        assert_eq!(
+            UserConfig::path_from_env(
+                &env(&[("APPDATA", r"C:\Users\user\AppData\Roaming")]),
+                DownloadPlatform::Windows
+            )
+            .as_deref(),
+            Some("C:/Users/user/AppData/Roaming/tool-tool/config.kdl")
+        );
+    }
+
+    #[test]
+    fn parse() -> ToolToolResult<()> {
+        assert_eq!(UserConfig::parse("config.kdl", "")?, UserConfig::default());
+        assert_eq!(
+            UserConfig::parse(
+                "config.kdl",
+                r#"
+                network {
+                    ca_bundle "certs/corporate.pem"
+                    connect_timeout 10
+                    read_timeout 120
+                }
+                "#
+            )?,
+            UserConfig {
+                ca_bundle: Some("certs/corporate.pem".to_string()),
+                connect_timeout: Some(Duration::from_secs(10)),
+                read_timeout: Some(Duration::from_secs(120)),
+                mirrors: vec![],
+            }
+        );
+        assert_eq!(
+            UserConfig::parse(
+                "config.kdl",
+                r#"
+                mirrors {
+                    "https://github.com/" "https://mirror.corp/github/" "https://backup.corp/github/"
+                    "https://nodejs.org/dist/" "https://mirror.corp/node/"
+                }
+                "#
+            )?
+            .mirrors,
+            vec![
+                MirrorRule::new("https://github.com/", "https://mirror.corp/github/"),
+                MirrorRule::new("https://github.com/", "https://backup.corp/github/"),
+                MirrorRule::new("https://nodejs.org/dist/", "https://mirror.corp/node/"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |kdl: &str| format!("{:#}", UserConfig::parse("config.kdl", kdl).unwrap_err());
+        assert_eq!(
+            error("proxies {}"),
+            "Unexpected item 'proxies' in user configuration 'config.kdl', expected one of: network, mirrors"
+        );
+        assert_eq!(
+            error("mirrors { \"https://github.com/\" }"),
+            "Expected mirror urls for 'https://github.com/' in 'config.kdl', e.g. \"https://github.com/\" \"https://mirror.example.com/\""
+        );
+        assert_eq!(
+            error("mirrors { \"https://github.com/\" 42 }"),
+            "Expected mirror urls for 'https://github.com/' in 'config.kdl', e.g. \"https://github.com/\" \"https://mirror.example.com/\""
+        );
+        assert_eq!(
+            error("network { timeout 10 }"),
+            "Unexpected network setting 'timeout' in 'config.kdl', expected one of: ca_bundle, connect_timeout, read_timeout"
+        );
+        assert_eq!(
+            error("network { connect_timeout \"10s\" }"),
+            "Expected a number of seconds for 'connect_timeout'"
+        );
+        assert_eq!(
+            error("network { ca_bundle 42 }"),
+            "Expected a string value for 'ca_bundle'"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use crate::download_task::{
+    CHECKSUM_MARKER_FILE_NAME, install_tool, remove_stale_temp_dirs, update_checksums,
+};
+use crate::manifest::{Manifest, ManifestDifferences};
+use crate::workspace::Workspace;
+use tool_tool_base::result::{HelpError, ToolToolError, ToolToolResult, bail};
+use tracing::info;
+
+/// Result of verifying a single installed tool
+enum VerifyOutcome {
+    NotInstalled,
+    Ok(usize),
+    NoManifest,
+    Changed(ManifestDifferences),
+}
+
+/// Verifies the installed files of all tools (or only the given one) against their manifests,
+/// reinstalling tools with modified, missing or extra files if `repair` is set
+pub fn run_verify_task(
+    workspace: &mut Workspace,
+    tool_name: Option<&str>,
+    repair: bool,
+) -> ToolToolResult<()> {
+    if let Some(tool_name) = tool_name
+        && !workspace
+            .config()
+            .tools
+            .iter()
+            .any(|tool| tool.name == tool_name)
+    {
+        bail!("Unknown tool '{tool_name}'");
+    }
+    if repair {
+        remove_stale_temp_dirs(workspace)?;
+    }
+    let adapter = workspace.adapter();
+    let mut new_checksums = workspace.checksums.clone();
+    let mut failed_tools = vec![];
+    for tool in workspace.config().tools.iter() {
+        if tool_name.is_some_and(|tool_name| tool.name != tool_name) {
+            continue;
+        }
+        let tool_dir = workspace.tool_dir(tool);
+        let outcome = if !adapter.file_exists(&tool_dir.join(CHECKSUM_MARKER_FILE_NAME))? {
+            VerifyOutcome::NotInstalled
+        } else if let Some(manifest) = Manifest::load(adapter, &tool_dir)? {
+            let differences = manifest.compare(&Manifest::from_directory(adapter, &tool_dir)?);
+            if differences.is_empty() {
+                VerifyOutcome::Ok(manifest.files.len())
+            } else {
+                VerifyOutcome::Changed(differences)
+            }
+        } else {
+            VerifyOutcome::NoManifest
+        };
+        let prefix = format!("Tool '{}' {}", tool.name, tool.version);
+        let report = match &outcome {
+            VerifyOutcome::NotInstalled => format!("{prefix}: not installed"),
+            VerifyOutcome::Ok(file_count) => format!("{prefix}: OK ({file_count} files)"),
+            VerifyOutcome::NoManifest => {
+                format!("{prefix}: no manifest found, cannot verify installed files")
+            }
+            VerifyOutcome::Changed(differences) => {
+                let count = differences.count();
+                let plural = if count == 1 { "" } else { "s" };
+                let mut report = format!("{prefix}: {count} problem{plural}");
+                for (kind, paths) in [
+                    ("modified", &differences.modified),
+                    ("missing", &differences.missing),
+                    ("extra", &differences.extra),
+                ] {
+                    for path in paths {
+                        report.push_str(&format!("\n\t{kind}: {path}"));
+                    }
+                }
+                report
+            }
+        };
+        adapter.print(&format!("{report}\n"));
+        if matches!(outcome, VerifyOutcome::NotInstalled | VerifyOutcome::Ok(_)) {
+            continue;
+        }
+        if repair {
+            info!("Reinstalling tool '{}'", tool.name);
+            install_tool(workspace, tool, &mut new_checksums)?;
+            adapter.print(&format!("{prefix}: repaired\n"));
+        } else {
+            failed_tools.push(tool.name.as_str());
+        }
+    }
+    if !failed_tools.is_empty() {
+        return Err(ToolToolError::from(HelpError::new(
+            format!("Verification failed for tools: {}", failed_tools.join(", ")),
+            "Run 'tool-tool --verify --repair' to reinstall the affected tools".to_string(),
+        )));
+    }
+    update_checksums(workspace, new_checksums)
+}
@@ -1,15 +1,23 @@
 use crate::adapter::{Adapter, AdapterBox};
 use crate::checksums::Checksums;
+use crate::configuration::platform::HostPlatform;
 use crate::configuration::{
-    CACHE_DIRECTORY, CONFIGURATION_FILE_NAME, TOOL_TOOL_DIRECTORY, ToolConfiguration,
-    ToolToolConfiguration,
+    CACHE_DIRECTORY, CHECKSUM_FILE_NAME, CONFIGURATION_FILE_NAME, TOOL_TOOL_DIRECTORY,
+    ToolConfiguration, ToolToolConfiguration,
 };
+use crate::mirrors::Mirrors;
+use crate::options::Options;
+use crate::shared_cache::SharedCache;
 use crate::types::FilePath;
+use std::sync::{Arc, Mutex, PoisonError};
 use tool_tool_base::result::ToolToolResult;
 
 pub struct Workspace {
     config: ToolToolConfiguration,
     pub(crate) checksums: Checksums,
+    options: Options,
+    /// Parsed on first use, since only downloads need them
+    mirrors: Mutex<Option<Arc<Mirrors>>>,
     adapter: AdapterBox,
 }
 impl Workspace {
@@ -17,10 +25,16 @@ impl Workspace {
         Self {
             config,
             checksums: Checksums::default(),
+            options: Options::default(),
+            mirrors: Mutex::default(),
             adapter,
         }
     }
 
+    pub fn with_options(self, options: Options) -> Self {
+        Self { options, ..self }
+    }
+
     pub fn config(&self) -> &ToolToolConfiguration {
         &self.config
     }
@@ -33,6 +47,10 @@ impl Workspace {
         &self.checksums
     }
 
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
     pub fn config_path(&self) -> FilePath {
         FilePath::from(CONFIGURATION_FILE_NAME)
     }
@@ -40,6 +58,10 @@ impl Workspace {
         FilePath::from(TOOL_TOOL_DIRECTORY)
     }
 
+    pub fn checksums_path(&self) -> FilePath {
+        self.tool_tool_dir().join(CHECKSUM_FILE_NAME)
+    }
+
     pub fn cache_dir(&self) -> FilePath {
         FilePath::from(CACHE_DIRECTORY)
     }
@@ -47,20 +69,45 @@ impl Workspace {
         self.cache_dir().join("tools")
     }
 
+    pub fn host_platform(&self) -> HostPlatform {
+        HostPlatform::from_adapter(self.adapter())
+    }
+
     pub fn tool_dir(&self, tool: &ToolConfiguration) -> FilePath {
         self.cache_dir().join(format!(
             "{}-{}-{}",
             tool.name,
             tool.version,
-            self.adapter.get_platform()
+            self.host_platform()
         ))
     }
 
+    /// User-level artifact cache shared between projects, `None` if disabled
+    pub fn shared_cache(&self) -> Option<SharedCache> {
+        SharedCache::from_env(&self.adapter.env(), self.adapter.get_platform())
+    }
+
+    /// Mirror rules for downloads, from `TOOL_TOOL_MIRRORS` or the user configuration file
+    ///
+    /// The rules are read once per run, parallel downloads wait for the first one to read them
+    pub fn mirrors(&self) -> ToolToolResult<Arc<Mirrors>> {
+        let mut mirrors = self.mirrors.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(mirrors) = &*mirrors {
+            return Ok(mirrors.clone());
+        }
+        let parsed = Arc::new(Mirrors::from_env(self.adapter.as_ref())?);
+        *mirrors = Some(parsed.clone());
+        Ok(parsed)
+    }
+
+    /// Directory holding the temporary directories of downloads and installations in progress
+    pub fn temp_dir(&self) -> FilePath {
+        self.cache_dir().join("tmp")
+    }
+
     pub fn create_temp_dir(&self, prefix: &str) -> ToolToolResult<FilePath> {
         let random_string = self.adapter.random_string()?;
-        let temp_dir = self
-            .cache_dir()
-            .join(format!("tmp/{prefix}-{random_string}"));
+        let temp_dir = self.temp_dir().join(format!("{prefix}-{random_string}"));
         self.adapter.create_directory_all(&temp_dir)?;
         Ok(temp_dir)
     }
@@ -0,0 +1,347 @@
+use base64ct::{Base64, Encoding};
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::path::Path;
+use tool_tool_base::logging::{debug, warn};
+use tool_tool_logic::configuration::platform::DownloadPlatform;
+
+/// Prefix of the environment variables holding a token for a host, followed by the host name in
+/// upper case with all other characters replaced by `_` (e.g. `TOOL_TOOL_AUTH_GITHUB_COM`)
+pub const AUTH_ENV_VAR_PREFIX: &str = "TOOL_TOOL_AUTH_";
+
+/// Environment variable overriding the location of the netrc file
+pub const NETRC_ENV_VAR: &str = "NETRC";
+
+/// Login of a netrc `machine` or `default` entry
+#[derive(Clone, PartialEq)]
+pub struct NetrcLogin {
+    pub login: String,
+    pub password: String,
+}
+
+impl Debug for NetrcLogin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NetrcLogin")
+            .field("login", &self.login)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+/// Logins of a netrc file, as used by curl, git and many other tools
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Netrc {
+    pub machines: Vec<(String, NetrcLogin)>,
+    pub default: Option<NetrcLogin>,
+}
+
+impl Netrc {
+    /// Parses the content of a netrc file
+    ///
+    /// Entries without a password are skipped, `account` values and `macdef` macros are ignored
+    pub fn parse(content: &str) -> Self {
+        let mut netrc = Self::default();
+        let mut tokens = content
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(str::split_whitespace)
+            .peekable();
+        let mut entry: Option<NetrcEntry> = None;
+        while let Some(token) = tokens.next() {
+            match token {
+                "machine" => {
+                    netrc.add(entry.take());
+                    entry = tokens.next().map(|machine| NetrcEntry {
+                        machine: Some(machine.to_ascii_lowercase()),
+                        ..NetrcEntry::default()
+                    });
+                }
+                "default" => {
+                    netrc.add(entry.take());
+                    entry = Some(NetrcEntry::default());
+                }
+                "login" | "password" => {
+                    let value = tokens.next().map(str::to_string);
+                    if let Some(entry) = &mut entry {
+                        if token == "login" {
+                            entry.login = value;
+                        } else {
+                            entry.password = value;
+                        }
+                    }
+                }
+                "account" => {
+                    tokens.next();
+                }
+                "macdef" => {
+                    // Macros end with an empty line, which is lost when splitting into tokens,
+                    // so skip to the next entry instead
+                    netrc.add(entry.take());
+                    while tokens
+                        .next_if(|token| *token != "machine" && *token != "default")
+                        .is_some()
+                    {}
+                }
+                _ => {}
+            }
+        }
+        netrc.add(entry);
+        netrc
+    }
+
+    fn add(&mut self, entry: Option<NetrcEntry>) {
+        let Some(NetrcEntry {
+            machine,
+            login,
+            password: Some(password),
+        }) = entry
+        else {
+            return;
+        };
+        let login = NetrcLogin {
+            login: login.unwrap_or_default(),
+            password,
+        };
+        match machine {
+            Some(machine) => self.machines.push((machine, login)),
+            None => {
+                self.default.get_or_insert(login);
+            }
+        }
+    }
+
+    /// The login for the host, falling back to the `default` entry
+    pub fn login_for(&self, host: &str) -> Option<&NetrcLogin> {
+        self.machines
+            .iter()
+            .find(|(machine, _)| machine.eq_ignore_ascii_case(host))
+            .map(|(_, login)| login)
+            .or(self.default.as_ref())
+    }
+}
+
+/// Netrc entry while parsing, `machine` is `None` for the `default` entry
+#[derive(Default)]
+struct NetrcEntry {
+    machine: Option<String>,
+    login: Option<String>,
+    password: Option<String>,
+}
+
+/// Credentials for downloads from hosts requiring authentication
+#[derive(Clone, Default, PartialEq)]
+pub struct Credentials {
+    /// Tokens of the `TOOL_TOOL_AUTH_<HOST>` variables, keyed by the `<HOST>` part
+    tokens: BTreeMap<String, String>,
+    netrc: Netrc,
+}
+
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("tokens", &self.tokens.keys().collect::<Vec<_>>())
+            .field("netrc", &self.netrc)
+            .finish()
+    }
+}
+
+impl Credentials {
+    pub fn new(tokens: BTreeMap<String, String>, netrc: Netrc) -> Self {
+        Self { tokens, netrc }
+    }
+
+    /// Reads the `TOOL_TOOL_AUTH_<HOST>` tokens and the netrc file
+    ///
+    /// The netrc file is `$NETRC`, falling back to `~/.netrc` (`%USERPROFILE%\_netrc` on
+    /// windows), an unreadable file is ignored with a warning
+    pub fn from_env(env: &[(String, String)], platform: DownloadPlatform) -> Self {
+        let get = |key: &str| {
+            env.iter()
+                .find(|(env_key, value)| env_key == key && !value.is_empty())
+                .map(|(_, value)| value.as_str())
+        };
+        let tokens = env
+            .iter()
+            .filter_map(|(key, value)| {
+                let host = key.strip_prefix(AUTH_ENV_VAR_PREFIX)?;
+                let token = value.trim();
+                (!host.is_empty() && !token.is_empty())
+                    .then(|| (host.to_ascii_uppercase(), token.to_string()))
+            })
+            .collect();
+        let netrc_paths = if let Some(netrc) = get(NETRC_ENV_VAR) {
+            vec![netrc.to_string()]
+        } else if platform == DownloadPlatform::Windows {
+            get("USERPROFILE")
+                .map(|home| vec![format!("{home}/_netrc"), format!("{home}/.netrc")])
+                .unwrap_or_default()
+        } else {
+            get("HOME")
+                .map(|home| vec![format!("{home}/.netrc")])
+                .unwrap_or_default()
+        };
+        let mut netrc = Netrc::default();
+        if let Some(path) = netrc_paths.iter().find(|path| Path::new(path).exists()) {
+            match std::fs::read_to_string(path) {
+                Ok(content) => {
+                    debug!("Using netrc file {path}");
+                    netrc = Netrc::parse(&content);
+                }
+                Err(error) => warn!("Ignoring netrc file {path}, it could not be read: {error}"),
+            }
+        }
+        Self { tokens, netrc }
+    }
+
+    /// The value of the `Authorization` header for the host, if any credentials are configured
+    ///
+    /// A `TOOL_TOOL_AUTH_<HOST>` token is sent as a bearer token (unless it already includes a
+    /// scheme, e.g. `Basic ...`) and takes precedence over the netrc file
+    pub fn authorization_for(&self, host: &str) -> Option<String> {
+        if let Some(token) = self.tokens.get(&host_env_var_suffix(host)) {
+            return Some(if token.contains(' ') {
+                token.clone()
+            } else {
+                format!("Bearer {token}")
+            });
+        }
+        let login = self.netrc.login_for(host)?;
+        let basic = Base64::encode_string(format!("{}:{}", login.login, login.password).as_bytes());
+        Some(format!("Basic {basic}"))
+    }
+}
+
+/// The `<HOST>` part of the `TOOL_TOOL_AUTH_<HOST>` variable for a host
+pub fn host_env_var_suffix(host: &str) -> String {
+    host.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_temp_dir::test_temp_dir;
+
+    fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_netrc() {
+        let netrc = Netrc::parse(
+            "# artifactory\n\
+             machine artifactory.corp.example.com login ci password s3cret\n\
+             machine github.com\n  login user\n  account ignored\n  password ghp_token\n\
+             machine nopassword.example.com login someone\n\
+             macdef init\n  cd /pub\n\n\
+             default login anonymous password guest\n",
+        );
+        assert_eq!(
+            netrc.login_for("ARTIFACTORY.corp.example.com"),
+            Some(&NetrcLogin {
+                login: "ci".to_string(),
+                password: "s3cret".to_string()
+            })
+        );
+        assert_eq!(
+            netrc
+                .login_for("github.com")
+                .map(|login| login.password.as_str()),
+            Some("ghp_token")
+        );
+        // Entries without a password fall back to the default login
+        assert_eq!(
+            netrc
+                .login_for("nopassword.example.com")
+                .map(|login| login.login.as_str()),
+            Some("anonymous")
+        );
+        assert_eq!(netrc.machines.len(), 2);
+        assert_eq!(
+            format!("{:?}", netrc.default),
+            r#"Some(NetrcLogin { login: "anonymous", password: "***" })"#
+        );
+    }
+
+    #[test]
+    fn test_host_env_var_suffix() {
+        assert_eq!(host_env_var_suffix("github.com"), "GITHUB_COM");
+        assert_eq!(
+            host_env_var_suffix("artifactory.corp-example.com"),
+            "ARTIFACTORY_CORP_EXAMPLE_COM"
+        );
+    }
+
+    #[test]
+    fn authorization() {
+        let netrc = Netrc::parse(
+            "machine github.com login user password netrc\nmachine example.com login user password pass",
+        );
+        let credentials = Credentials::new(
+            BTreeMap::from([
+                ("GITHUB_COM".to_string(), "ghp_token".to_string()),
+                ("GITLAB_COM".to_string(), "Basic dXNlcjpwYXNz".to_string()),
+            ]),
+            netrc,
+        );
+        assert_eq!(
+            credentials.authorization_for("github.com").as_deref(),
+            Some("Bearer ghp_token")
+        );
+        assert_eq!(
+            credentials.authorization_for("gitlab.com").as_deref(),
+            Some("Basic dXNlcjpwYXNz")
+        );
+        assert_eq!(
+            credentials.authorization_for("example.com").as_deref(),
+            Some("Basic dXNlcjpwYXNz")
+        );
+        assert_eq!(credentials.authorization_for("other.com"), None);
+        assert_eq!(
+            format!("{credentials:?}"),
+            r#"Credentials { tokens: ["GITHUB_COM", "GITLAB_COM"], netrc: Netrc { machines: [("github.com", NetrcLogin { login: "user", password: "***" }), ("example.com", NetrcLogin { login: "user", password: "***" })], default: None } }"#
+        );
+    }
+
+    #[test]
+    fn credentials_from_env() {
+        let temp_dir = test_temp_dir!();
+        let netrc_path = temp_dir.used_by(|path| {
+            let netrc_path = path.join(".netrc");
+            std::fs::write(&netrc_path, "machine example.com login user password pass").unwrap();
+            netrc_path
+        });
+        let home = netrc_path.parent().unwrap().to_str().unwrap().to_string();
+        let credentials = Credentials::from_env(
+            &env(&[
+                ("HOME", &home),
+                ("TOOL_TOOL_AUTH_GITHUB_COM", " ghp_token "),
+                ("TOOL_TOOL_AUTH_EMPTY_COM", ""),
+            ]),
+            DownloadPlatform::Linux,
+        );
+        assert_eq!(
+            credentials,
+            Credentials::new(
+                BTreeMap::from([("GITHUB_COM".to_string(), "ghp_token".to_string())]),
+                Netrc::parse("machine example.com login user password pass"),
+            )
+        );
+        // $NETRC takes precedence over the home directory
+        let credentials = Credentials::from_env(
+            &env(&[("HOME", &home), (NETRC_ENV_VAR, "/does/not/exist")]),
+            DownloadPlatform::Linux,
+        );
+        assert_eq!(credentials, Credentials::default());
+    }
+}
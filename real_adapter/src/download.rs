@@ -1,51 +1,469 @@
-use tool_tool_base::result::{Context, ToolToolResult};
-use ureq::tls::{RootCerts, TlsConfig};
+use crate::auth::Credentials;
+use crate::network::{Agents, NetworkSettings};
+use crate::real_adapter::host_platform;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+use tool_tool_base::logging::{debug, warn};
+use tool_tool_base::result::{Context, ToolToolError, ToolToolResult, err};
+use tool_tool_logic::adapter::DownloadProgress;
+use tool_tool_logic::origin::Origin;
+use tool_tool_logic::redact::redact_url;
+use ureq::http::StatusCode;
+
+/// Environment variable setting the number of retries after transient download errors
+pub const RETRIES_ENV_VAR: &str = "TOOL_TOOL_DOWNLOAD_RETRIES";
+
+/// Environment variable setting the initial backoff between retries in milliseconds
+pub const BACKOFF_ENV_VAR: &str = "TOOL_TOOL_DOWNLOAD_BACKOFF_MS";
+
+/// Maximum number of redirects followed for a single request
+const MAX_REDIRECTS: u32 = 10;
+
+/// How often and how long to wait before retrying a download after a transient error
+///
+/// The backoff doubles with every attempt up to `max_backoff`, a `Retry-After` header sent with
+/// a 429 or 503 response takes precedence (but is also capped at `max_backoff`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Reads the policy from the environment, invalid values are ignored with a warning
+    pub fn from_env(env: &[(String, String)]) -> Self {
+        let mut policy = Self::default();
+        let get = |key: &str| -> Option<u64> {
+            let (_, value) = env.iter().find(|(env_key, _)| env_key == key)?;
+            match value.trim().parse() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    warn!("Ignoring invalid value '{value}' of {key}, expected a number");
+                    None
+                }
+            }
+        };
+        if let Some(retries) = get(RETRIES_ENV_VAR) {
+            policy.max_retries = u32::try_from(retries).unwrap_or(u32::MAX);
+        }
+        if let Some(backoff) = get(BACKOFF_ENV_VAR) {
+            policy.initial_backoff = Duration::from_millis(backoff);
+            policy.max_backoff = policy.max_backoff.max(policy.initial_backoff);
+        }
+        policy
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+/// Failure of a single download attempt
+enum AttemptError {
+    /// Worth retrying, optionally after the delay requested by the server
+    Transient {
+        error: ToolToolError,
+        retry_after: Option<Duration>,
+    },
+    Fatal(ToolToolError),
+}
 
 pub struct Downloader {
-    agent: ureq::Agent,
+    /// Agents are built up front, configuration errors are reported when downloading
+    agents: Result<Agents, String>,
+    credentials: Credentials,
+    retry_policy: RetryPolicy,
 }
 
 impl Downloader {
     pub fn new() -> Self {
-        let agent = ureq::config::Config::builder()
-            .tls_config(
-                TlsConfig::builder()
-                    .root_certs(RootCerts::PlatformVerifier)
-                    .build(),
-            )
-            .build()
-            .new_agent();
+        let env: Vec<(String, String)> = std::env::vars().collect();
+        let platform = host_platform();
+        let agents = NetworkSettings::from_env(&env, platform)
+            .and_then(|settings| settings.agents())
+            .map_err(|error| format!("{error:#}"));
+        Self {
+            agents,
+            credentials: Credentials::from_env(&env, platform),
+            retry_policy: RetryPolicy::from_env(&env),
+        }
+    }
 
-        Self { agent }
+    pub fn with_settings(
+        retry_policy: RetryPolicy,
+        network_settings: &NetworkSettings,
+        credentials: Credentials,
+    ) -> ToolToolResult<Self> {
+        Ok(Self {
+            agents: Ok(network_settings.agents()?),
+            credentials,
+            retry_policy,
+        })
     }
 
-    pub fn download(&self, url: &str, destination_path: &std::path::Path) -> ToolToolResult<()> {
+    /// Downloads the url to the destination path, retrying transient errors
+    ///
+    /// Connection errors, 5xx responses and 429 responses are retried. If a connection drops
+    /// after part of the body was written, the next attempt requests only the remaining bytes
+    /// with a `Range` header. Resuming only works within a single call, the destination is
+    /// created on the first successful response and removed again if the download fails
+    ///
+    /// The headers (and the `Authorization` header from the credentials of the host) are only sent
+    /// to the host of the url, they are dropped when a redirect leads to a different host
+    pub fn download(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        destination_path: &Path,
+        progress: &mut dyn FnMut(&DownloadProgress),
+    ) -> ToolToolResult<()> {
+        let redacted_url = redact_url(url);
         (|| -> ToolToolResult<()> {
-            let response = self.agent.get(url).call()?;
-            let mut reader = response.into_body().into_reader();
-            let mut output_file = std::fs::File::create(destination_path)?;
-            std::io::copy(&mut reader, &mut output_file)?;
-            Ok(())
+            let agents = self
+                .agents
+                .as_ref()
+                .map_err(|error| err!("Invalid network configuration: {error}"))?;
+            let headers = self.request_headers(url, headers);
+            let mut output = Output {
+                path: destination_path,
+                file: None,
+            };
+            let start = Instant::now();
+            let mut attempt = 0;
+            let result = loop {
+                let result = self.attempt_download(
+                    agents,
+                    url,
+                    &headers,
+                    &mut output,
+                    start,
+                    progress,
+                );
+                let (error, retry_after) = match result {
+                    Ok(()) => break Ok(()),
+                    Err(AttemptError::Fatal(error)) => break Err(error),
+                    Err(AttemptError::Transient { error, retry_after }) => (error, retry_after),
+                };
+                if attempt >= self.retry_policy.max_retries {
+                    break Err(error);
+                }
+                let delay = retry_after
+                    .map(|delay| delay.min(self.retry_policy.max_backoff))
+                    .unwrap_or_else(|| self.retry_policy.backoff(attempt));
+                attempt += 1;
+                warn!(
+                    "Download of '{redacted_url}' failed: {error:#}, retrying in {:.1}s (attempt {attempt} of {})",
+                    delay.as_secs_f64(),
+                    self.retry_policy.max_retries
+                );
+                std::thread::sleep(delay);
+            };
+            if result.is_err() && output.file.take().is_some() {
+                // Do not leave a partial file behind
+                if let Err(error) = std::fs::remove_file(destination_path) {
+                    warn!("Failed to remove partial download {destination_path:?}: {error}");
+                }
+            }
+            result
         })()
-        .with_context(|| format!("Failed to download '{url}' to '{destination_path:?}'"))
+        .with_context(|| format!("Failed to download '{redacted_url}' to '{destination_path:?}'"))
+    }
+
+    /// The configured headers, plus an `Authorization` header from the credentials of the host
+    /// unless the configured headers already include one
+    fn request_headers(&self, url: &str, headers: &[(String, String)]) -> Vec<(String, String)> {
+        let mut request_headers = headers.to_vec();
+        if !headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+            && let Some(origin) = Origin::of(url)
+            && let Some(authorization) = self.credentials.authorization_for(&origin.host)
+        {
+            debug!("Using credentials for {}", origin.host);
+            request_headers.push(("Authorization".to_string(), authorization));
+        }
+        request_headers
+    }
+
+    fn attempt_download(
+        &self,
+        agents: &Agents,
+        url: &str,
+        headers: &[(String, String)],
+        output: &mut Output,
+        start: Instant,
+        progress: &mut dyn FnMut(&DownloadProgress),
+    ) -> Result<(), AttemptError> {
+        let transient = |error: ToolToolError| AttemptError::Transient {
+            error,
+            retry_after: None,
+        };
+        let offset = match &output.file {
+            Some(file) => file
+                .metadata()
+                .map_err(|error| AttemptError::Fatal(error.into()))?
+                .len(),
+            None => 0,
+        };
+        if offset > 0 {
+            debug!(
+                "Resuming download of '{}' at byte {offset}",
+                redact_url(url)
+            );
+        }
+        let origin = Origin::of(url);
+        let mut request_url = url.to_string();
+        let mut redirects = 0;
+        // Once a redirect left the host, headers are not sent again even if it redirects back
+        let mut send_headers = true;
+        let response = loop {
+            let mut request = agents.agent_for(&request_url).get(&request_url);
+            send_headers &= Origin::of(&request_url) == origin;
+            if send_headers {
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+            }
+            if offset > 0 {
+                request = request.header("Range", format!("bytes={offset}-"));
+            }
+            let response = match request.call() {
+                Ok(response) => response,
+                Err(
+                    error @ (ureq::Error::Io(_)
+                    | ureq::Error::ConnectionFailed
+                    | ureq::Error::Timeout(_)
+                    | ureq::Error::HostNotFound),
+                ) => return Err(transient(error.into())),
+                Err(error) => return Err(AttemptError::Fatal(error.into())),
+            };
+            let location = response
+                .headers()
+                .get("location")
+                .and_then(|value| value.to_str().ok());
+            match location {
+                Some(location) if response.status().is_redirection() => {
+                    if redirects >= MAX_REDIRECTS {
+                        return Err(AttemptError::Fatal(err!(
+                            "Too many redirects (more than {MAX_REDIRECTS})"
+                        )));
+                    }
+                    redirects += 1;
+                    request_url = resolve_location(&request_url, location);
+                    debug!("Following redirect to '{}'", redact_url(&request_url));
+                }
+                _ => break response,
+            }
+        };
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            let retry_after = response
+                .headers()
+                .get("retry-after")
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after);
+            return Err(AttemptError::Transient {
+                error: err!("HTTP status {status}"),
+                retry_after,
+            });
+        }
+        if status == StatusCode::RANGE_NOT_SATISFIABLE
+            && let Some(output_file) = &mut output.file
+        {
+            // The partial file can not be resumed, start over
+            truncate(output_file).map_err(AttemptError::Fatal)?;
+            return Err(transient(err!("HTTP status {status}")));
+        }
+        if !status.is_success() {
+            return Err(AttemptError::Fatal(err!("HTTP status {status}")));
+        }
+        let output_file = match &mut output.file {
+            Some(output_file) => output_file,
+            None => output.file.insert(
+                File::create(output.path).map_err(|error| AttemptError::Fatal(error.into()))?,
+            ),
+        };
+        let content_length = response
+            .headers()
+            .get("content-length")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+        let mut downloaded = offset;
+        if status == StatusCode::PARTIAL_CONTENT {
+            let resumed = response
+                .headers()
+                .get("content-range")
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with(&format!("bytes {offset}-")));
+            if !resumed {
+                // Unexpected range, start over without a range request
+                truncate(output_file).map_err(AttemptError::Fatal)?;
+                return Err(transient(err!("Unexpected partial content response")));
+            }
+        } else {
+            // The server ignored the range request (or this is the first attempt)
+            truncate(output_file).map_err(AttemptError::Fatal)?;
+            downloaded = 0;
+        }
+        let total = content_length.map(|length| downloaded + length);
+        let mut reader = response.into_body().into_reader();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = reader
+                .read(&mut buffer)
+                .map_err(|error| transient(error.into()))?;
+            if read == 0 {
+                return Ok(());
+            }
+            output_file
+                .write_all(&buffer[..read])
+                .map_err(|error| AttemptError::Fatal(error.into()))?;
+            downloaded += read as u64;
+            progress(&DownloadProgress {
+                downloaded,
+                total,
+                elapsed: start.elapsed(),
+            });
+        }
     }
 }
 
+/// The destination of a download, the file is only created once the server sent a successful
+/// response
+struct Output<'a> {
+    path: &'a Path,
+    file: Option<File>,
+}
+
+/// Resolves the `Location` header of a redirect against the url of the request
+fn resolve_location(url: &str, location: &str) -> String {
+    let is_absolute = location.split_once("://").is_some_and(|(scheme, _)| {
+        scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    });
+    if is_absolute {
+        return location.to_string();
+    }
+    let (scheme, rest) = url.split_once("://").unwrap_or(("http", url));
+    if let Some(location) = location.strip_prefix("//") {
+        return format!("{scheme}://{location}");
+    }
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let base = format!("{scheme}://{}", &rest[..authority_end]);
+    if location.starts_with('/') {
+        return format!("{base}{location}");
+    }
+    let path = rest[authority_end..]
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default();
+    let directory = path.rsplit_once('/').map_or("", |(directory, _)| directory);
+    format!("{base}{directory}/{location}")
+}
+
+fn truncate(file: &mut File) -> ToolToolResult<()> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(())
+}
+
 impl Default for Downloader {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Parses a `Retry-After` value, either delay seconds or an HTTP date
+///
+/// Dates in the past mean the request can be retried immediately
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::auth::{Netrc, host_env_var_suffix};
     use assertables::assert_starts_with;
     use httpmock::Method::GET;
     use httpmock::MockServer;
-    use std::path::PathBuf;
+    use std::collections::BTreeMap;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+    use std::time::Instant;
     use test_temp_dir::{TestTempDir, test_temp_dir};
 
+    fn downloader(retry_policy: RetryPolicy) -> Downloader {
+        Downloader::with_settings(
+            retry_policy,
+            &NetworkSettings::default(),
+            Credentials::default(),
+        )
+        .unwrap()
+    }
+
+    fn fast_retries(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(10),
+        }
+    }
+
+    /// Serves the given raw responses to consecutive connections, closing each connection after
+    /// the response was written, and returns the request headers received
+    ///
+    /// Used to simulate connection drops, which httpmock can not produce
+    fn serve_raw(responses: Vec<&'static str>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/download", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = vec![];
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    request.push_str(&line.to_ascii_lowercase());
+                }
+                requests.push(request);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
     struct TestContext {
         temp_dir: TestTempDir,
         server: MockServer,
@@ -67,7 +485,7 @@ mod tests {
             temp_dir,
             server,
             content: content.to_string(),
-            downloader: Downloader::new(),
+            downloader: downloader(RetryPolicy::default()),
         }
     }
 
@@ -75,11 +493,20 @@ mod tests {
     fn test_download() {
         let ctx = setup();
         let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        let mut progress = None;
         ctx.downloader
-            .download(&ctx.server.url("/download_url"), &local_path.as_path())
+            .download(
+                &ctx.server.url("/download_url"),
+                &[],
+                local_path.as_path(),
+                &mut |update| progress = Some(update.clone()),
+            )
             .unwrap();
         let actual_content = std::fs::read_to_string(local_path.as_path()).unwrap();
         assert_eq!(actual_content, ctx.content);
+        let progress = progress.expect("Expected progress updates");
+        assert_eq!(progress.downloaded, 16);
+        assert_eq!(progress.total, Some(16));
     }
 
     #[test]
@@ -95,20 +522,462 @@ mod tests {
         let url = ctx.server.url("/download_url_404");
         let error = ctx
             .downloader
-            .download(&url, &local_path.as_path())
+            .download(&url, &[], local_path.as_path(), &mut |_| {})
             .expect_err("Expected error");
         assert_starts_with!(error.to_string(), "Failed to download 'http");
     }
 
+    #[test]
+    fn test_404_leaves_no_file() {
+        let ctx = setup();
+        ctx.server.mock(|when, then| {
+            when.method(GET).path("/download_url_404");
+            then.status(404);
+        });
+
+        let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        ctx.downloader
+            .download(
+                &ctx.server.url("/download_url_404"),
+                &[],
+                local_path.as_path(),
+                &mut |_| {},
+            )
+            .expect_err("Expected error");
+        assert!(!local_path.exists());
+    }
+
     #[test]
     fn test_invalid_path() {
         let ctx = setup();
 
-        let url = ctx.server.url("/download");
+        let local_path = ctx
+            .temp_dir
+            .used_by(|path| path.join("missing_directory").join("file_download"));
+        let url = ctx.server.url("/download_url");
         let error = ctx
             .downloader
-            .download(&url, &PathBuf::from("invalid_path"))
+            .download(&url, &[], local_path.as_path(), &mut |_| {})
             .expect_err("Expected error");
         assert_starts_with!(error.to_string(), "Failed to download 'http");
     }
+
+    #[test]
+    fn test_404_is_not_retried() {
+        let ctx = setup();
+        let mock = ctx.server.mock(|when, then| {
+            when.method(GET).path("/download_url_404");
+            then.status(404);
+        });
+
+        let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        downloader(fast_retries(3))
+            .download(
+                &ctx.server.url("/download_url_404"),
+                &[],
+                local_path.as_path(),
+                &mut |_| {},
+            )
+            .expect_err("Expected error");
+        mock.assert_hits(1);
+    }
+
+    #[test]
+    fn test_server_error_retries_exhausted() {
+        let ctx = setup();
+        let mock = ctx.server.mock(|when, then| {
+            when.method(GET).path("/download_url_503");
+            then.status(503);
+        });
+
+        let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        let error = downloader(fast_retries(2))
+            .download(
+                &ctx.server.url("/download_url_503"),
+                &[],
+                local_path.as_path(),
+                &mut |_| {},
+            )
+            .expect_err("Expected error");
+        assert_eq!(
+            format!("{:#}", error.root_cause()),
+            "HTTP status 503 Service Unavailable"
+        );
+        mock.assert_hits(3);
+    }
+
+    #[test]
+    fn test_rate_limit_honours_retry_after() {
+        let ctx = setup();
+        let mock = ctx.server.mock(|when, then| {
+            when.method(GET).path("/download_url_429");
+            then.status(429).header("Retry-After", "0");
+        });
+
+        let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        let start = Instant::now();
+        // The backoff would take minutes, the server asks to retry immediately
+        downloader(RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(60),
+        })
+        .download(
+            &ctx.server.url("/download_url_429"),
+            &[],
+            local_path.as_path(),
+            &mut |_| {},
+        )
+        .expect_err("Expected error");
+        mock.assert_hits(3);
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_rate_limit_honours_retry_after_date() {
+        let ctx = setup();
+        let mock = ctx.server.mock(|when, then| {
+            when.method(GET).path("/download_url_429");
+            then.status(429)
+                .header("Retry-After", "Sun, 06 Nov 1994 08:49:37 GMT");
+        });
+
+        let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        let start = Instant::now();
+        // The date has passed, so the download is retried immediately instead of after the backoff
+        downloader(RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(60),
+        })
+        .download(
+            &ctx.server.url("/download_url_429"),
+            &[],
+            local_path.as_path(),
+            &mut |_| {},
+        )
+        .expect_err("Expected error");
+        mock.assert_hits(3);
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(Duration::ZERO)
+        );
+        let in_a_minute = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let delay = parse_retry_after(&in_a_minute).unwrap();
+        assert!(
+            delay > Duration::from_secs(55) && delay <= Duration::from_secs(60),
+            "{delay:?}"
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_retry_after_server_error() {
+        let temp_dir = test_temp_dir!();
+        let (url, handle) = serve_raw(vec![
+            "HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\ndownload content",
+        ]);
+        let local_path = temp_dir.used_by(|path| path.join("file_download"));
+        downloader(fast_retries(3))
+            .download(&url, &[], local_path.as_path(), &mut |_| {})
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(local_path.as_path()).unwrap(),
+            "download content"
+        );
+        assert_eq!(handle.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_resume_dropped_download() {
+        let temp_dir = test_temp_dir!();
+        let (url, handle) = serve_raw(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\ndownload",
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 8-15/16\r\nContent-Length: 8\r\nConnection: close\r\n\r\n content",
+        ]);
+        let local_path = temp_dir.used_by(|path| path.join("file_download"));
+        let mut progress = vec![];
+        downloader(fast_retries(3))
+            .download(&url, &[], local_path.as_path(), &mut |update| {
+                progress.push((update.downloaded, update.total))
+            })
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(local_path.as_path()).unwrap(),
+            "download content"
+        );
+        assert_eq!(progress, vec![(8, Some(16)), (16, Some(16))]);
+        let requests = handle.join().unwrap();
+        assert!(!requests[0].contains("range:"));
+        assert!(requests[1].contains("range: bytes=8-\r\n"));
+    }
+
+    #[test]
+    fn test_restart_when_range_is_ignored() {
+        let temp_dir = test_temp_dir!();
+        let (url, handle) = serve_raw(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\ndownload",
+            "HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\ndownload content",
+        ]);
+        let local_path = temp_dir.used_by(|path| path.join("file_download"));
+        downloader(fast_retries(3))
+            .download(&url, &[], local_path.as_path(), &mut |_| {})
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(local_path.as_path()).unwrap(),
+            "download content"
+        );
+        assert_eq!(handle.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_stalled_body_times_out_and_resumes() {
+        let temp_dir = test_temp_dir!();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/download", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut streams = vec![];
+            for response in [
+                // Stalls after half of the body, without closing the connection
+                "HTTP/1.1 200 OK\r\nContent-Length: 16\r\n\r\ndownload",
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 8-15/16\r\nContent-Length: 8\r\nConnection: close\r\n\r\n content",
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+                streams.push(stream);
+            }
+            streams
+        });
+        let network_settings = NetworkSettings {
+            read_timeout: Duration::from_millis(200),
+            ..NetworkSettings::default()
+        };
+        let downloader =
+            Downloader::with_settings(fast_retries(1), &network_settings, Credentials::default())
+                .unwrap();
+        let local_path = temp_dir.used_by(|path| path.join("file_download"));
+        let start = Instant::now();
+        downloader
+            .download(&url, &[], local_path.as_path(), &mut |_| {})
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            std::fs::read_to_string(local_path.as_path()).unwrap(),
+            "download content"
+        );
+        drop(handle.join().unwrap());
+    }
+
+    fn credentials(host: &str, token: &str) -> Credentials {
+        Credentials::new(
+            BTreeMap::from([(host_env_var_suffix(host), token.to_string())]),
+            Netrc::default(),
+        )
+    }
+
+    #[test]
+    fn test_download_with_headers_and_credentials() {
+        let ctx = setup();
+        let mock = ctx.server.mock(|when, then| {
+            when.method(GET)
+                .path("/private")
+                .header("x-api-key", "header-secret")
+                .header("authorization", "Bearer token-secret");
+            then.status(200).body("private content");
+        });
+        let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        let headers = vec![("X-Api-Key".to_string(), "header-secret".to_string())];
+        Downloader::with_settings(
+            RetryPolicy::default(),
+            &NetworkSettings::default(),
+            credentials("127.0.0.1", "token-secret"),
+        )
+        .unwrap()
+        .download(
+            &ctx.server.url("/private"),
+            &headers,
+            local_path.as_path(),
+            &mut |_| {},
+        )
+        .unwrap();
+        mock.assert();
+        assert_eq!(
+            std::fs::read_to_string(local_path.as_path()).unwrap(),
+            "private content"
+        );
+    }
+
+    #[test]
+    fn test_configured_authorization_takes_precedence() {
+        let ctx = setup();
+        let mock = ctx.server.mock(|when, then| {
+            when.method(GET)
+                .path("/private")
+                .header("authorization", "token configured");
+            then.status(200).body("private content");
+        });
+        let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        let headers = vec![("authorization".to_string(), "token configured".to_string())];
+        Downloader::with_settings(
+            RetryPolicy::default(),
+            &NetworkSettings::default(),
+            credentials("127.0.0.1", "token-secret"),
+        )
+        .unwrap()
+        .download(
+            &ctx.server.url("/private"),
+            &headers,
+            local_path.as_path(),
+            &mut |_| {},
+        )
+        .unwrap();
+        mock.assert();
+    }
+
+    #[test]
+    fn test_redirect_to_same_host_keeps_headers() {
+        let ctx = setup();
+        ctx.server.mock(|when, then| {
+            when.method(GET).path("/releases/latest");
+            then.status(302).header("location", "v1/tool.tar.gz");
+        });
+        let mock = ctx.server.mock(|when, then| {
+            when.method(GET)
+                .path("/releases/v1/tool.tar.gz")
+                .header("x-api-key", "header-secret");
+            then.status(200).body("private content");
+        });
+        let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        let headers = vec![("X-Api-Key".to_string(), "header-secret".to_string())];
+        ctx.downloader
+            .download(
+                &ctx.server.url("/releases/latest"),
+                &headers,
+                local_path.as_path(),
+                &mut |_| {},
+            )
+            .unwrap();
+        mock.assert();
+    }
+
+    #[test]
+    fn test_redirect_to_other_host_drops_credentials() {
+        let ctx = setup();
+        let (storage_url, handle) = serve_raw(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\ndownload content",
+        ]);
+        ctx.server.mock(|when, then| {
+            when.method(GET).path("/releases/asset");
+            then.status(302)
+                .header("location", format!("{storage_url}?signed=1"));
+        });
+        let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        let headers = vec![("X-Api-Key".to_string(), "header-secret".to_string())];
+        Downloader::with_settings(
+            RetryPolicy::default(),
+            &NetworkSettings::default(),
+            credentials("127.0.0.1", "token-secret"),
+        )
+        .unwrap()
+        .download(
+            &ctx.server.url("/releases/asset"),
+            &headers,
+            local_path.as_path(),
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(local_path.as_path()).unwrap(),
+            "download content"
+        );
+        let requests = handle.join().unwrap();
+        assert!(requests[0].starts_with("get /download?signed=1 "));
+        assert!(!requests[0].contains("x-api-key"));
+        assert!(!requests[0].contains("authorization"));
+    }
+
+    #[test]
+    fn test_credentials_are_redacted_in_errors() {
+        let ctx = setup();
+        ctx.server.mock(|when, then| {
+            when.method(GET).path("/private");
+            then.status(401);
+        });
+        let local_path = ctx.temp_dir.used_by(|path| path.join("file_download"));
+        let url = ctx
+            .server
+            .url("/private?private_token=query-secret")
+            .replace("http://", "http://user:url-secret@");
+        let error = ctx
+            .downloader
+            .download(&url, &[], local_path.as_path(), &mut |_| {})
+            .expect_err("Expected error");
+        let message = format!("{error:#}");
+        assert!(!message.contains("secret"), "{message}");
+        assert_starts_with!(message, "Failed to download 'http://***@127.0.0.1:");
+        assert!(message.contains("/private?private_token=***'"), "{message}");
+        assert!(
+            message.ends_with("HTTP status 401 Unauthorized"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn test_resolve_location() {
+        let url = "https://example.com/releases/latest?arch=x64";
+        assert_eq!(
+            resolve_location(url, "https://cdn.example.com/tool.tar.gz"),
+            "https://cdn.example.com/tool.tar.gz"
+        );
+        assert_eq!(
+            resolve_location(url, "//cdn.example.com/tool.tar.gz"),
+            "https://cdn.example.com/tool.tar.gz"
+        );
+        assert_eq!(
+            resolve_location(url, "/download/tool.tar.gz"),
+            "https://example.com/download/tool.tar.gz"
+        );
+        assert_eq!(
+            resolve_location(url, "v1/tool.tar.gz?a=b://c"),
+            "https://example.com/releases/v1/tool.tar.gz?a=b://c"
+        );
+    }
+
+    #[test]
+    fn test_retry_policy_from_env() {
+        let env = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        };
+        assert_eq!(RetryPolicy::from_env(&[]), RetryPolicy::default());
+        assert_eq!(
+            RetryPolicy::from_env(&env(&[(RETRIES_ENV_VAR, "5"), (BACKOFF_ENV_VAR, "250")])),
+            RetryPolicy {
+                max_retries: 5,
+                initial_backoff: Duration::from_millis(250),
+                max_backoff: Duration::from_secs(30),
+            }
+        );
+        assert_eq!(
+            RetryPolicy::from_env(&env(&[(RETRIES_ENV_VAR, "many")])),
+            RetryPolicy::default()
+        );
+        assert_eq!(RetryPolicy::default().backoff(0), Duration::from_secs(1));
+        assert_eq!(RetryPolicy::default().backoff(3), Duration::from_secs(8));
+        assert_eq!(RetryPolicy::default().backoff(10), Duration::from_secs(30));
+    }
 }
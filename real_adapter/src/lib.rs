@@ -1,3 +1,5 @@
 pub mod real_adapter;
 pub use real_adapter::RealAdapter;
+pub mod auth;
 pub mod download;
+pub mod network;
@@ -0,0 +1,600 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tool_tool_base::logging::{debug, warn};
+use tool_tool_base::result::{Context, ToolToolResult, bail, err};
+use tool_tool_logic::configuration::platform::DownloadPlatform;
+use tool_tool_logic::origin::{Origin, split_host_port};
+use tool_tool_logic::redact::redact_url;
+use tool_tool_logic::user_config::UserConfig;
+use ureq::tls::{Certificate, PemItem, RootCerts, TlsConfig};
+use ureq::unversioned::resolver::DefaultResolver;
+use ureq::unversioned::transport::{
+    Buffers, ConnectionDetails, Connector, DefaultConnector, NextTimeout, Transport,
+};
+use ureq::{Agent, Proxy};
+
+/// Environment variable pointing to a PEM file with additional trusted CA certificates
+pub const CA_BUNDLE_ENV_VAR: &str = "TOOL_TOOL_CA_BUNDLE";
+
+/// Environment variable setting the connect timeout in seconds
+pub const CONNECT_TIMEOUT_ENV_VAR: &str = "TOOL_TOOL_CONNECT_TIMEOUT";
+
+/// Environment variable setting how long to wait for data from the server in seconds
+pub const READ_TIMEOUT_ENV_VAR: &str = "TOOL_TOOL_READ_TIMEOUT";
+
+/// Proxy, certificate and timeout settings for downloads
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkSettings {
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    /// Hosts (or domains) that are accessed directly, even if a proxy is configured
+    pub no_proxy: Vec<String>,
+    /// PEM file with CA certificates trusted in addition to the platform's certificates
+    pub ca_bundle: Option<PathBuf>,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            http_proxy: None,
+            https_proxy: None,
+            no_proxy: vec![],
+            ca_bundle: None,
+            connect_timeout: Duration::from_secs(30),
+            read_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl NetworkSettings {
+    /// Reads the settings from the environment and the user configuration file, the
+    /// environment takes precedence
+    ///
+    /// Proxies are taken from `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` (or their
+    /// lowercase variants)
+    pub fn from_env(env: &[(String, String)], platform: DownloadPlatform) -> ToolToolResult<Self> {
+        let get = |key: &str| {
+            [key.to_ascii_lowercase(), key.to_string()]
+                .iter()
+                .find_map(|key| {
+                    env.iter()
+                        .find(|(env_key, value)| env_key == key && !value.trim().is_empty())
+                })
+                .map(|(_, value)| value.trim().to_string())
+        };
+        let mut settings = Self::default();
+        if let Some(config_path) = UserConfig::path_from_env(env, platform)
+            && Path::new(&config_path).exists()
+        {
+            let content = std::fs::read_to_string(&config_path)
+                .with_context(|| format!("Failed to read user configuration '{config_path}'"))?;
+            let user_config = UserConfig::parse(&config_path, &content)?;
+            settings.ca_bundle = user_config.ca_bundle.map(|ca_bundle| {
+                // Relative paths are relative to the configuration file
+                Path::new(&config_path)
+                    .parent()
+                    .unwrap_or(Path::new(""))
+                    .join(ca_bundle)
+            });
+            settings.connect_timeout = user_config
+                .connect_timeout
+                .unwrap_or(settings.connect_timeout);
+            settings.read_timeout = user_config.read_timeout.unwrap_or(settings.read_timeout);
+        }
+        let all_proxy = get("ALL_PROXY");
+        settings.http_proxy = get("HTTP_PROXY").or_else(|| all_proxy.clone());
+        settings.https_proxy = get("HTTPS_PROXY").or(all_proxy);
+        settings.no_proxy = get("NO_PROXY")
+            .iter()
+            .flat_map(|no_proxy| no_proxy.split(','))
+            .map(|entry| entry.trim().to_ascii_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        if let Some(ca_bundle) = get(CA_BUNDLE_ENV_VAR) {
+            settings.ca_bundle = Some(PathBuf::from(ca_bundle));
+        }
+        for (key, timeout) in [
+            (CONNECT_TIMEOUT_ENV_VAR, &mut settings.connect_timeout),
+            (READ_TIMEOUT_ENV_VAR, &mut settings.read_timeout),
+        ] {
+            if let Some(value) = get(key) {
+                let seconds = value.parse().map_err(|_| {
+                    err!("Invalid value '{value}' of {key}, expected a number of seconds")
+                })?;
+                *timeout = Duration::from_secs(seconds);
+            }
+        }
+        Ok(settings)
+    }
+
+    /// The proxy to use for the url, `None` if it should be accessed directly
+    pub fn proxy_for(&self, url: &str) -> Option<&str> {
+        let origin = Origin::of(url)?;
+        let proxy = match origin.scheme.as_str() {
+            "https" => self.https_proxy.as_deref()?,
+            "http" => self.http_proxy.as_deref()?,
+            _ => return None,
+        };
+        let host = &origin.host;
+        let port = origin.effective_port();
+        let bypass = self.no_proxy.iter().any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+            let (entry_host, entry_port) = split_host_port(entry);
+            if entry_port.is_some() && entry_port != port {
+                return false;
+            }
+            let entry_host = entry_host.trim_start_matches("*.").trim_start_matches('.');
+            host == entry_host || host.ends_with(&format!(".{entry_host}"))
+        });
+        (!bypass).then_some(proxy)
+    }
+
+    /// Builds the agents used for downloads: one for direct connections and one per proxy
+    pub(crate) fn agents(&self) -> ToolToolResult<Agents> {
+        let root_certs = match &self.ca_bundle {
+            Some(ca_bundle) => load_root_certs(ca_bundle)
+                .with_context(|| format!("Failed to load CA bundle {ca_bundle:?}"))?,
+            None => RootCerts::PlatformVerifier,
+        };
+        let build_agent = |proxy: Option<&str>| -> ToolToolResult<Agent> {
+            let proxy = proxy
+                .map(|proxy| {
+                    Proxy::new(proxy)
+                        .map_err(|error| err!("Invalid proxy '{}': {error}", redact_url(proxy)))
+                })
+                .transpose()?;
+            let config = ureq::config::Config::builder()
+                .tls_config(TlsConfig::builder().root_certs(root_certs.clone()).build())
+                .proxy(proxy)
+                .timeout_connect(Some(self.connect_timeout))
+                .timeout_recv_response(Some(self.read_timeout))
+                // Status codes are evaluated by the downloader, to decide whether to retry
+                .http_status_as_error(false)
+                // Redirects are followed by the downloader, which drops credentials when the
+                // redirect leads to another host
+                .max_redirects(0)
+                .max_redirects_will_error(false)
+                .build();
+            let connector = DefaultConnector::new().chain(ReadTimeoutConnector {
+                read_timeout: self.read_timeout,
+            });
+            Ok(Agent::with_parts(
+                config,
+                connector,
+                DefaultResolver::default(),
+            ))
+        };
+        Ok(Agents {
+            direct: build_agent(None)?,
+            http_proxy: self
+                .http_proxy
+                .as_deref()
+                .map(|proxy| build_agent(Some(proxy)))
+                .transpose()?,
+            https_proxy: self
+                .https_proxy
+                .as_deref()
+                .map(|proxy| build_agent(Some(proxy)))
+                .transpose()?,
+            settings: self.clone(),
+        })
+    }
+}
+
+/// Agents for direct and proxied connections, since ureq configures proxies per agent
+pub(crate) struct Agents {
+    settings: NetworkSettings,
+    direct: Agent,
+    http_proxy: Option<Agent>,
+    https_proxy: Option<Agent>,
+}
+
+impl Agents {
+    pub(crate) fn agent_for(&self, url: &str) -> &Agent {
+        let Some(proxy) = self.settings.proxy_for(url) else {
+            return &self.direct;
+        };
+        debug!(
+            "Using proxy {} for '{}'",
+            redact_url(proxy),
+            redact_url(url)
+        );
+        let agent = if url.to_ascii_lowercase().starts_with("https:") {
+            &self.https_proxy
+        } else {
+            &self.http_proxy
+        };
+        agent.as_ref().unwrap_or(&self.direct)
+    }
+}
+
+/// Limits every wait for data from the server to the read timeout
+///
+/// ureq only offers a timeout for the whole response body, which would abort large downloads
+/// that are still making progress, while a server stalling in the middle of the body should
+/// fail the attempt (so it can be retried)
+#[derive(Debug)]
+struct ReadTimeoutConnector {
+    read_timeout: Duration,
+}
+
+impl Connector<Box<dyn Transport>> for ReadTimeoutConnector {
+    type Out = ReadTimeoutTransport;
+
+    fn connect(
+        &self,
+        _details: &ConnectionDetails,
+        chained: Option<Box<dyn Transport>>,
+    ) -> Result<Option<Self::Out>, ureq::Error> {
+        Ok(chained.map(|inner| ReadTimeoutTransport {
+            inner,
+            read_timeout: self.read_timeout,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct ReadTimeoutTransport {
+    inner: Box<dyn Transport>,
+    read_timeout: Duration,
+}
+
+impl Transport for ReadTimeoutTransport {
+    fn buffers(&mut self) -> &mut dyn Buffers {
+        self.inner.buffers()
+    }
+
+    fn transmit_output(&mut self, amount: usize, timeout: NextTimeout) -> Result<(), ureq::Error> {
+        self.inner.transmit_output(amount, timeout)
+    }
+
+    fn await_input(&mut self, timeout: NextTimeout) -> Result<bool, ureq::Error> {
+        let timeout = if *timeout.after > self.read_timeout {
+            NextTimeout {
+                after: self.read_timeout.into(),
+                reason: ureq::Timeout::RecvBody,
+            }
+        } else {
+            timeout
+        };
+        self.inner.await_input(timeout)
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.inner.is_open()
+    }
+
+    fn is_tls(&self) -> bool {
+        self.inner.is_tls()
+    }
+}
+
+/// The platform's trusted certificates together with the certificates of the CA bundle
+fn load_root_certs(ca_bundle: &Path) -> ToolToolResult<RootCerts> {
+    let pem = std::fs::read(ca_bundle)?;
+    let mut certs = vec![];
+    for item in ureq::tls::parse_pem(&pem) {
+        if let PemItem::Certificate(cert) = item? {
+            certs.push(cert);
+        }
+    }
+    if certs.is_empty() {
+        bail!("No certificates found");
+    }
+    debug!("Loaded {} certificates from {ca_bundle:?}", certs.len());
+    let native_certs = rustls_native_certs::load_native_certs();
+    for error in native_certs.errors {
+        warn!("Failed to load platform certificate: {error}");
+    }
+    certs.extend(
+        native_certs
+            .certs
+            .iter()
+            .map(|cert| Certificate::from_der(cert.as_ref()).to_owned()),
+    );
+    Ok(RootCerts::new_with_certs(&certs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Credentials;
+    use crate::download::{Downloader, RetryPolicy};
+    use httpmock::Method::GET;
+    use httpmock::MockServer;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+    use test_temp_dir::test_temp_dir;
+
+    fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn no_retries() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Minimal stand-in for an HTTP proxy, tunneling CONNECT requests to their target and
+    /// recording the targets
+    fn start_connect_proxy() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        let targets = Arc::new(Mutex::new(vec![]));
+        let recorded_targets = targets.clone();
+        std::thread::spawn(move || {
+            for client in listener.incoming() {
+                let client = client.unwrap();
+                let targets = recorded_targets.clone();
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(client.try_clone().unwrap());
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                    }
+                    let target = request_line
+                        .strip_prefix("CONNECT ")
+                        .and_then(|rest| rest.split_whitespace().next())
+                        .expect("CONNECT request")
+                        .to_string();
+                    targets.lock().unwrap().push(target.clone());
+                    let mut upstream = TcpStream::connect(&target).unwrap();
+                    let mut client = client;
+                    client
+                        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                        .unwrap();
+                    let mut upstream_writer = upstream.try_clone().unwrap();
+                    std::thread::spawn(move || std::io::copy(&mut reader, &mut upstream_writer));
+                    let _ = std::io::copy(&mut upstream, &mut client);
+                });
+            }
+        });
+        (proxy_url, targets)
+    }
+
+    #[test]
+    fn settings_from_env() -> ToolToolResult<()> {
+        let linux = DownloadPlatform::Linux;
+        assert_eq!(
+            NetworkSettings::from_env(&[], linux)?,
+            NetworkSettings::default()
+        );
+        let settings = NetworkSettings::from_env(
+            &env(&[
+                ("HTTPS_PROXY", "http://proxy.corp:3128"),
+                ("http_proxy", "http://lower.corp:3128"),
+                ("HTTP_PROXY", "http://upper.corp:3128"),
+                ("NO_PROXY", "localhost, .internal.corp,"),
+                (CA_BUNDLE_ENV_VAR, "/etc/ssl/corp.pem"),
+                (CONNECT_TIMEOUT_ENV_VAR, "5"),
+            ]),
+            linux,
+        )?;
+        assert_eq!(
+            settings,
+            NetworkSettings {
+                http_proxy: Some("http://lower.corp:3128".to_string()),
+                https_proxy: Some("http://proxy.corp:3128".to_string()),
+                no_proxy: vec!["localhost".to_string(), ".internal.corp".to_string()],
+                ca_bundle: Some(PathBuf::from("/etc/ssl/corp.pem")),
+                connect_timeout: Duration::from_secs(5),
+                read_timeout: Duration::from_secs(60),
+            }
+        );
+        let settings =
+            NetworkSettings::from_env(&env(&[("ALL_PROXY", "socks5://proxy:1080")]), linux)?;
+        assert_eq!(settings.http_proxy.as_deref(), Some("socks5://proxy:1080"));
+        assert_eq!(settings.https_proxy.as_deref(), Some("socks5://proxy:1080"));
+        let error =
+            NetworkSettings::from_env(&env(&[(READ_TIMEOUT_ENV_VAR, "1m")]), linux).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid value '1m' of TOOL_TOOL_READ_TIMEOUT, expected a number of seconds"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn settings_from_user_config() -> ToolToolResult<()> {
+        let temp_dir = test_temp_dir!();
+        let config_path = temp_dir.used_by(|path| {
+            let config_path = path.join("config.kdl");
+            std::fs::write(
+                &config_path,
+                "network {\n    ca_bundle \"corp.pem\"\n    read_timeout 120\n}\n",
+            )
+            .unwrap();
+            config_path
+        });
+        let config_path = config_path.to_str().unwrap();
+        let settings = NetworkSettings::from_env(
+            &env(&[("TOOL_TOOL_USER_CONFIG", config_path)]),
+            DownloadPlatform::Linux,
+        )?;
+        assert_eq!(
+            settings.ca_bundle,
+            Some(Path::new(config_path).with_file_name("corp.pem"))
+        );
+        assert_eq!(settings.read_timeout, Duration::from_secs(120));
+        assert_eq!(settings.connect_timeout, Duration::from_secs(30));
+        // The environment takes precedence
+        let settings = NetworkSettings::from_env(
+            &env(&[
+                ("TOOL_TOOL_USER_CONFIG", config_path),
+                (CA_BUNDLE_ENV_VAR, "/other.pem"),
+                (READ_TIMEOUT_ENV_VAR, "10"),
+            ]),
+            DownloadPlatform::Linux,
+        )?;
+        assert_eq!(settings.ca_bundle, Some(PathBuf::from("/other.pem")));
+        assert_eq!(settings.read_timeout, Duration::from_secs(10));
+        Ok(())
+    }
+
+    #[test]
+    fn proxy_for() {
+        let settings = NetworkSettings {
+            http_proxy: Some("http://http-proxy:3128".to_string()),
+            https_proxy: Some("http://https-proxy:3128".to_string()),
+            no_proxy: vec![
+                "localhost".to_string(),
+                ".internal.corp".to_string(),
+                "mirror.corp:8443".to_string(),
+                "registry.corp:443".to_string(),
+                "::1".to_string(),
+            ],
+            ..NetworkSettings::default()
+        };
+        let proxy_for = |url| settings.proxy_for(url);
+        assert_eq!(
+            proxy_for("https://github.com/a/b.tar.gz"),
+            Some("http://https-proxy:3128")
+        );
+        assert_eq!(
+            proxy_for("http://example.com/a"),
+            Some("http://http-proxy:3128")
+        );
+        assert_eq!(proxy_for("http://localhost:8080/a"), None);
+        assert_eq!(proxy_for("https://artifacts.internal.corp/a"), None);
+        assert_eq!(proxy_for("https://internal.corp/a"), None);
+        assert_eq!(
+            proxy_for("https://notinternal.corp/a"),
+            Some("http://https-proxy:3128")
+        );
+        assert_eq!(proxy_for("https://mirror.corp:8443/a"), None);
+        assert_eq!(
+            proxy_for("https://mirror.corp/a"),
+            Some("http://https-proxy:3128")
+        );
+        // Default ports match entries with an explicit port
+        assert_eq!(proxy_for("https://registry.corp/a"), None);
+        assert_eq!(
+            proxy_for("http://registry.corp/a"),
+            Some("http://http-proxy:3128")
+        );
+        assert_eq!(proxy_for("http://[::1]:8080/a"), None);
+        assert_eq!(proxy_for("ftp://example.com/a"), None);
+        let bypass_all = NetworkSettings {
+            no_proxy: vec!["*".to_string()],
+            ..settings.clone()
+        };
+        assert_eq!(bypass_all.proxy_for("https://github.com/a"), None);
+    }
+
+    #[test]
+    fn download_through_proxy() {
+        let temp_dir = test_temp_dir!();
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/download_url");
+            then.status(200).body("proxied content");
+        });
+        let (proxy_url, targets) = start_connect_proxy();
+        let settings = NetworkSettings {
+            http_proxy: Some(proxy_url),
+            ..NetworkSettings::default()
+        };
+        let local_path = temp_dir.used_by(|path| path.join("file_download"));
+        Downloader::with_settings(no_retries(), &settings, Credentials::default())
+            .unwrap()
+            .download(
+                &server.url("/download_url"),
+                &[],
+                local_path.as_path(),
+                &mut |_| {},
+            )
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(local_path.as_path()).unwrap(),
+            "proxied content"
+        );
+        assert_eq!(*targets.lock().unwrap(), [server.address().to_string()]);
+    }
+
+    #[test]
+    fn download_bypasses_proxy() {
+        let temp_dir = test_temp_dir!();
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/download_url");
+            then.status(200).body("direct content");
+        });
+        let (proxy_url, targets) = start_connect_proxy();
+        let settings = NetworkSettings {
+            http_proxy: Some(proxy_url),
+            no_proxy: vec!["127.0.0.1".to_string()],
+            ..NetworkSettings::default()
+        };
+        let local_path = temp_dir.used_by(|path| path.join("file_download"));
+        Downloader::with_settings(no_retries(), &settings, Credentials::default())
+            .unwrap()
+            .download(
+                &server.url("/download_url"),
+                &[],
+                local_path.as_path(),
+                &mut |_| {},
+            )
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(local_path.as_path()).unwrap(),
+            "direct content"
+        );
+        assert!(targets.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_timeout() {
+        let temp_dir = test_temp_dir!();
+        // Accepts connections, but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/download", listener.local_addr().unwrap());
+        let settings = NetworkSettings {
+            read_timeout: Duration::from_millis(200),
+            ..NetworkSettings::default()
+        };
+        let local_path = temp_dir.used_by(|path| path.join("file_download"));
+        let start = Instant::now();
+        let error = Downloader::with_settings(no_retries(), &settings, Credentials::default())
+            .unwrap()
+            .download(&url, &[], local_path.as_path(), &mut |_| {})
+            .unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(format!("{error:#}").contains("timeout"), "{error:#}");
+        drop(listener);
+    }
+
+    #[test]
+    fn invalid_ca_bundle() {
+        let temp_dir = test_temp_dir!();
+        let ca_bundle = temp_dir.used_by(|path| {
+            let ca_bundle = path.join("empty.pem");
+            std::fs::write(&ca_bundle, "no certificates").unwrap();
+            ca_bundle
+        });
+        let settings = NetworkSettings {
+            ca_bundle: Some(ca_bundle.to_path_buf()),
+            ..NetworkSettings::default()
+        };
+        let error = settings.agents().err().expect("Expected error");
+        assert!(
+            format!("{error:#}").ends_with("empty.pem\": No certificates found"),
+            "{error:#}"
+        );
+    }
+}
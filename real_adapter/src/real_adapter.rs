@@ -1,17 +1,20 @@
 use crate::download;
 use rand::Rng;
 use rand::distr::Alphanumeric;
-use std::cell::RefCell;
 use std::env;
 use std::fmt::Debug;
 use std::fs::{File, TryLockError};
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
 use std::process::Command;
-use std::time::{Duration, Instant};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tool_tool_base::logging::debug;
 use tool_tool_base::result::{Context, ToolToolResult, bail, err};
-use tool_tool_logic::adapter::{Adapter, ExecutionRequest, ReadSeek};
-use tool_tool_logic::configuration::platform::DownloadPlatform;
+use tool_tool_logic::adapter::{
+    Adapter, DownloadProgress, ExecutionRequest, FileMetadata, ReadSeek,
+};
+use tool_tool_logic::configuration::platform::{Architecture, DownloadPlatform, Libc};
 use tool_tool_logic::types::{EnvPair, FilePath};
 
 pub struct RealAdapter {
@@ -19,7 +22,7 @@ pub struct RealAdapter {
     downloader: download::Downloader,
     reference_instant: Instant,
     lockfile_path: PathBuf,
-    lockfile: RefCell<Option<File>>,
+    lockfile: Mutex<Option<File>>,
 }
 
 impl RealAdapter {
@@ -34,11 +37,21 @@ impl RealAdapter {
     }
 
     fn resolve_path(&self, path: &FilePath) -> ToolToolResult<PathBuf> {
+        // Absolute paths point outside of the project, e.g. to the shared cache
+        if std::path::Path::new(path.as_str()).is_absolute() {
+            return Ok(PathBuf::from(path.as_str()));
+        }
         Ok(path.to_path(&self.base_path))
     }
 
+    fn lockfile(&self) -> MutexGuard<'_, Option<File>> {
+        self.lockfile
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
     fn assert_locked(&self) -> ToolToolResult<()> {
-        if self.lockfile.borrow().is_none() {
+        if self.lockfile().is_none() {
             bail!("Inconsistent internal state: lockfile is not locked")
         }
         Ok(())
@@ -97,20 +110,52 @@ impl Adapter for RealAdapter {
         std::process::exit(exit_code);
     }
 
-    fn download_file(&self, url: &str, destination_path: &FilePath) -> ToolToolResult<()> {
+    fn download_file(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        destination_path: &FilePath,
+        progress: &mut dyn FnMut(&DownloadProgress),
+    ) -> ToolToolResult<()> {
         self.assert_locked()?;
-        self.downloader
-            .download(url, &self.resolve_path(destination_path)?)?;
+        self.downloader.download(
+            url,
+            headers,
+            &self.resolve_path(destination_path)?,
+            progress,
+        )?;
         Ok(())
     }
 
+    fn is_terminal(&self) -> bool {
+        std::io::stderr().is_terminal()
+    }
+
+    fn print_progress(&self, line: &str) {
+        // Return to the start of the line and clear the rest of it
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r{line}\x1b[K");
+        let _ = stderr.flush();
+    }
+
     fn get_platform(&self) -> DownloadPlatform {
-        #[cfg(target_os = "macos")]
-        return DownloadPlatform::Darwin;
+        host_platform()
+    }
+
+    fn get_architecture(&self) -> Architecture {
+        #[cfg(target_arch = "x86_64")]
+        return Architecture::X86_64;
+        #[cfg(target_arch = "aarch64")]
+        return Architecture::Aarch64;
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        compile_error!("tool-tool only supports the x86_64 and aarch64 architectures");
+    }
+
+    fn get_libc(&self) -> Option<Libc> {
         #[cfg(target_os = "linux")]
-        return DownloadPlatform::Linux;
-        #[cfg(target_os = "windows")]
-        return DownloadPlatform::Windows;
+        return Some(detect_libc());
+        #[cfg(not(target_os = "linux"))]
+        return None;
     }
 
     fn execute(&self, request: ExecutionRequest) -> ToolToolResult<i32> {
@@ -139,8 +184,14 @@ impl Adapter for RealAdapter {
         Ok(self.reference_instant.elapsed())
     }
 
+    fn system_time(&self) -> ToolToolResult<Duration> {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .with_context(|| "System time is before the unix epoch")
+    }
+
     fn try_lock(&self) -> ToolToolResult<bool> {
-        if self.lockfile.borrow().is_some() {
+        if self.lockfile().is_some() {
             bail!("Lock already held");
         }
         let parent_path = self.lockfile_path.parent().ok_or_else(|| {
@@ -154,7 +205,7 @@ impl Adapter for RealAdapter {
         let lock_result = lockfile.try_lock();
         match lock_result {
             Ok(_) => {
-                *self.lockfile.borrow_mut() = Some(lockfile);
+                *self.lockfile() = Some(lockfile);
                 Ok(true)
             }
             Err(TryLockError::WouldBlock) => Ok(false),
@@ -168,7 +219,7 @@ impl Adapter for RealAdapter {
     }
 
     fn unlock(&self) -> ToolToolResult<()> {
-        let Some(lockfile) = self.lockfile.take() else {
+        let Some(lockfile) = self.lockfile().take() else {
             bail!("Lock not held");
         };
         lockfile.unlock()?;
@@ -192,8 +243,210 @@ impl Adapter for RealAdapter {
                 std::fs::Permissions::from_mode(0o755),
             )?;
         }
+        #[cfg(target_os = "macos")]
+        remove_quarantine(&self.resolve_path(_path)?)?;
         Ok(())
     }
+
+    fn create_symlink(&self, path: &FilePath, target: &str) -> ToolToolResult<()> {
+        self.assert_locked()?;
+        let physical_path = self.resolve_path(path)?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(target, &physical_path)
+            .with_context(|| format!("Failed to create symbolic link {physical_path:?}"))?;
+        #[cfg(windows)]
+        {
+            let target = target.replace('/', "\\");
+            let target_path = physical_path
+                .parent()
+                .map(|parent| parent.join(&target))
+                .unwrap_or_else(|| PathBuf::from(&target));
+            let result = if target_path.is_dir() {
+                std::os::windows::fs::symlink_dir(&target, &physical_path)
+            } else {
+                std::os::windows::fs::symlink_file(&target, &physical_path)
+            };
+            // Creating symbolic links requires special privileges on windows, fall back to a copy
+            if let Err(error) = result {
+                if !target_path.is_file() {
+                    return Err(error).with_context(|| {
+                        format!("Failed to create symbolic link {physical_path:?}")
+                    });
+                }
+                std::fs::copy(&target_path, &physical_path).with_context(|| {
+                    format!("Failed to copy {target_path:?} to {physical_path:?}")
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn list_directory(&self, path: &FilePath) -> ToolToolResult<Vec<String>> {
+        self.assert_locked()?;
+        let physical_path = self.resolve_path(path)?;
+        if !physical_path.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut names = vec![];
+        for entry in std::fs::read_dir(&physical_path)
+            .with_context(|| format!("Failed to list directory {physical_path:?}"))?
+        {
+            names.push(entry?.file_name().to_string_lossy().to_string());
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn file_metadata(&self, path: &FilePath) -> ToolToolResult<FileMetadata> {
+        self.assert_locked()?;
+        let physical_path = self.resolve_path(path)?;
+        let metadata = std::fs::metadata(&physical_path)
+            .with_context(|| format!("Failed to read metadata of {physical_path:?}"))?;
+        Ok(FileMetadata {
+            is_directory: metadata.is_dir(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            modified: metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
+        })
+    }
+
+    fn link_file(&self, from: &FilePath, to: &FilePath) -> ToolToolResult<()> {
+        self.assert_locked()?;
+        let from_path = self.resolve_path(from)?;
+        let to_path = self.resolve_path(to)?;
+        if let Err(error) = std::fs::hard_link(&from_path, &to_path) {
+            debug!("Failed to link {from_path:?} to {to_path:?}, copying instead: {error}");
+            // std::fs::copy uses reflinks where the file system supports them
+            std::fs::copy(&from_path, &to_path)
+                .with_context(|| format!("Failed to copy {from_path:?} to {to_path:?}"))?;
+        }
+        Ok(())
+    }
+
+    fn delete_file(&self, path: &FilePath) -> ToolToolResult<()> {
+        self.assert_locked()?;
+        let physical_path = self.resolve_path(path)?;
+        std::fs::remove_file(&physical_path)
+            .with_context(|| format!("Failed to delete file {physical_path:?}"))?;
+        Ok(())
+    }
+
+    fn rename(&self, from: &FilePath, to: &FilePath) -> ToolToolResult<()> {
+        self.assert_locked()?;
+        let from_path = self.resolve_path(from)?;
+        let to_path = self.resolve_path(to)?;
+        std::fs::rename(&from_path, &to_path)
+            .with_context(|| format!("Failed to rename {from_path:?} to {to_path:?}"))?;
+        Ok(())
+    }
+
+    fn list_files(&self, path: &FilePath) -> ToolToolResult<Vec<FilePath>> {
+        self.assert_locked()?;
+        let physical_path = self.resolve_path(path)?;
+        let mut files = vec![];
+        if physical_path.is_dir() {
+            collect_files(&physical_path, &physical_path, &mut files)?;
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn set_file_mode(&self, _path: &FilePath, _mode: u32) -> ToolToolResult<()> {
+        self.assert_locked()?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(
+                self.resolve_path(_path)?,
+                std::fs::Permissions::from_mode(_mode),
+            )?;
+        }
+        #[cfg(target_os = "macos")]
+        if _mode & 0o111 != 0 {
+            remove_quarantine(&self.resolve_path(_path)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the regular files below `directory`, with paths relative to `base`
+fn collect_files(
+    base: &std::path::Path,
+    directory: &std::path::Path,
+    files: &mut Vec<FilePath>,
+) -> ToolToolResult<()> {
+    let entries = std::fs::read_dir(directory)
+        .with_context(|| format!("Failed to list directory {directory:?}"))?;
+    for entry in entries {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        if file_type.is_dir() {
+            collect_files(base, &path, files)?;
+        } else if file_type.is_file() {
+            let relative_path = path.strip_prefix(base)?;
+            files.push(FilePath::from_path(relative_path).with_context(|| {
+                format!("Failed to convert {relative_path:?} to a relative path")
+            })?);
+        }
+    }
+    Ok(())
+}
+
+/// The operating system tool-tool was built for
+pub(crate) fn host_platform() -> DownloadPlatform {
+    #[cfg(target_os = "macos")]
+    return DownloadPlatform::MacOS;
+    #[cfg(target_os = "linux")]
+    return DownloadPlatform::Linux;
+    #[cfg(target_os = "windows")]
+    return DownloadPlatform::Windows;
+    #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+    compile_error!("tool-tool only supports linux, macos and windows");
+}
+
+/// Removes the quarantine attribute, so Gatekeeper does not block the downloaded binary
+#[cfg(target_os = "macos")]
+fn remove_quarantine(path: &std::path::Path) -> ToolToolResult<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let result = unsafe {
+        libc::removexattr(
+            c_path.as_ptr(),
+            c"com.apple.quarantine".as_ptr(),
+            libc::XATTR_NOFOLLOW,
+        )
+    };
+    if result != 0 {
+        let error = std::io::Error::last_os_error();
+        if error.raw_os_error() != Some(libc::ENOATTR) {
+            return Err(error)
+                .with_context(|| format!("Failed to remove quarantine attribute from {path:?}"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn detect_libc() -> Libc {
+    let has_loader = |dir: &str, prefix: &str| {
+        std::fs::read_dir(dir).is_ok_and(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+        })
+    };
+    // glibc hosts may have the musl loader installed alongside (e.g. the debian musl package),
+    // so only hosts without a glibc loader are considered musl based
+    let has_glibc_loader = has_loader("/lib64", "ld-linux-") || has_loader("/lib", "ld-linux-");
+    if !has_glibc_loader && has_loader("/lib", "ld-musl-") {
+        Libc::Musl
+    } else {
+        Libc::Gnu
+    }
 }
 
 impl Debug for RealAdapter {
@@ -204,7 +457,7 @@ impl Debug for RealAdapter {
 
 impl Drop for RealAdapter {
     fn drop(&mut self) {
-        let lockfile = self.lockfile.take();
+        let lockfile = self.lockfile().take();
         if let Some(lockfile) = lockfile {
             lockfile.unlock().unwrap();
         }
@@ -310,7 +563,7 @@ mod tests {
         let context = setup();
         let file_path = "foo/bar/baz";
         let path = context.temp_dir.as_path_untracked().join(file_path);
-        std::fs::create_dir_all(&path.join("fizzbuzz")).unwrap();
+        std::fs::create_dir_all(path.join("fizzbuzz")).unwrap();
         context
             .adapter
             .delete_directory_all(&FilePath::from(file_path))
@@ -319,7 +572,11 @@ mod tests {
         let path = context.temp_dir.as_path_untracked().join(file_path);
         assert!(!std::path::PathBuf::from(&path).exists());
         assert!(
-            std::path::PathBuf::from(context.temp_dir.as_path_untracked().join("foo/bar")).exists()
+            context
+                .temp_dir
+                .as_path_untracked()
+                .join("foo/bar")
+                .exists()
         );
     }
 
@@ -329,4 +586,162 @@ mod tests {
         let random_string = context.adapter.random_string().unwrap();
         assert_eq!(random_string.len(), 16);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn create_symlink() {
+        let context = setup();
+        let base_path = context.temp_dir.as_path_untracked();
+        std::fs::create_dir_all(base_path.join("lib")).unwrap();
+        std::fs::write(base_path.join("lib/tool.js"), "tool").unwrap();
+        std::fs::create_dir_all(base_path.join("bin")).unwrap();
+        context
+            .adapter
+            .create_symlink(&FilePath::from("bin/tool"), "../lib/tool.js")
+            .unwrap();
+        let link_path = base_path.join("bin/tool");
+        assert!(link_path.is_symlink());
+        assert_eq!(
+            std::fs::read_link(&link_path).unwrap(),
+            PathBuf::from("../lib/tool.js")
+        );
+        assert_eq!(std::fs::read_to_string(&link_path).unwrap(), "tool");
+    }
+
+    #[test]
+    fn list_directory_and_metadata() {
+        let context = setup();
+        let base_path = context.temp_dir.as_path_untracked();
+        std::fs::create_dir_all(base_path.join("cache/tool-1.0")).unwrap();
+        std::fs::write(base_path.join("cache/lockfile"), "lock").unwrap();
+        let names = context
+            .adapter
+            .list_directory(&FilePath::from("cache"))
+            .unwrap();
+        assert_eq!(names, vec!["lockfile", "tool-1.0"]);
+        assert!(
+            context
+                .adapter
+                .list_directory(&FilePath::from("missing"))
+                .unwrap()
+                .is_empty()
+        );
+        let metadata = context
+            .adapter
+            .file_metadata(&FilePath::from("cache/lockfile"))
+            .unwrap();
+        assert!(!metadata.is_directory);
+        assert_eq!(metadata.size, 4);
+        assert!(metadata.modified > Duration::ZERO);
+        let metadata = context
+            .adapter
+            .file_metadata(&FilePath::from("cache/tool-1.0"))
+            .unwrap();
+        assert!(metadata.is_directory);
+    }
+
+    #[test]
+    fn link_and_delete_file() {
+        let context = setup();
+        let base_path = context.temp_dir.as_path_untracked();
+        std::fs::write(base_path.join("artifact"), "artifact").unwrap();
+        context
+            .adapter
+            .link_file(&FilePath::from("artifact"), &FilePath::from("linked"))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(base_path.join("linked")).unwrap(),
+            "artifact"
+        );
+        context
+            .adapter
+            .delete_file(&FilePath::from("artifact"))
+            .unwrap();
+        assert!(!base_path.join("artifact").exists());
+        assert!(base_path.join("linked").exists());
+    }
+
+    #[test]
+    fn absolute_paths() {
+        let context = setup();
+        let absolute_path = context.temp_dir.as_path_untracked().join("shared");
+        std::fs::write(&absolute_path, "shared").unwrap();
+        assert!(absolute_path.is_absolute());
+        let file_path = FilePath::from(absolute_path.to_str().unwrap());
+        assert!(context.adapter.file_exists(&file_path).unwrap());
+    }
+
+    #[test]
+    fn rename() {
+        let context = setup();
+        let base_path = context.temp_dir.as_path_untracked();
+        std::fs::create_dir_all(base_path.join("staging/bin")).unwrap();
+        std::fs::write(base_path.join("staging/bin/tool"), "tool").unwrap();
+        context
+            .adapter
+            .rename(&FilePath::from("staging"), &FilePath::from("tool"))
+            .unwrap();
+        assert!(!base_path.join("staging").exists());
+        assert_eq!(
+            std::fs::read_to_string(base_path.join("tool/bin/tool")).unwrap(),
+            "tool"
+        );
+    }
+
+    #[test]
+    fn list_files() {
+        let context = setup();
+        let base_path = context.temp_dir.as_path_untracked();
+        std::fs::create_dir_all(base_path.join("tool/bin")).unwrap();
+        std::fs::create_dir_all(base_path.join("tool/empty")).unwrap();
+        std::fs::write(base_path.join("tool/bin/tool"), "tool").unwrap();
+        std::fs::write(base_path.join("tool/README"), "readme").unwrap();
+        let files = context.adapter.list_files(&FilePath::from("tool")).unwrap();
+        assert_eq!(
+            files,
+            vec![FilePath::from("README"), FilePath::from("bin/tool")]
+        );
+        assert!(
+            context
+                .adapter
+                .list_files(&FilePath::from("missing"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn set_file_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        let context = setup();
+        let path = context.temp_dir.as_path_untracked().join("tool");
+        File::create(&path).unwrap();
+        context
+            .adapter
+            .set_file_mode(&FilePath::from("tool"), 0o750)
+            .unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+    }
+
+    #[test]
+    fn system_time_matches_modification_times() {
+        let context = setup();
+        let path = context.temp_dir.as_path_untracked().join("old");
+        let file = File::create(&path).unwrap();
+        let age = Duration::from_secs(2 * 60 * 60);
+        file.set_modified(SystemTime::now() - age).unwrap();
+        drop(file);
+        let modified = context
+            .adapter
+            .file_metadata(&FilePath::from("old"))
+            .unwrap()
+            .modified;
+        let elapsed = context.adapter.system_time().unwrap() - modified;
+        assert!(
+            elapsed >= age && elapsed < age + Duration::from_secs(60),
+            "{elapsed:?}"
+        );
+    }
 }